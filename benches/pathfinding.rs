@@ -0,0 +1,75 @@
+//! One frame of a late night crowd: every NPC chases or escapes the player, once with an A* per NPC
+//! and once sampling the shared flow fields. Run with `cargo bench --bench pathfinding`.
+
+use std::{hint::black_box, time::{Duration, Instant}};
+
+use bevy::{math::ivec2, prelude::*};
+use vampillage::{
+    map::{plugin::TrespassableCells, tilemap::TransformToGrid},
+    npc::{components::NpcState, flowfield::{FlowField, FlowFields}, pathfinder::pathfinder},
+};
+
+const SIZE: i32 = 128;
+const NPCS: usize = 200;
+const FRAMES: u32 = 20;
+
+/// Open ground crossed by walls with a few gaps, like the village's fences
+fn village() -> TrespassableCells {
+    let mut cells = vec![vec![true; SIZE as usize]; SIZE as usize];
+    for x in (8..SIZE).step_by(16) {
+        for y in 0..SIZE {
+            cells[x as usize][y as usize] = y % 32 == 5;
+        }
+    }
+    TrespassableCells {cells: cells.into(), ready: true, ..default()}
+}
+
+/// Spread over the map, the same ones every run
+fn npc_cells(trespassable: &TrespassableCells) -> Vec<IVec2> {
+    let mut seed: u32 = 12345;
+    let mut next = || {
+        seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (seed >> 8) as i32 % SIZE
+    };
+    let mut npcs = Vec::with_capacity(NPCS);
+    while npcs.len() < NPCS {
+        let pos = ivec2(next(), next());
+        if trespassable.is_trespassable(&pos) {
+            npcs.push(pos);
+        }
+    }
+    npcs
+}
+
+fn measure(name: &str, mut frame: impl FnMut() -> usize) -> Duration {
+    let start = Instant::now();
+    let mut found = 0;
+    for _ in 0..FRAMES {
+        found = black_box(frame());
+    }
+    let per_frame = start.elapsed() / FRAMES;
+    println!("{name:<12} {per_frame:>12.2?} per frame, {found}/{NPCS} paths");
+    per_frame
+}
+
+fn main() {
+    let trespassable = village();
+    let transformer = TransformToGrid {ready: true, ..default()};
+    let npcs = npc_cells(&trespassable);
+    let player = ivec2(SIZE / 2 + 3, SIZE / 2);
+
+    for state in [NpcState::Chase, NpcState::Escape] {
+        println!("{state:?}, {NPCS} NPCs on a {SIZE}x{SIZE} grid");
+        let astar = measure("A*", || {
+            npcs.iter().filter(|npc| pathfinder(**npc, player, &trespassable, &transformer, state, false).is_some()).count()
+        });
+        let flow = measure("flow field", || {
+            // the worst case, the player enters a new cell every frame
+            let toward = FlowField::toward(&trespassable, player);
+            let away = FlowField::away(&toward, &trespassable);
+            let fields = FlowFields {goal: Some(player), toward, away};
+            npcs.iter().filter(|npc| fields.path(state, &trespassable, **npc, false).is_some()).count()
+        });
+        println!("{:.1}x faster\n", astar.as_secs_f64() / flow.as_secs_f64());
+    }
+}
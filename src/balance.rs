@@ -0,0 +1,515 @@
+use bevy::{asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext}, prelude::*};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{map::terrain::TerrainTable, npc::schedule::Activity};
+
+pub const BALANCE_PATH: &str = "game.balance.ron";
+
+pub struct BalancePlugin;
+
+impl Plugin for BalancePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_asset::<GameBalance>()
+        .register_asset_loader(GameBalanceLoader)
+        .insert_resource(GameBalance::default())
+        .add_systems(PreStartup, load_balance)
+        .add_systems(PreUpdate, (apply_balance, report_balance_errors));
+    }
+}
+
+/// Every tuning number of a run. Loaded from `assets/game.balance.ron` and
+/// hot-reloaded when the file changes, systems read the copy kept as a resource.
+/// Missing fields fall back to the defaults below.
+#[derive(Asset, Resource, TypePath, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct GameBalance {
+    pub player: PlayerBalance,
+    pub damage: DamageBalance,
+    pub rewards: RewardBalance,
+    pub civilian: CivilianBalance,
+    pub hunter: HunterBalance,
+    pub steering: SteeringBalance,
+    pub perception: PerceptionBalance,
+    pub search: SearchBalance,
+    pub squad: SquadBalance,
+    pub alert: AlertBalance,
+    pub schedule: ScheduleBalance,
+    pub daycycle: DayCycleBalance,
+    pub terrain: TerrainTable,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PlayerBalance {
+    pub hp: f32,
+    pub max_hp: f32,
+    pub max_xp: f32,
+    pub max_xp_growth: f32,
+    pub max_speed: f32,
+    pub accumulation_gain: f32,
+    pub phys_res: f32,
+    pub dash_cd: f32,
+    pub dash_tick: f32,
+    pub dash_duration: f32,
+    pub hp_gain: f32,
+    pub xp_gain: f32,
+    pub hunger_rate: f32,
+    pub respawn_delay: f32,
+}
+
+impl Default for PlayerBalance {
+    fn default() -> Self {
+        PlayerBalance {
+            hp: 80.,
+            max_hp: 80.,
+            max_xp: 100.,
+            max_xp_growth: 1.2,
+            max_speed: 60.,
+            accumulation_gain: 500.,
+            phys_res: 0.2,
+            dash_cd: 1.5,
+            dash_tick: 1.,
+            dash_duration: 0.35,
+            hp_gain: 5.,
+            xp_gain: 10.,
+            hunger_rate: 2.,
+            respawn_delay: 5.,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DamageBalance {
+    /// Fraction of max hp taken by a thrown weapon
+    pub projectile: f32,
+    /// Fraction of max hp taken by a civilian hit
+    pub civilian: f32,
+    /// Flat damage of touching a hunter during the day
+    pub hunter: f32,
+}
+
+impl Default for DamageBalance {
+    fn default() -> Self {
+        DamageBalance {
+            projectile: 0.1,
+            civilian: 0.05,
+            hunter: 15.,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RewardBalance {
+    pub civilian_score: f32,
+    pub hunter_score: f32,
+    pub hunter_xp_multiplier: f32,
+}
+
+impl Default for RewardBalance {
+    fn default() -> Self {
+        RewardBalance {
+            civilian_score: 100.,
+            hunter_score: 500.,
+            hunter_xp_multiplier: 3.,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CivilianBalance {
+    /// Sight range in daylight
+    pub spot_dist: f32,
+    /// Width of the view cone, in degrees
+    pub fov: f32,
+    /// Multiplies how far noises carry
+    pub hearing: f32,
+    /// Further than this civilians calm down once they lose their path
+    pub threshold: f32,
+    pub max_speed: f32,
+    pub accel: f32,
+    /// How close to a waypoint counts as reaching it
+    pub arrival_radius: f32,
+    pub spawn_chance: f64,
+    pub max_count: usize,
+}
+
+impl Default for CivilianBalance {
+    fn default() -> Self {
+        CivilianBalance {
+            spot_dist: 100.,
+            fov: 140.,
+            hearing: 1.,
+            threshold: 100.,
+            max_speed: 40.,
+            accel: 350.,
+            arrival_radius: 4.,
+            spawn_chance: 0.15,
+            max_count: 200,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct HunterBalance {
+    /// Sight range in daylight
+    pub spot_dist: f32,
+    /// Width of the view cone, in degrees
+    pub fov: f32,
+    /// Multiplies how far noises carry
+    pub hearing: f32,
+    /// Closer than this hunters back off
+    pub threshold: f32,
+    /// Further than this hunters walk closer before throwing
+    pub upp_threshold: f32,
+    pub max_speed: f32,
+    pub accel: f32,
+    pub throw_cd: f32,
+    pub projectile_speed: f32,
+    /// How close to a waypoint counts as reaching it
+    pub arrival_radius: f32,
+    pub spawn_chance: f64,
+    pub max_count: usize,
+}
+
+impl Default for HunterBalance {
+    fn default() -> Self {
+        HunterBalance {
+            spot_dist: 200.,
+            fov: 110.,
+            hearing: 1.5,
+            threshold: 100.,
+            upp_threshold: 200.,
+            max_speed: 50.,
+            accel: 450.,
+            throw_cd: 0.5,
+            projectile_speed: 150.,
+            arrival_radius: 4.,
+            spawn_chance: 0.15,
+            max_count: 200,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SteeringBalance {
+    /// NPCs closer than this push each other apart
+    pub radius: f32,
+    /// Closest two NPCs may pass each other
+    pub body_radius: f32,
+    pub separation: f32,
+    pub avoidance: f32,
+    /// How far ahead, in seconds, collisions are avoided
+    pub horizon: f32,
+    /// How fast standing NPCs make room
+    pub idle_speed: f32,
+}
+
+impl Default for SteeringBalance {
+    fn default() -> Self {
+        SteeringBalance {
+            radius: 20.,
+            body_radius: 5.,
+            separation: 1.5,
+            avoidance: 1.,
+            horizon: 1.,
+            idle_speed: 15.,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct PerceptionBalance {
+    /// Fraction of the sight range left at full night
+    pub night_sight: f32,
+    /// Closer than this NPCs notice the player behind their back
+    pub feel_dist: f32,
+    /// How far an NPC that spots the player is heard by the others
+    pub alert_radius: f32,
+    pub dash_noise: f32,
+    pub kill_noise: f32,
+    pub hit_noise: f32,
+}
+
+impl Default for PerceptionBalance {
+    fn default() -> Self {
+        PerceptionBalance {
+            night_sight: 0.5,
+            feel_dist: 20.,
+            alert_radius: 120.,
+            dash_noise: 60.,
+            kill_noise: 150.,
+            hit_noise: 80.,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SearchBalance {
+    /// How long hunters search before giving up
+    pub duration: f32,
+    /// How far ahead, in seconds, the player's last velocity is followed
+    pub lead: f32,
+    /// How far from where the player vanished hiding spots are checked, in cells
+    pub radius: i32,
+    pub max_points: usize,
+    /// How long hunters look around at each point
+    pub look_time: f32,
+    /// Hunters searching spots closer than this split the points between them
+    pub share_radius: f32,
+}
+
+impl Default for SearchBalance {
+    fn default() -> Self {
+        SearchBalance {
+            duration: 15.,
+            lead: 1.,
+            radius: 6,
+            max_points: 6,
+            look_time: 2.,
+            share_radius: 150.,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SquadBalance {
+    /// Hunters after the player closer than this to each other form a squad
+    pub radius: f32,
+    /// How far from the player flankers and blockers stand
+    pub ring: f32,
+    /// How far from its spot, in cells, a flanker may stand to see the player
+    pub reach: i32,
+    /// The same for a blocker, it stays right in the way
+    pub block_reach: i32,
+    /// The throwers' cooldown as a fraction of the hunters' `throw_cd`
+    pub thrower_cd: f32,
+    /// Squads fall back this many seconds before dawn
+    pub retreat_before: f32,
+    /// How far from the player squads fall back, in cells
+    pub retreat_dist: i32,
+}
+
+impl Default for SquadBalance {
+    fn default() -> Self {
+        SquadBalance {
+            radius: 150.,
+            ring: 150.,
+            reach: 3,
+            block_reach: 1,
+            thrower_cd: 0.7,
+            retreat_before: 3.,
+            retreat_dist: 15,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AlertBalance {
+    /// Alert added by every reported kill
+    pub per_report: f32,
+    pub max: f32,
+    /// Alert lost every day without a report
+    pub decay: f32,
+    /// How close witnesses get to a hunter or a campfire to report
+    pub report_dist: f32,
+    /// Hunter spawn chance added per alert level, as a fraction of the base chance
+    pub hunter_spawn: f32,
+    /// Chance a new civilian is armed per alert level
+    pub armed_per_level: f32,
+    /// From this level on NPCs wander in groups
+    pub group_level: f32,
+    /// How far wandering NPCs look for company
+    pub group_radius: f32,
+}
+
+impl Default for AlertBalance {
+    fn default() -> Self {
+        AlertBalance {
+            per_report: 1.,
+            max: 5.,
+            decay: 1.,
+            report_dist: 24.,
+            hunter_spawn: 0.5,
+            armed_per_level: 0.1,
+            group_level: 2.,
+            group_radius: 150.,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ScheduleBalance {
+    /// What civilians do from each time of the day on. The time is the one of `get_local_time_f`,
+    /// dusk is 0 and dawn 0.5.
+    pub activities: Vec<(f32, Activity)>,
+    /// How far, in cells, civilians stroll around their destination
+    pub stray: i32,
+}
+
+impl Default for ScheduleBalance {
+    fn default() -> Self {
+        ScheduleBalance {
+            activities: vec![
+                (0., Activity::Home),
+                (0.5, Activity::Work),
+                (0.75, Activity::Wander),
+                (0.88, Activity::Gather),
+            ],
+            stray: 3,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DayCycleBalance {
+    pub day_duration: f32,
+    pub translation_duration: f32,
+}
+
+impl Default for DayCycleBalance {
+    fn default() -> Self {
+        DayCycleBalance {
+            day_duration: 15.,
+            translation_duration: 1.,
+        }
+    }
+}
+
+impl DayCycleBalance {
+    /// Length of a full day + night, including both translations
+    pub fn cycle_duration(&self) -> f32 {
+        self.translation_duration * 2. + self.day_duration * 2.
+    }
+}
+
+impl GameBalance {
+    /// Every value the game would panic or break on, the file parses fine but can't be played
+    pub fn validate(&self) -> Result<(), InvalidBalance> {
+        let mut errors = Vec::new();
+        chance(&mut errors, "civilian.spawn_chance", self.civilian.spawn_chance);
+        chance(&mut errors, "hunter.spawn_chance", self.hunter.spawn_chance);
+        positive(&mut errors, "daycycle.day_duration", self.daycycle.day_duration);
+        positive(&mut errors, "daycycle.translation_duration", self.daycycle.translation_duration);
+        positive(&mut errors, "player.dash_duration", self.player.dash_duration);
+        positive(&mut errors, "hunter.throw_cd", self.hunter.throw_cd);
+        positive(&mut errors, "squad.thrower_cd", self.squad.thrower_cd);
+        positive(&mut errors, "steering.radius", self.steering.radius);
+        positive(&mut errors, "steering.horizon", self.steering.horizon);
+        seconds(&mut errors, "player.dash_cd", self.player.dash_cd);
+        seconds(&mut errors, "player.dash_tick", self.player.dash_tick);
+        seconds(&mut errors, "player.respawn_delay", self.player.respawn_delay);
+        seconds(&mut errors, "search.duration", self.search.duration);
+        seconds(&mut errors, "search.look_time", self.search.look_time);
+        if errors.is_empty() {Ok(())} else {Err(InvalidBalance(errors))}
+    }
+}
+
+fn chance(errors: &mut Vec<String>, field: &str, value: f64) {
+    if !(0. ..=1.).contains(&value) {
+        errors.push(format!("{field} must be between 0 and 1, got {value}"));
+    }
+}
+
+fn positive(errors: &mut Vec<String>, field: &str, value: f32) {
+    if !(value.is_finite() && value > 0.) {
+        errors.push(format!("{field} must be above 0, got {value}"));
+    }
+}
+
+fn seconds(errors: &mut Vec<String>, field: &str, value: f32) {
+    if !(value.is_finite() && value >= 0.) {
+        errors.push(format!("{field} can't be negative, got {value}"));
+    }
+}
+
+/// The fields [`GameBalance::validate`] refused
+#[derive(Debug, Error)]
+#[error("{}", .0.join(", "))]
+pub struct InvalidBalance(pub Vec<String>);
+
+#[derive(Resource)]
+pub struct BalanceHandle(pub Handle<GameBalance>);
+
+#[derive(Default)]
+pub struct GameBalanceLoader;
+
+#[derive(Debug, Error)]
+pub enum GameBalanceLoaderError {
+    #[error("could not read balance file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid balance file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("invalid balance values: {0}")]
+    Invalid(#[from] InvalidBalance),
+}
+
+impl AssetLoader for GameBalanceLoader {
+    type Asset = GameBalance;
+    type Settings = ();
+    type Error = GameBalanceLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let balance = ron::de::from_bytes::<GameBalance>(&bytes)?;
+        balance.validate()?;
+        Ok(balance)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["balance.ron"]
+    }
+}
+
+fn load_balance(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands.insert_resource(BalanceHandle(asset_server.load(BALANCE_PATH)));
+}
+
+fn apply_balance(
+    mut events: EventReader<AssetEvent<GameBalance>>,
+    handle: Res<BalanceHandle>,
+    assets: Res<Assets<GameBalance>>,
+    mut balance: ResMut<GameBalance>,
+) {
+    for event in events.read() {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } if *id == handle.0.id() => {
+                if let Some(loaded) = assets.get(*id) {
+                    *balance = loaded.clone();
+                    info!("Game balance applied!");
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn report_balance_errors(
+    mut failed: EventReader<AssetLoadFailedEvent<GameBalance>>,
+) {
+    for event in failed.read() {
+        // the resource is only overwritten by a successful load, so the game goes on with the last good values
+        error!("Failed to load {}: {}. Keeping the previous balance.", event.path, event.error);
+    }
+}
@@ -1,7 +1,7 @@
 use bevy::{math::{uvec2, vec3}, prelude::*, render::view::visibility};
 use rand::Rng;
 
-use crate::core::functions::TextureAtlasLayoutHandles;
+use crate::{core::functions::TextureAtlasLayoutHandles, screen::Screen};
 
 
 
@@ -50,7 +50,8 @@ pub fn spawn_player_animation_bundle(commands: &mut Commands, asset_server: &Res
         },
         Name::new("Player"),
         TransformBundle::default(),
-        VisibilityBundle::default()
+        VisibilityBundle::default(),
+        StateScoped(Screen::Playing),
         )).with_children(|commands|{
             commands.spawn((
                 Name::new("Body"),
@@ -95,7 +96,8 @@ pub fn spawn_hunter_animation_bundle(mut commands: &mut Commands, asset_server:
             ..default()
         },
         VisibilityBundle::default(),
-        TransformBundle::default()
+        TransformBundle::default(),
+        StateScoped(Screen::Playing),
     )).with_children(|commands|{
         commands.spawn((
             Name::new("Body"),
@@ -173,19 +175,19 @@ impl PartType {
 
 
 
-pub fn spawn_civilian_animation_bundle(commands: &mut Commands, asset_server: &Res<AssetServer>, layout_handles: &mut ResMut<TextureAtlasLayoutHandles>) -> Entity {
-    let body_variant = rand::thread_rng().gen_range(0..BODY_COUNT);
-    let outfit_variant = rand::thread_rng().gen_range(0..OUTFIT_COUNT);
-    let hair_variant = rand::thread_rng().gen_range(0..HAIR_COUNT);
-    let weapon_variant = rand::thread_rng().gen_range(0..WEAPON_COUNT);
+pub fn spawn_civilian_animation_bundle(commands: &mut Commands, asset_server: &Res<AssetServer>, layout_handles: &mut ResMut<TextureAtlasLayoutHandles>, rng: &mut impl Rng) -> Entity {
+    let body_variant = rng.gen_range(0..BODY_COUNT);
+    let outfit_variant = rng.gen_range(0..OUTFIT_COUNT);
+    let hair_variant = rng.gen_range(0..HAIR_COUNT);
+    let weapon_variant = rng.gen_range(0..WEAPON_COUNT);
 
-    let main_color = rand::thread_rng().gen::<f32>() * 0.25 + 0.5;
-    let second_color = rand::thread_rng().gen::<f32>() * 0.5;
-    let third_color = rand::thread_rng().gen::<f32>() * 0.2;
+    let main_color = rng.gen::<f32>() * 0.25 + 0.5;
+    let second_color = rng.gen::<f32>() * 0.5;
+    let third_color = rng.gen::<f32>() * 0.2;
 
-    let main_idx = rand::thread_rng().gen_range(0..=2);
-    let second_idx = rand::thread_rng().gen_range(0..=2);
-    let third_idx = rand::thread_rng().gen_range(0..=2);
+    let main_idx = rng.gen_range(0..=2);
+    let second_idx = rng.gen_range(0..=2);
+    let third_idx = rng.gen_range(0..=2);
     let mut eye_color = [0.; 3];
     eye_color[main_idx] = main_color;
     eye_color[second_idx] = (eye_color[second_idx] + second_color).clamp(0., 1.); 
@@ -197,7 +199,8 @@ pub fn spawn_civilian_animation_bundle(commands: &mut Commands, asset_server: &R
         },
         Name::new("Civilian"),
         TransformBundle::default(),
-        VisibilityBundle::default()
+        VisibilityBundle::default(),
+        StateScoped(Screen::Playing),
         )).with_children(|commands|{
         commands.spawn((
             Name::new("Body"),
@@ -439,6 +442,15 @@ impl AnimationController{
         self.direction = 0;
     }
 
+    /// The way the character looks
+    pub fn facing(&self) -> Vec2{
+        match self.direction {
+            1 => Vec2::NEG_X,
+            2 => Vec2::Y,
+            3 => Vec2::X,
+            _ => Vec2::NEG_Y,
+        }
+    }
     pub fn arm(&mut self){
         self.armed = true
     }
@@ -0,0 +1,304 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use bevy::{asset::ron, input::InputSystem, prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::core::functions::ConfigDir;
+
+pub const CONTROLS_FILE: &str = "controls.ron";
+/// Stick tilt below this is ignored, worn sticks rarely rest at zero
+pub const STICK_DEADZONE: f32 = 0.15;
+
+/// Maps devices to [`Action`]s. Gameplay reads [`ActionState`], never the keyboard or a gamepad.
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConfigDir>();
+        // the headless app inserts the defaults beforehand, so tests never see a player's config
+        if !app.world().contains_resource::<InputBindings>() {
+            let bindings = InputBindings::load(app.world().resource::<ConfigDir>());
+            app.insert_resource(bindings);
+        }
+        app
+        .init_resource::<ActionState>()
+        .init_resource::<Rebinding>()
+        .init_resource::<RebindConflict>()
+        .add_event::<RebindAction>()
+        .add_systems(PreUpdate, (start_rebinding, rebind, update_action_state).chain().in_set(ActionSystem).after(InputSystem));
+    }
+}
+
+/// Runs in `PreUpdate`, after bevy's input. Anything reading [`ActionState`] there goes after it.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ActionSystem;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Dash,
+    Pause,
+    Interact,
+    ToggleLowFx,
+    Settings,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Dash,
+        Action::Pause,
+        Action::Interact,
+        Action::ToggleLowFx,
+        Action::Settings,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::Dash => "Dash",
+            Action::Pause => "Pause",
+            Action::Interact => "Interact",
+            Action::ToggleLowFx => "Low FX",
+            Action::Settings => "Settings",
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct InputBindings {
+    pub keys: BTreeMap<Action, Vec<KeyCode>>,
+    /// Gamepad buttons, on any connected pad. The left stick always moves.
+    #[serde(default)]
+    pub buttons: BTreeMap<Action, Vec<GamepadButtonType>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        InputBindings {
+            keys: BTreeMap::from([
+                (Action::MoveUp, vec![KeyCode::KeyW, KeyCode::ArrowUp]),
+                (Action::MoveDown, vec![KeyCode::KeyS, KeyCode::ArrowDown]),
+                (Action::MoveLeft, vec![KeyCode::KeyA, KeyCode::ArrowLeft]),
+                (Action::MoveRight, vec![KeyCode::KeyD, KeyCode::ArrowRight]),
+                (Action::Dash, vec![KeyCode::ShiftLeft]),
+                (Action::Pause, vec![KeyCode::Escape]),
+                (Action::Interact, vec![KeyCode::Enter, KeyCode::KeyE]),
+                (Action::ToggleLowFx, vec![KeyCode::F4]),
+                (Action::Settings, vec![KeyCode::F10]),
+            ]),
+            buttons: BTreeMap::from([
+                (Action::MoveUp, vec![GamepadButtonType::DPadUp]),
+                (Action::MoveDown, vec![GamepadButtonType::DPadDown]),
+                (Action::MoveLeft, vec![GamepadButtonType::DPadLeft]),
+                (Action::MoveRight, vec![GamepadButtonType::DPadRight]),
+                (Action::Dash, vec![GamepadButtonType::East, GamepadButtonType::RightTrigger]),
+                (Action::Pause, vec![GamepadButtonType::Start]),
+                (Action::Interact, vec![GamepadButtonType::South]),
+                (Action::ToggleLowFx, vec![GamepadButtonType::Select]),
+            ]),
+        }
+    }
+}
+
+impl InputBindings {
+    pub fn path(config_dir: &ConfigDir) -> PathBuf {
+        config_dir.path(CONTROLS_FILE)
+    }
+
+    /// Saved bindings, defaults for anything missing or unreadable.
+    pub fn load(config_dir: &ConfigDir) -> Self {
+        let mut bindings = InputBindings::default();
+        let path = InputBindings::path(config_dir);
+        let Ok(text) = fs::read_to_string(&path) else {return bindings};
+        match ron::from_str::<InputBindings>(&text) {
+            Ok(saved) => {
+                bindings.keys.extend(saved.keys);
+                bindings.buttons.extend(saved.buttons);
+            }
+            Err(e) => warn!("Ignoring {}: {}", path.display(), e),
+        }
+        bindings
+    }
+
+    pub fn save(&self, config_dir: &ConfigDir) {
+        let path = InputBindings::path(config_dir);
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                fs::write(&path, text).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            warn!("Failed to save controls to {}: {}", path.display(), e);
+        }
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn buttons(&self, action: Action) -> &[GamepadButtonType] {
+        self.buttons.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Makes `key` the first key of `action`, its other keys stay. Other actions give `key` up,
+    /// unless it is the only key they have: then nothing changes and that action is returned.
+    pub fn rebind(&mut self, action: Action, key: KeyCode) -> Result<(), Action> {
+        if let Some(other) = Action::ALL.into_iter().find(|other| *other != action && self.keys(*other) == [key]) {
+            return Err(other);
+        }
+        for keys in self.keys.values_mut() {
+            keys.retain(|k| *k != key);
+        }
+        let keys = self.keys.entry(action).or_default();
+        if keys.is_empty() {
+            keys.push(key);
+        } else {
+            keys[0] = key;
+        }
+        Ok(())
+    }
+
+    /// Short name of the first key bound to `action`, for hints on screen
+    pub fn describe(&self, action: Action) -> String {
+        self.keys(action).first().map(key_name).unwrap_or_else(|| "-".to_string())
+    }
+}
+
+pub fn key_name(key: &KeyCode) -> String {
+    let name = format!("{:?}", key);
+    for prefix in ["Key", "Digit"] {
+        if let Some(stripped) = name.strip_prefix(prefix) {
+            return stripped.to_string();
+        }
+    }
+    name
+}
+
+/// This frame's actions
+#[derive(Resource, Default, Debug)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    move_axis: Vec2,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// The `Move` action, not normalized. Length is at most 1, less with a half tilted stick.
+    pub fn move_axis(&self) -> Vec2 {
+        self.move_axis
+    }
+}
+
+/// Ask for the next key press to become the binding of an action. Escape cancels.
+#[derive(Event)]
+pub struct RebindAction(pub Action);
+
+/// The action waiting for a key, if any
+#[derive(Resource, Default)]
+pub struct Rebinding(pub Option<Action>);
+
+/// The last key refused by a rebinding, with the action it is the only key of
+#[derive(Resource, Default)]
+pub struct RebindConflict(pub Option<(KeyCode, Action)>);
+
+fn start_rebinding(
+    mut events: EventReader<RebindAction>,
+    mut rebinding: ResMut<Rebinding>,
+    mut conflict: ResMut<RebindConflict>,
+) {
+    if let Some(RebindAction(action)) = events.read().last() {
+        rebinding.0 = Some(*action);
+        conflict.0 = None;
+    }
+}
+
+fn rebind(
+    mut rebinding: ResMut<Rebinding>,
+    mut conflict: ResMut<RebindConflict>,
+    mut bindings: ResMut<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    config_dir: Res<ConfigDir>,
+) {
+    let Some(action) = rebinding.0 else {return};
+    let Some(key) = keyboard.get_just_pressed().next() else {return};
+    if *key != KeyCode::Escape {
+        // keeps waiting for another key
+        if let Err(other) = bindings.rebind(action, *key) {
+            conflict.0 = Some((*key, other));
+            return;
+        }
+        bindings.save(&config_dir);
+        info!("{} bound to {:?}", action.name(), key);
+    }
+    conflict.0 = None;
+    rebinding.0 = None;
+}
+
+fn update_action_state(
+    mut state: ResMut<ActionState>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    rebinding: Res<Rebinding>,
+) {
+    state.pressed.clear();
+    state.just_pressed.clear();
+    // the key that was just bound shouldn't fire its action right away
+    if rebinding.0.is_some() || rebinding.is_changed() {
+        state.move_axis = Vec2::ZERO;
+        return;
+    }
+    for action in Action::ALL {
+        let keys = bindings.keys(action);
+        let buttons = || gamepads.iter().flat_map(|gamepad| bindings.buttons(action).iter().map(move |b| GamepadButton::new(gamepad, *b)));
+        if keyboard.any_pressed(keys.iter().copied()) || gamepad_buttons.any_pressed(buttons()) {
+            state.pressed.insert(action);
+        }
+        if keyboard.any_just_pressed(keys.iter().copied()) || gamepad_buttons.any_just_pressed(buttons()) {
+            state.just_pressed.insert(action);
+        }
+    }
+    let axis = |positive, negative| state.pressed(positive) as i32 as f32 - state.pressed(negative) as i32 as f32;
+    let digital = Vec2::new(axis(Action::MoveRight, Action::MoveLeft), axis(Action::MoveUp, Action::MoveDown));
+    // keys and the d-pad win over the stick, the stick keeps its tilt
+    state.move_axis = if digital != Vec2::ZERO {
+        digital.normalize()
+    } else {
+        gamepads.iter()
+            .map(|gamepad| left_stick(gamepad, &gamepad_axes))
+            .find(|stick| *stick != Vec2::ZERO)
+            .unwrap_or_default()
+    };
+}
+
+fn left_stick(gamepad: Gamepad, axes: &Axis<GamepadAxis>) -> Vec2 {
+    let axis = |axis_type| axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.);
+    let stick = Vec2::new(axis(GamepadAxisType::LeftStickX), axis(GamepadAxisType::LeftStickY));
+    if stick.length() < STICK_DEADZONE {
+        return Vec2::ZERO;
+    }
+    stick.clamp_length_max(1.)
+}
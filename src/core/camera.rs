@@ -73,17 +73,7 @@ fn setup_camera(
             ..default()
         },
         MainCamera,
-        BloomSettings{
-            intensity: 0.5,
-            low_frequency_boost: 0.8,
-            low_frequency_boost_curvature: 0.8,
-            high_pass_frequency: 1.6,
-            prefilter_settings: BloomPrefilterSettings{
-                threshold: 0.6,
-                threshold_softness: 0.7
-            },
-            composite_mode: BloomCompositeMode::Additive
-        },
+        bloom_settings(),
         AmbientLight2d {
             brightness: 1.,
             ..default()
@@ -92,6 +82,21 @@ fn setup_camera(
     ));
 }
 
+/// Added to and removed from the [`MainCamera`] by the bloom setting
+pub fn bloom_settings() -> BloomSettings {
+    BloomSettings{
+        intensity: 0.5,
+        low_frequency_boost: 0.8,
+        low_frequency_boost_curvature: 0.8,
+        high_pass_frequency: 1.6,
+        prefilter_settings: BloomPrefilterSettings{
+            threshold: 0.6,
+            threshold_softness: 0.7
+        },
+        composite_mode: BloomCompositeMode::Additive
+    }
+}
+
 struct CameraScale(f32);
 
 impl Default for CameraScale{
@@ -1,20 +1,23 @@
 pub mod plugin {
-    use bevy::{app::Plugin, asset::{AssetMetaCheck, AssetPlugin}, math::vec2, prelude::{default, App, PluginGroup}, render::{texture::ImagePlugin, view::Msaa}, window::{PresentMode, Window, WindowPlugin, WindowTheme}, DefaultPlugins};
+    use bevy::{app::Plugin, asset::{AssetMetaCheck, AssetPlugin}, math::vec2, prelude::{default, App, PluginGroup}, render::{texture::ImagePlugin, view::Msaa}, window::{Window, WindowPlugin, WindowTheme}, DefaultPlugins};
     use bevy_easings::EasingsPlugin;
     use bevy_rapier2d::render::RapierDebugRenderPlugin;
     use bevy_rapier2d::prelude::*;
 
-    use crate::core::{camera::plugin::EnhancedCameraPlugin, despawn_lifetime::DespawnLifetimePlugin, functions::TextureAtlasLayoutHandles, post_processing::PostProcessPlugin, ui::UIPlugin};
+    use crate::{core::{camera::plugin::EnhancedCameraPlugin, despawn_lifetime::DespawnLifetimePlugin, functions::{ConfigDir, TextureAtlasLayoutHandles}, post_processing::PostProcessPlugin, ui::UIPlugin}, settings::Settings};
     pub struct DefaultPlugin;
 
     impl Plugin for DefaultPlugin {
         fn build(&self, app: &mut App) {
+            // loaded before the window exists, so it opens with the saved present mode
+            app.init_resource::<ConfigDir>();
+            let settings = Settings::load(app.world().resource::<ConfigDir>());
             app.add_plugins((
                 DefaultPlugins
                 .set(ImagePlugin::default_nearest())
                 .set(WindowPlugin {
                     primary_window: Some(Window {
-                                present_mode: PresentMode::AutoNoVsync,
+                                present_mode: settings.present_mode,
                                 window_theme: Some(WindowTheme::Dark),
                                 canvas: Some("#bevy".to_string()),
                                 fit_canvas_to_parent: true,
@@ -39,23 +42,29 @@ pub mod plugin {
                 UIPlugin
             ),
             );
+            app.insert_resource(settings);
             app.insert_resource(TextureAtlasLayoutHandles::default());
             app.insert_resource(Msaa::Off);
-            app.insert_resource(RapierConfiguration {
-                gravity: vec2(0.0, 0.0),
-                physics_pipeline_active: true,
-                query_pipeline_active: true,
-                timestep_mode: TimestepMode::Variable {
-                    max_dt: 1.0 / 60.0,
-                    time_scale: 1.0,
-                    substeps: 1,
-                },
-                scaled_shape_subdivision: 10,
-                force_update_from_transform_changes: false,
-            });
+            app.insert_resource(rapier_configuration());
             app.add_plugins(bevy::diagnostic::FrameTimeDiagnosticsPlugin::default());
         }
     }
+
+    /// Top-down physics, no gravity. Shared with the headless app.
+    pub fn rapier_configuration() -> RapierConfiguration {
+        RapierConfiguration {
+            gravity: vec2(0.0, 0.0),
+            physics_pipeline_active: true,
+            query_pipeline_active: true,
+            timestep_mode: TimestepMode::Variable {
+                max_dt: 1.0 / 60.0,
+                time_scale: 1.0,
+                substeps: 1,
+            },
+            scaled_shape_subdivision: 10,
+            force_update_from_transform_changes: false,
+        }
+    }
 }
 /*
 app.add_systems(Startup, set_window_icon);
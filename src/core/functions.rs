@@ -37,4 +37,35 @@ impl TextureAtlasLayoutHandles {
             handle
         }
     }
-}
\ No newline at end of file
+}
+/// Value of `--name <value>` or `--name=<value>` on the command line.
+pub fn arg_value(name: &str) -> Option<String> {
+    let flag = format!("--{}", name);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&flag).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Where player config files live: `$VAMPILLAGE_CONFIG_DIR`, or `config/` next to the working directory.
+/// The headless app gets a fresh temporary one, so tests never touch a player's files.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct ConfigDir(pub std::path::PathBuf);
+
+impl Default for ConfigDir {
+    fn default() -> Self {
+        ConfigDir(std::env::var_os("VAMPILLAGE_CONFIG_DIR").map_or_else(|| "config".into(), std::path::PathBuf::from))
+    }
+}
+
+impl ConfigDir {
+    pub fn path(&self, file: &str) -> std::path::PathBuf {
+        self.0.join(file)
+    }
+}
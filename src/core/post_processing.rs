@@ -299,9 +299,9 @@ pub struct PostProcessUniform {
     day_color: Vec4,
     night_color: Vec4,
 
-    vignette_strength: f32,
+    pub vignette_strength: f32,
 
-    wave_strength: f32,
+    pub wave_strength: f32,
 
     // WebGL2 structs must be 16 byte aligned.
     //[cfg(feature = "webgl2")]
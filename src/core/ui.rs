@@ -5,10 +5,12 @@ use bevy::prelude::*;
 use bevy::app::Plugin;
 use bevy::ui::widget::UiImageSize;
 use bevy::ui::ContentSize;
-use bevy::window::WindowResized;
+use bevy::window::{PrimaryWindow, WindowResized};
 
 use crate::player::components::Player;
-use crate::{get_local_time_f, DAY_DURATION, TRANSLATION_DURATION};
+use crate::balance::GameBalance;
+use crate::screen::Screen;
+use crate::systems::{get_local_time_f, RunStart};
 
 use super::camera::plugin::{MainCamera, TARGET_ASPECT, TARGET_HEIGHT, TARGET_WIDTH};
 use super::functions::TextureAtlasLayoutHandles;
@@ -28,9 +30,9 @@ pub struct UIPlugin;
 
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PreStartup, setup);
+        app.add_systems(OnEnter(Screen::Playing), setup);
         app.add_systems(Update, on_resize_system);
-        app.add_systems(Update, update);
+        app.add_systems(Update, update.run_if(in_state(Screen::Playing)));
     }
 }
 
@@ -64,6 +66,7 @@ fn setup(
             ..default()
         },
         PlayerUINode,
+        StateScoped(Screen::Playing),
     )).with_children(|commands|{
         commands.spawn(
             NodeBundle {
@@ -143,19 +146,23 @@ fn setup(
 
 fn on_resize_system(
     mut resize_reader: EventReader<WindowResized>,
-    mut ui_style: Query<(&mut Style, &UiImageSize), With<PlayerUINode>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut ui_style: Query<(&mut Style, Ref<UiImageSize>), With<PlayerUINode>>,
 ) {
-    for e in resize_reader.read() {
-        for (mut style, size) in ui_style.iter_mut() {
-            let size = size.size();
-            if TARGET_ASPECT > e.width / e.height {
-                // width is smaller than now, resize relative to width
-                style.width = Val::Px(2. * e.width / TARGET_WIDTH * size.x as f32);
-                style.height = Val::Px(2. * e.width / TARGET_WIDTH * size.y as f32);
-            } else {
-                style.height = Val::Px(2. * e.height / TARGET_HEIGHT * size.y as f32);
-                style.width = Val::Px(2. * e.height / TARGET_HEIGHT * size.x as f32);
-            }
+    // the hud is spawned with every run, so it also has to be fitted once its images are in
+    let resized = resize_reader.read().count() > 0;
+    let Ok(window) = window.get_single() else {return};
+    let (width, height) = (window.width(), window.height());
+    for (mut style, size) in ui_style.iter_mut() {
+        if !resized && !size.is_changed() {continue}
+        let size = size.size();
+        if TARGET_ASPECT > width / height {
+            // width is smaller than now, resize relative to width
+            style.width = Val::Px(2. * width / TARGET_WIDTH * size.x as f32);
+            style.height = Val::Px(2. * width / TARGET_WIDTH * size.y as f32);
+        } else {
+            style.height = Val::Px(2. * height / TARGET_HEIGHT * size.y as f32);
+            style.width = Val::Px(2. * height / TARGET_HEIGHT * size.x as f32);
         }
     }
 }
@@ -165,13 +172,15 @@ fn update(
     mut blood_e: Query<&mut TextureAtlas, (With<Blood>, Without<Daynight>)>,
     mut daynight_e: Query<(&mut TextureAtlas, &mut UiImage), (Without<Blood>, With<Daynight>)>,
     time: Res<Time<Virtual>>,
-    player_stats: Query<&Player>
+    run_start: Res<RunStart>,
+    player_stats: Query<&Player>,
+    balance: Res<GameBalance>,
 ){
-    if let Ok(stats) = player_stats.get_single(){
-        blood_e.single_mut().index = ((1. - (stats.hp as f32 / stats.max_hp as f32)) * 20.).round() as usize % 20;
+    if let (Ok(stats), Ok(mut blood)) = (player_stats.get_single(), blood_e.get_single_mut()) {
+        blood.index = ((1. - (stats.hp as f32 / stats.max_hp as f32)) * 20.).round() as usize % 20;
     }
-    let (mut atlas, mut image) = daynight_e.single_mut();
-    let t = (get_local_time_f(time.elapsed_seconds()) + 0.75) % 1.;
+    let Ok((mut atlas, mut image)) = daynight_e.get_single_mut() else {return};
+    let t = (get_local_time_f(run_start.elapsed(&time).as_secs_f32(), &balance.daycycle) + 0.75) % 1.;
     let d = (t * (29. * 2. - 2.)).ceil() as usize;
     atlas.index = if d < 29 {image.flip_x = false; d} else {image.flip_x = true; (29 * 2) - d - 2};
 }
@@ -2,9 +2,9 @@
 
 use bevy::{dev_tools::states::log_transitions, prelude::*};
 
-use crate::screen::Screen;
+use crate::{screen::Screen, systems::GameState};
 
 pub(super) fn plugin(app: &mut App) {
     // Print state transitions in dev builds
-    app.add_systems(Update, log_transitions::<Screen>);
+    app.add_systems(Update, (log_transitions::<Screen>, log_transitions::<GameState>));
 }
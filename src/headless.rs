@@ -0,0 +1,114 @@
+use std::{path::PathBuf, sync::atomic::{AtomicU32, Ordering}, time::Duration};
+
+use bevy::{asset::AssetMetaCheck, prelude::*, render::render_resource::Shader, state::app::StatesPlugin, text::Font, time::TimeUpdateStrategy};
+use bevy_rapier2d::prelude::*;
+
+use crate::{controls::InputBindings, core::{default::plugin::rapier_configuration, despawn_lifetime::DespawnLifetimePlugin, functions::{ConfigDir, TextureAtlasLayoutHandles}}, map::plugin::MapPath, player::replay::{Replay, ReplayPlayer}, rng::GameRng, screen::Screen, GameplayPlugin};
+
+/// Runs [`GameplayPlugin`] without a window, renderer or audio.
+/// Time advances by a fixed step every `app.update()` and [`GameRng`] uses a fixed seed,
+/// so a run only depends on the inputs fed to it.
+///
+/// ```ignore
+/// let mut app = App::new();
+/// app.add_plugins(HeadlessAppPlugin::default().with_map("map/fixture.ldtk"));
+/// app.update();
+/// ```
+pub struct HeadlessAppPlugin {
+    pub asset_root: String,
+    pub map: String,
+    pub timestep: Duration,
+    pub seed: u64,
+    pub replay: Option<Replay>,
+    /// A fresh temporary directory for every app, so tests don't share or touch saved config
+    pub config_dir: PathBuf,
+}
+
+/// Numbers the temporary config directories of the apps in a test binary
+static APPS: AtomicU32 = AtomicU32::new(0);
+
+impl Default for HeadlessAppPlugin {
+    fn default() -> Self {
+        HeadlessAppPlugin {
+            asset_root: "assets".to_string(),
+            map: MapPath::default().0,
+            timestep: Duration::from_secs_f64(1. / 60.),
+            seed: 0,
+            replay: None,
+            config_dir: std::env::temp_dir().join(format!("vampillage-{}-{}", std::process::id(), APPS.fetch_add(1, Ordering::Relaxed))),
+        }
+    }
+}
+
+impl HeadlessAppPlugin {
+    pub fn with_asset_root(mut self, asset_root: impl Into<String>) -> Self {
+        self.asset_root = asset_root.into();
+        self
+    }
+
+    pub fn with_map(mut self, map: impl Into<String>) -> Self {
+        self.map = map.into();
+        self
+    }
+
+    pub fn with_timestep(mut self, timestep: Duration) -> Self {
+        self.timestep = timestep;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_config_dir(mut self, config_dir: impl Into<PathBuf>) -> Self {
+        self.config_dir = config_dir.into();
+        self
+    }
+
+    /// Plays `replay` back, on its own seed and map.
+    pub fn with_replay(mut self, replay: Replay) -> Self {
+        self.seed = replay.seed;
+        self.map = replay.map.clone();
+        self.replay = Some(replay);
+        self
+    }
+}
+
+impl Plugin for HeadlessAppPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                file_path: self.asset_root.clone(),
+                meta_check: AssetMetaCheck::Never,
+                ..default()
+            },
+            TransformPlugin,
+            HierarchyPlugin,
+            StatesPlugin,
+            bevy::input::InputPlugin,
+            RapierPhysicsPlugin::<NoUserData>::default(),
+            DespawnLifetimePlugin,
+        ));
+        // asset types the renderer would normally register, sprites and text still hold handles to them
+        app.init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .init_asset::<Font>()
+            .init_asset::<Shader>()
+            .init_asset::<Mesh>();
+        app.insert_resource(TextureAtlasLayoutHandles::default());
+        app.insert_resource(rapier_configuration());
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(self.timestep));
+        app.insert_resource(MapPath(self.map.clone()));
+        app.insert_resource(ConfigDir(self.config_dir.clone()));
+        app.insert_resource(InputBindings::default());
+        app.insert_resource(GameRng::new(self.seed));
+        if let Some(replay) = &self.replay {
+            app.insert_resource(ReplayPlayer::new(replay.clone()));
+        }
+        // no menus, a run starts right away
+        app.insert_state(Screen::Playing);
+        app.add_plugins(GameplayPlugin);
+    }
+}
@@ -1,13 +1,17 @@
 #[cfg(feature = "dev")]
 mod dev_tools;
-mod game;
-mod screen;
+pub mod screen;
 mod ui;
 
 pub mod core;
+pub mod balance;
+pub mod controls;
 pub mod player;
 pub mod npc;
 pub mod map;
+pub mod menu;
+pub mod rng;
+pub mod settings;
 pub mod systems;
 pub mod stuff;
 pub mod characters;
@@ -15,20 +19,67 @@ pub mod sounds;
 
 use bevy::{prelude::*};
 
+use balance::BalancePlugin;
 use characters::plugin::CharacterAnimationPlugin;
-use map::plugin::TileMapPlugin;
+use controls::ControlsPlugin;
+use map::plugin::{MapPath, TileMapFxPlugin, TileMapPlugin};
+use menu::MenuPlugin;
 use npc::systems::RosesCollected;
 use npc::NPCPlugin;
-use player::PlayerPlugin;
-use sounds::AudioPlugin;
+use player::{replay::replay_from_args, PlayerPlugin};
+use rng::GameRng;
+use screen::Screen;
+use settings::SettingsPlugin;
+use sounds::{components::PlaySoundEvent, AudioPlugin};
 use stuff::{simple_anim_update, spawn_follow_blood_particle, update_blood_particles};
 use systems::*;
 
+pub use headless::HeadlessAppPlugin;
+
+mod headless;
+
+/// The windowed game, everything from [`GameplayPlugin`] plus rendering, audio and menus.
+/// Starts on the splash screen, or right in a run when playing a replay.
 pub struct AppPlugin;
 
 const NUM_ROSES: u32 = 3;
 
 impl Plugin for AppPlugin {
+    fn build(&self, app: &mut App) {
+        app
+    .add_plugins((
+        core::default::plugin::DefaultPlugin,
+        //SwitchableEguiInspectorPlugin,
+        //ScreenDiagnosticsPlugin,
+        GameplayPlugin,
+        TileMapFxPlugin,
+        AudioPlugin,
+        SettingsPlugin,
+        screen::plugin,
+    ))
+    .add_systems(Update, apply_daycycle_fx.after(update_daycycle).run_if(in_state(GameState::InGame)));
+
+    #[cfg(feature = "dev")]
+    app.add_plugins(dev_tools::plugin);
+
+    let (recorder, replay_player) = replay_from_args();
+    if let Some(recorder) = recorder {
+        app.insert_resource(recorder);
+    }
+    if let Some(replay_player) = replay_player {
+        app.insert_resource(MapPath(replay_player.replay.map.clone()))
+            .insert_resource(GameRng::new(replay_player.replay.seed))
+            .insert_resource(replay_player)
+            .insert_state(Screen::Playing);
+    }
+    }
+}
+
+/// Game logic that does not need a window or a GPU: map, physics driven player and NPCs,
+/// day cycle and scoring. Shared by [`AppPlugin`] and [`HeadlessAppPlugin`].
+pub struct GameplayPlugin;
+
+impl Plugin for GameplayPlugin {
     fn build(&self, app: &mut App) {
         // Order new `AppStep` variants by adding them here:
         app.configure_sets(
@@ -37,35 +88,47 @@ impl Plugin for AppPlugin {
         );
         app
     .add_plugins((
-        core::default::plugin::DefaultPlugin,
-        //SwitchableEguiInspectorPlugin,
-        //ScreenDiagnosticsPlugin,
+        BalancePlugin,
+        ControlsPlugin,
+        MenuPlugin,
         TileMapPlugin,
-    ))
-    .insert_state(GameState::InGame)
+        screen::playing::plugin,
+    ));
+    // the headless app skips the menus by starting in `Screen::Playing`
+    if !app.world().contains_resource::<State<Screen>>() {
+        app.init_state::<Screen>();
+    }
+    app
+    .add_sub_state::<GameState>()
+    .enable_state_scoped_entities::<Screen>()
+    .init_resource::<GameRng>()
+    .init_resource::<RunStart>()
+    .init_resource::<PauseReasons>()
     .insert_resource(DayCycle {
         is_night: true,
         is_translating: false,
+        daytime: 1.,
     })
     .insert_resource(RosesCollected {
         collected: 0,
         max: NUM_ROSES,
     })
     .add_event::<PauseEvent>()
+    .add_event::<PlaySoundEvent>()
     .add_plugins((
         PlayerPlugin,
         NPCPlugin,
         CharacterAnimationPlugin,
-        AudioPlugin,
     ))
-    .add_systems(Startup, spawn_starter_screen)
-    .add_systems(Update, interact_start_button)
     .add_systems(Update, (
         (update_daycycle, update_score).run_if(in_state(GameState::InGame)), 
         simple_anim_update.run_if(in_state(GameState::InGame)),
         update_blood_particles.run_if(in_state(GameState::InGame)),
-        pause_game
-    ));
+        pause_game.run_if(in_state(Screen::Playing)),
+    ))
+    .add_systems(OnEnter(Screen::Playing), start_run)
+    .add_systems(OnEnter(Screen::Restarting), restart_run)
+    .add_systems(OnExit(Screen::Playing), end_run);
     }
 }
 
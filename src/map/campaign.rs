@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{ldtk::Level, prelude::*};
+use bevy_rapier2d::prelude::Velocity;
+
+use crate::{npc::components::{Civilian, Hunter, Projectile}, player::{components::Player, systems::{PlayerController, PLAYER_SPAWN}}};
+
+use super::{plugin::TrespassableCells, tilemap::TransformToGrid};
+
+/// A run plays the root levels of the LDtk project in order. Collecting every rose of a level
+/// moves on to the next one, only the last level wins the run.
+#[derive(Resource, Default, Debug)]
+pub struct Campaign {
+    /// Index of the current level among the project's root levels
+    pub level: usize,
+    /// Where the player starts and respawns in the current level, none until the project is loaded
+    pub start: Option<Vec2>,
+}
+
+impl Campaign {
+    pub fn level_selection(&self) -> LevelSelection {
+        LevelSelection::index(self.level)
+    }
+
+    /// The current level, once the project is loaded
+    pub fn current_level<'a>(&self, project: &'a LdtkProject) -> Option<&'a Level> {
+        project.get_raw_level_at_indices(&LevelIndices::in_root(self.level))
+    }
+
+    pub fn has_next_level(&self, project: &LdtkProject) -> bool {
+        project.get_raw_level_at_indices(&LevelIndices::in_root(self.level + 1)).is_some()
+    }
+
+    /// The `PlayerStart` entity of the current level, the middle of the level without one.
+    /// Levels sit at their world position, so every level has its own.
+    pub fn level_start(&self, project: &LdtkProject) -> Option<Vec2> {
+        let level = self.current_level(project)?;
+        let px = level.layer_instances.iter().flatten()
+            .flat_map(|layer| layer.entity_instances.iter())
+            .find(|entity| entity.identifier == "PlayerStart")
+            .map_or(IVec2::new(level.px_wid / 2, level.px_hei / 2), |entity| entity.px);
+        // LDtk's y points down
+        Some(Vec2::new((level.world_x + px.x) as f32, -(level.world_y + px.y) as f32))
+    }
+
+    /// Where the player respawns
+    pub fn respawn_point(&self) -> Vec2 {
+        self.start.unwrap_or(PLAYER_SPAWN)
+    }
+}
+
+/// Every rose of a level was collected and there is a level after it
+#[derive(Event)]
+pub struct LevelCompleted;
+
+/// Selects the next level. bevy_ecs_ldtk despawns the old one together with its colliders and
+/// spawners, the grids and camera bounds are rebuilt from the new one like at the start of a run.
+/// The player keeps their upgrades and is moved to the new level's start by [`place_player_at_start`].
+pub fn advance_level(
+    mut commands: Commands,
+    mut level_completed: EventReader<LevelCompleted>,
+    mut campaign: ResMut<Campaign>,
+    mut level_selection: ResMut<LevelSelection>,
+    npcs: Query<Entity, Or<(With<Civilian>, With<Hunter>, With<Projectile>)>>,
+) {
+    if level_completed.read().count() == 0 {return}
+    campaign.level += 1;
+    campaign.start = None;
+    *level_selection = campaign.level_selection();
+    info!("Advancing to level {}", campaign.level);
+    commands.insert_resource(TransformToGrid::default());
+    commands.insert_resource(TrespassableCells::default());
+    for entity in npcs.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Puts the player at the start of the current level once the project is loaded, at the start of
+/// a run and after every [`advance_level`]
+pub fn place_player_at_start(
+    mut campaign: ResMut<Campaign>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    mut player: Query<(&mut Transform, &mut Velocity, &mut PlayerController), With<Player>>,
+) {
+    if campaign.start.is_some() {return}
+    let Some(project) = ldtk_projects.get_single().ok().and_then(|handle| ldtk_project_assets.get(handle)) else {return};
+    let Some(start) = campaign.level_start(project) else {return};
+    campaign.start = Some(start);
+    for (mut transform, mut velocity, mut controller) in player.iter_mut() {
+        transform.translation = start.extend(transform.translation.z);
+        *velocity = Velocity::zero();
+        controller.accumulated_velocity = Vec2::ZERO;
+    }
+}
@@ -1,2 +1,5 @@
 pub mod tilemap;
-pub mod plugin;
\ No newline at end of file
+pub mod plugin;
+pub mod campaign;
+pub mod terrain;
+pub mod streaming;
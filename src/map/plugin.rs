@@ -1,41 +1,50 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
-use bevy::{math::ivec2, prelude::*, transform::commands, utils::HashSet};
+use bevy::{prelude::*, transform::commands, utils::{HashMap, HashSet}};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::{ActiveEvents, Collider, RigidBody, Sensor, Velocity};
 use bevy_light_2d::prelude::Light2dPlugin;
-use crate::player::components::Player;
+use crate::{player::components::Player, screen::Screen};
 
-use super::tilemap::{self, setup_camera_bounds, update_emitter_tiles, RaycastableTileObsticle, TileObsticle, TransformToGrid};
+use super::{campaign::{advance_level, place_player_at_start, Campaign, LevelCompleted}, streaming::{despawn_npcs_in_unloaded_levels, select_player_level}, terrain::spawn_terrain_colliders, tilemap::{self, setup_camera_bounds, update_emitter_tiles, TransformToGrid}};
 
 pub struct TileMapPlugin;
 
 impl Plugin for TileMapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((
-            LdtkPlugin,
-            Light2dPlugin,
-        ));
-        app.insert_resource(LevelSelection::Uid(0))
+        app.add_plugins(LdtkPlugin);
+        app.init_resource::<MapPath>();
+        app.init_resource::<Campaign>();
+        app.add_event::<LevelCompleted>();
+        app.insert_resource(LevelSelection::index(0))
         .insert_resource(LdtkSettings {
             level_spawn_behavior: LevelSpawnBehavior::UseWorldTranslation {
-                load_level_neighbors: false,
+                load_level_neighbors: true,
             },
             set_clear_color: SetClearColor::FromLevelBackground,
             ..Default::default()
         });
         app.add_event::<RespawnRosesEvent>();
-        app.add_systems(PreStartup, tilemap::pre_setup);
-        app.add_systems(Update, (tilemap::watcher, spawn_collectables, respawn_collectables));
-        app.add_systems(Update, (tilemap::spawn_tile_collision, update_emitter_tiles, setup_camera_bounds, update_unit_grid, tilemap::spawn_tile_tree, tilemap::spawn_raycastable_tile_collision, tilemap::update_animated_trees));
-        app.add_systems(PreUpdate, trespassable_spawn_listener);
+        app.insert_resource(TransformToGrid::default());
+        app.add_systems(OnEnter(Screen::Playing), tilemap::spawn_map);
+        app.add_systems(Update, (
+            advance_level,
+            place_player_at_start,
+            despawn_npcs_in_unloaded_levels,
+            tilemap::invalidate_level_geometry,
+            tilemap::build_level_grids,
+            select_player_level,
+        ).chain());
+        app.add_systems(Update, (spawn_collectables, respawn_collectables));
+        app.add_systems(Update, (spawn_terrain_colliders, setup_camera_bounds.run_if(resource_changed::<TransformToGrid>), update_unit_grid));
         app.register_ldtk_entity::<HunterSpawnerBundle>("HunterSpawner");
         app.register_ldtk_entity::<CivilianSpawnerBundle>("CivilianSpawner");
         app.register_ldtk_entity::<CollectableRoseBundle>("Rose");
-        app.register_ldtk_int_cell_for_layer::<tilemap::RaycastableTileObsticleBundle>("Ground", 1);
+        app.register_ldtk_entity::<HomeBundle>("Home");
+        app.register_ldtk_entity::<WorkplaceBundle>("Work");
+        app.register_ldtk_entity::<GatheringPointBundle>("Gathering");
+        // colliders come from the balance's TerrainTable, only the trees need a bundle
         app.register_ldtk_int_cell_for_layer::<tilemap::TiledTreeBundle>("Ground", 3);
-        app.register_ldtk_int_cell_for_layer::<tilemap::TileObsticleBundle>("Ground", 4);
-        app.register_ldtk_int_cell_for_layer::<tilemap::RaycastableTileObsticleBundle>("Ground", 5);
 
         app.register_ldtk_int_cell_for_layer::<tilemap::LightEmitterPYBundle>("Emitters", 1);
         app.register_ldtk_int_cell_for_layer::<tilemap::LightEmitterPYNXBundle>("Emitters", 2);
@@ -55,6 +64,27 @@ impl Plugin for TileMapPlugin {
     }
 }
 
+/// Lights, window overlays and swaying trees. Only makes sense with a renderer,
+/// so the headless app leaves it out.
+pub struct TileMapFxPlugin;
+
+impl Plugin for TileMapFxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Light2dPlugin);
+        app.add_systems(Update, (update_emitter_tiles, tilemap::spawn_tile_tree, tilemap::update_animated_trees));
+    }
+}
+
+/// The LDtk project `spawn_map` spawns, relative to the asset folder.
+#[derive(Resource)]
+pub struct MapPath(pub String);
+
+impl Default for MapPath {
+    fn default() -> Self {
+        MapPath("map/larger_map.ldtk".to_string())
+    }
+}
+
 
 #[derive(Component, Default)]
 pub struct CollectableRose;
@@ -166,19 +196,92 @@ impl Default for CivilianSpawnerBundle {
     }
 }
 
-#[derive(Resource, Default)]
+/// A door civilians go in at night
+#[derive(Clone, Copy, Debug, Default, Component)]
+pub struct Home;
+
+#[derive(Clone, Debug, Default, Bundle, LdtkEntity)]
+pub struct HomeBundle {
+    home: Home,
+}
+
+/// Where civilians spend the day
+#[derive(Clone, Copy, Debug, Default, Component)]
+pub struct Workplace;
+
+#[derive(Clone, Debug, Default, Bundle, LdtkEntity)]
+pub struct WorkplaceBundle {
+    workplace: Workplace,
+}
+
+/// Where civilians meet at dusk, besides the campfires
+#[derive(Clone, Copy, Debug, Default, Component)]
+pub struct GatheringPoint;
+
+#[derive(Clone, Debug, Default, Bundle, LdtkEntity)]
+pub struct GatheringPointBundle {
+    point: GatheringPoint,
+}
+
+/// Cells NPCs can walk on, stitched from every loaded level like [`TransformToGrid::stitched`]
+#[derive(Resource, Default, Clone)]
 pub struct TrespassableCells{
-    pub cells: Vec<Vec<bool>>,
+    /// Shared with the path tasks, see [`crate::npc::pathfinder::PathGrids`]
+    pub cells: Arc<Vec<Vec<bool>>>,
+    /// Cells of each level on its own grid
+    pub levels: HashMap<LevelIid, Vec<Vec<bool>>>,
     pub units: HashSet<IVec2>,
     pub ready: bool
 }
 
 impl TrespassableCells {
+    /// Cells drawn as text, `#` is a wall and the first row is y = 0
+    pub fn from_rows(rows: &[&str]) -> Self {
+        let width = rows.first().map_or(0, |row| row.len());
+        let mut cells = vec![vec![true; rows.len()]; width];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                cells[x][y] = c != '#';
+            }
+        }
+        TrespassableCells {cells: Arc::new(cells), ready: true, ..default()}
+    }
+
     pub fn is_trespassable(&self, pos: &IVec2) -> bool{
         let Some(column) = self.cells.get(pos.x as usize) else {return false};
         let Some(value) = column.get(pos.y as usize) else {return false};
         *value
     }
+
+    /// Every cell the straight line between the centers of `from` and `to` touches is trespassable.
+    /// Where the line passes exactly through a corner both cells beside it have to be free too,
+    /// so it never cuts the corner of a wall.
+    pub fn line_of_sight(&self, from: IVec2, to: IVec2) -> bool{
+        let delta = to - from;
+        let step = delta.signum();
+        let (nx, ny) = (delta.x.abs(), delta.y.abs());
+        let (mut ix, mut iy) = (0, 0);
+        let mut pos = from;
+        if !self.is_trespassable(&pos) {return false}
+        while ix < nx || iy < ny {
+            // which cell border the line crosses next, (0.5 + ix) / nx against (0.5 + iy) / ny
+            let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+            if decision == 0 {
+                if !self.is_trespassable(&(pos + IVec2::new(step.x, 0))) || !self.is_trespassable(&(pos + IVec2::new(0, step.y))) {return false}
+                pos += step;
+                ix += 1;
+                iy += 1;
+            } else if decision < 0 {
+                pos.x += step.x;
+                ix += 1;
+            } else {
+                pos.y += step.y;
+                iy += 1;
+            }
+            if !self.is_trespassable(&pos) {return false}
+        }
+        true
+    }
 }
 
 
@@ -189,39 +292,14 @@ fn update_unit_grid(
     transfromer: Res<TransformToGrid>,
     units_q: Query<&Transform, (With<Velocity>, Without<Player>)>
 ){
-    trespassable.units.clear();
+    let mut units = HashSet::new();
     for t in units_q.iter(){
-        let pos = transfromer.from_world_i32(t.translation.xy());
-        trespassable.units.insert(pos);
+        // units outside of the loaded levels would land on unrelated cells
+        if !transfromer.stitched.contains(t.translation.xy()) {continue}
+        units.insert(transfromer.from_world_i32(t.translation.xy()));
     }
-}
-
-fn trespassable_spawn_listener(
-    //mut commands: Commands,
-    entity_q: Query<&GridCoords, Added<TileObsticle>>,
-    ray_entity_q: Query<&GridCoords, Added<RaycastableTileObsticle>>,
-    mut trespassable_cells: ResMut<TrespassableCells>,
-    transfromer: Res<TransformToGrid>,
-    //level_query: Query<(Entity, &LevelIid)>,
-    //ldtk_projects: Query<&Handle<LdtkProject>>,
-    //ldtk_project_assets: Res<Assets<LdtkProject>>,
-){
-    if !entity_q.is_empty() && transfromer.ready {
-        let cells_column = vec![true; transfromer.grid_size.y as usize];
-        let mut cells_grid = vec![cells_column; transfromer.grid_size.x as usize];
-        
-        for coords in entity_q.iter(){
-            let pos = ivec2(coords.x, transfromer.grid_size.y - coords.y - 1);
-            cells_grid[pos.x as usize][pos.y as usize] = false;
-        }
-
-        for coords in ray_entity_q.iter(){
-            let pos = ivec2(coords.x, transfromer.grid_size.y - coords.y - 1);
-            cells_grid[pos.x as usize][pos.y as usize] = false;
-        }
-
-        trespassable_cells.cells = cells_grid;
-        info!("Trespassable cells inited!");
-        trespassable_cells.ready = true;
+    // only marked changed when someone moved to another cell, the path grids are rebuilt then
+    if trespassable.units != units {
+        trespassable.units = units;
     }
-}
\ No newline at end of file
+}
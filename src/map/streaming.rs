@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+
+use crate::{npc::components::{Civilian, Hunter, Projectile}, player::components::Player};
+
+use super::tilemap::TransformToGrid;
+
+/// Selects the loaded level the player walks into. bevy_ecs_ldtk then loads its neighbors
+/// and unloads the levels that are not next to it anymore.
+pub fn select_player_level(
+    player: Query<&Transform, With<Player>>,
+    mut transformer: ResMut<TransformToGrid>,
+    mut level_selection: ResMut<LevelSelection>,
+) {
+    if !transformer.ready {return}
+    let Ok(player) = player.get_single() else {return};
+    let Some(iid) = transformer.level_at(player.translation.xy()).map(|(iid, _)| iid.clone()) else {return};
+    if transformer.current.as_ref() == Some(&iid) {return}
+    info!("Entering level {}", iid);
+    *level_selection = LevelSelection::Iid(iid.clone());
+    transformer.current = Some(iid);
+}
+
+/// NPCs and projectiles left in a level that is unloaded go away with it, like its spawners do.
+/// Runs before the level's grid is dropped.
+pub fn despawn_npcs_in_unloaded_levels(
+    mut commands: Commands,
+    mut level_events: EventReader<LevelEvent>,
+    transformer: Res<TransformToGrid>,
+    npcs: Query<(Entity, &Transform), Or<(With<Civilian>, With<Hunter>, With<Projectile>)>>,
+) {
+    for event in level_events.read() {
+        let LevelEvent::Despawned(iid) = event else {continue};
+        // the current level only goes away to be respawned
+        if transformer.current.as_ref() == Some(iid) {continue}
+        let Some(grid) = transformer.levels.get(iid) else {continue};
+        for (entity, transform) in npcs.iter() {
+            let pos = transform.translation.xy();
+            if !grid.contains(pos) {continue}
+            if transformer.levels.iter().any(|(other, other_grid)| other != iid && other_grid.contains(pos)) {continue}
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use bevy::{prelude::*, utils::{HashMap, HashSet}};
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::{Collider, CollisionGroups, Friction, Group, RigidBody, Sensor};
+use serde::Deserialize;
+
+use crate::{balance::GameBalance, player::systems::{RAYCASTABLE_STRUCT_CG, STRUCTURES_CG}};
+
+use super::tilemap::Structure;
+
+/// How the cells of one IntGrid value collide
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Terrain {
+    /// Collision groups the merged colliders are members of
+    pub groups: u32,
+    pub sensor: bool,
+    pub friction: f32,
+    /// Excluded from [`super::plugin::TrespassableCells`]
+    pub blocks_path: bool,
+}
+
+impl Terrain {
+    /// Stops characters and projectiles
+    pub const WALL: Terrain = Terrain {groups: STRUCTURES_CG, sensor: false, friction: 1., blocks_path: true};
+    /// Stops characters, projectiles fly over it
+    pub const RAYCASTABLE_WALL: Terrain = Terrain {groups: RAYCASTABLE_STRUCT_CG, sensor: false, friction: 1., blocks_path: true};
+}
+
+/// What a [`TerrainRow`] collides with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Collides {
+    Wall,
+    RaycastableWall,
+}
+
+/// One row of the `terrain` section of the balance file
+#[derive(Clone, Debug, Deserialize)]
+pub struct TerrainRow {
+    pub layer: String,
+    pub value: i32,
+    pub collides: Collides,
+    #[serde(default)]
+    pub sensor: bool,
+    #[serde(default = "TerrainRow::default_friction")]
+    pub friction: f32,
+    #[serde(default = "TerrainRow::default_blocks_path")]
+    pub blocks_path: bool,
+}
+
+impl TerrainRow {
+    fn default_friction() -> f32 {1.}
+    fn default_blocks_path() -> bool {true}
+
+    pub fn terrain(&self) -> Terrain {
+        let groups = match self.collides {
+            Collides::Wall => STRUCTURES_CG,
+            Collides::RaycastableWall => RAYCASTABLE_STRUCT_CG,
+        };
+        Terrain {groups, sensor: self.sensor, friction: self.friction, blocks_path: self.blocks_path}
+    }
+}
+
+/// Maps (IntGrid layer identifier, value) to the collider built for those cells.
+/// Read from the `terrain` rows of [`GameBalance`], the rows below are the defaults.
+/// Cells of values missing from the table don't collide. A reloaded table applies to the levels spawned after it.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(from = "Vec<TerrainRow>")]
+pub struct TerrainTable(pub HashMap<(String, i32), Terrain>);
+
+impl Default for TerrainTable {
+    fn default() -> Self {
+        TerrainTable(HashMap::new())
+            .with("Ground", 1, Terrain::RAYCASTABLE_WALL)
+            .with("Ground", 3, Terrain::WALL)
+            .with("Ground", 4, Terrain::WALL)
+            .with("Ground", 5, Terrain::RAYCASTABLE_WALL)
+    }
+}
+
+impl From<Vec<TerrainRow>> for TerrainTable {
+    fn from(rows: Vec<TerrainRow>) -> Self {
+        rows.iter().fold(TerrainTable(HashMap::new()), |table, row| table.with(&row.layer, row.value, row.terrain()))
+    }
+}
+
+impl TerrainTable {
+    pub fn with(mut self, layer: &str, value: i32, terrain: Terrain) -> Self {
+        self.0.insert((layer.to_string(), value), terrain);
+        self
+    }
+
+    pub fn get(&self, layer: &str, value: i32) -> Option<&Terrain> {
+        self.0.get(&(layer.to_string(), value))
+    }
+}
+
+/// A rectangle of cells, bounds are inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CellRect {
+    pub left: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub top: i32,
+}
+
+/// Represents a wide wall that is 1 tile tall
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+struct Plate {
+    left: i32,
+    right: i32,
+}
+
+/// Covers the cells with few rectangles:
+/// 1. combine the cells into flat "plates" in each individual row
+/// 2. combine the plates into rectangles across multiple rows wherever they line up exactly
+pub fn merge_cells(cells: &HashSet<IVec2>) -> Vec<CellRect> {
+    let Some(min) = cells.iter().copied().reduce(IVec2::min) else {return Vec::new()};
+    let max = cells.iter().copied().fold(min, IVec2::max);
+
+    let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
+    for y in min.y..=max.y {
+        let mut row_plates: Vec<Plate> = Vec::new();
+        let mut plate_start = None;
+        // + 1 so the plates that touch the right edge are terminated
+        for x in min.x..=max.x + 1 {
+            match (plate_start, cells.contains(&IVec2::new(x, y))) {
+                (Some(s), false) => {
+                    row_plates.push(Plate {left: s, right: x - 1});
+                    plate_start = None;
+                }
+                (None, true) => plate_start = Some(x),
+                _ => (),
+            }
+        }
+        plate_stack.push(row_plates);
+    }
+    // an extra empty row so the rects that touch the top edge are finished
+    plate_stack.push(Vec::new());
+
+    let mut rect_builder: HashMap<Plate, CellRect> = HashMap::new();
+    let mut prev_row: Vec<Plate> = Vec::new();
+    let mut rects: Vec<CellRect> = Vec::new();
+    for (y, current_row) in (min.y..).zip(plate_stack) {
+        for prev_plate in &prev_row {
+            if !current_row.contains(prev_plate) {
+                // remove the finished rect so that the same plate in the future starts a new rect
+                if let Some(rect) = rect_builder.remove(prev_plate) {
+                    rects.push(rect);
+                }
+            }
+        }
+        for plate in &current_row {
+            rect_builder
+                .entry(plate.clone())
+                .and_modify(|e| e.top += 1)
+                .or_insert(CellRect {bottom: y, top: y, left: plate.left, right: plate.right});
+        }
+        prev_row = current_row;
+    }
+    rects
+}
+
+/// Spawns merged colliders for the IntGrid cells listed in the [`TerrainTable`] of the balance.
+/// Cells of different values merge when their terrain is the same, never across levels or layers.
+/// The colliders are children of the level, so they are positioned relative to it
+/// and despawned together with it.
+pub fn spawn_terrain_colliders(
+    mut commands: Commands,
+    balance: Res<GameBalance>,
+    tiles: Query<(&GridCoords, &IntGridCell, &Parent), Added<IntGridCell>>,
+    layers: Query<(&LayerMetadata, &Parent)>,
+) {
+    if tiles.is_empty() {return}
+    // (level, layer, cell size, terrain) and the cells to merge
+    let mut groups: Vec<(Entity, Entity, i32, Terrain, HashSet<IVec2>)> = Vec::new();
+    for (coords, cell, layer) in tiles.iter() {
+        // an intgrid tile's parent is its layer, the layer's parent is the level
+        let Ok((metadata, level)) = layers.get(layer.get()) else {continue};
+        let Some(terrain) = balance.terrain.get(&metadata.identifier, cell.value) else {continue};
+        let key = (level.get(), layer.get(), *terrain);
+        let index = match groups.iter().position(|(l, y, _, t, _)| (*l, *y, *t) == key) {
+            Some(index) => index,
+            None => {
+                groups.push((key.0, key.1, metadata.grid_size, key.2, HashSet::new()));
+                groups.len() - 1
+            }
+        };
+        groups[index].4.insert(IVec2::new(coords.x, coords.y));
+    }
+
+    for (level_entity, _, grid_size, terrain, cells) in groups {
+        let grid_size = grid_size as f32;
+        commands.entity(level_entity).with_children(|level| {
+            for rect in merge_cells(&cells) {
+                let mut collider = level.spawn((
+                    Collider::cuboid(
+                        (rect.right - rect.left + 1) as f32 * grid_size / 2.,
+                        (rect.top - rect.bottom + 1) as f32 * grid_size / 2.,
+                    ),
+                    RigidBody::Fixed,
+                    Structure,
+                    CollisionGroups::new(Group::from_bits(terrain.groups).unwrap(), Group::ALL),
+                    Friction::new(terrain.friction),
+                    TransformBundle::from_transform(Transform::from_xyz(
+                        (rect.left + rect.right + 1) as f32 * grid_size / 2.,
+                        (rect.bottom + rect.top + 1) as f32 * grid_size / 2.,
+                        0.,
+                    )),
+                    Name::new("COLLIDER"),
+                ));
+                if terrain.sensor {
+                    collider.insert(Sensor);
+                }
+            }
+        });
+    }
+}
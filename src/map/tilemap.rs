@@ -1,102 +1,257 @@
-use bevy::{math::{ivec2, uvec2, vec2, vec3}, prelude::*, utils::{HashMap, HashSet}};
-use bevy_ecs_ldtk::prelude::*;
+use std::sync::Arc;
+
+use bevy::{math::{ivec2, uvec2, vec2, vec3}, prelude::*, utils::HashMap};
+use bevy_ecs_ldtk::{ldtk::{Level, Type}, prelude::*};
 use bevy_light_2d::light::PointLight2d;
-use bevy_rapier2d::{dynamics::RigidBody, geometry::{Collider, Friction}, prelude::{CollisionGroups, Group}};
 use noise::{core::perlin, NoiseFn, Perlin};
 use rand::Rng;
 use bevy_easings::*;
 
-use crate::{core::{camera::plugin::{CameraController, MainCamera}, functions::TextureAtlasLayoutHandles, post_processing::PostProcessUniform}, player::{components::Player, systems::{RAYCASTABLE_STRUCT_CG, STRUCTURES_CG}}, stuff::fire_bundle, DayCycle};
+use crate::{balance::GameBalance, core::{camera::plugin::{CameraController, MainCamera}, functions::TextureAtlasLayoutHandles}, npc::{components::NpcPath, navmesh::Navigation}, player::components::Player, rng::GameRng, screen::Screen, settings::Settings, stuff::fire_bundle, DayCycle};
+
+use super::{campaign::Campaign, plugin::{MapPath, TrespassableCells}};
 
 #[derive(Component)]
 pub struct Structure;
 
-pub fn pre_setup(
+/// Spawns the first level for a new run, grids are rebuilt from scratch once it is in.
+pub fn spawn_map(
     mut commands: Commands,
-    asset_server: Res<AssetServer>
+    asset_server: Res<AssetServer>,
+    map_path: Res<MapPath>,
 ){
-    let ldtk_handle = asset_server.load("map/larger_map.ldtk");
-    commands.spawn(LdtkWorldBundle {
-        ldtk_handle,
-        transform: Transform::from_translation(Vec3::Z * -11.),
-        ..Default::default()
-    });
-    commands.insert_resource(TransformToGrid{
-        height: 0.,
-        transform: vec2(0., 0.),
-        cell_size: vec2(16., 16.),
-        ready: false,
-        grid_size: ivec2(0, 0)
-    });
+    let campaign = Campaign::default();
+    commands.insert_resource(campaign.level_selection());
+    commands.insert_resource(campaign);
+    let ldtk_handle = asset_server.load(map_path.0.clone());
+    commands.spawn((
+        LdtkWorldBundle {
+            ldtk_handle,
+            transform: Transform::from_translation(Vec3::Z * -11.),
+            ..Default::default()
+        },
+        StateScoped(Screen::Playing),
+    ));
+    commands.insert_resource(TransformToGrid::default());
+    commands.insert_resource(TrespassableCells::default());
 }
 
-pub fn watcher (
-    mut commands: Commands,
-    res: Res<TransformToGrid>,
+/// Level geometry changes when the `.ldtk` file is hot reloaded, or a level is respawned or unloaded.
+/// A level's grid is dropped until it is in place again, while the current level is missing the
+/// grids are not ready. Merged colliders are children of their level, they go away with it and
+/// are rebuilt from the new tiles.
+pub fn invalidate_level_geometry(
+    mut asset_events: EventReader<AssetEvent<LdtkProject>>,
+    mut level_events: EventReader<LevelEvent>,
+    mut transformer: ResMut<TransformToGrid>,
+    mut trespassable: ResMut<TrespassableCells>,
+    paths: Query<&mut NpcPath>,
+){
+    let modified = asset_events.read().filter(|e| matches!(e, AssetEvent::Modified { .. })).count() > 0;
+    let mut removed = false;
+    for event in level_events.read() {
+        if let LevelEvent::SpawnTriggered(iid) | LevelEvent::Despawned(iid) = event {
+            removed |= transformer.levels.remove(iid).is_some();
+            transformer.navigation.remove(iid);
+            trespassable.levels.remove(iid);
+        }
+    }
+    if modified {
+        transformer.levels.clear();
+        transformer.navigation.clear();
+        trespassable.levels.clear();
+    }
+    if !modified && !removed {return}
+    stitch_level_grids(&mut transformer, &mut trespassable, paths);
+}
+
+/// Adds the grid of every level once it is spawned and its transform is propagated, with the cells
+/// that block pathfinding in the balance's [`super::terrain::TerrainTable`]. The selected level becomes the current one.
+pub fn build_level_grids(
+    mut level_events: EventReader<LevelEvent>,
+    mut transformer: ResMut<TransformToGrid>,
+    mut trespassable: ResMut<TrespassableCells>,
+    level_selection: Res<LevelSelection>,
     ldtk_projects: Query<&Handle<LdtkProject>>,
     ldtk_project_assets: Res<Assets<LdtkProject>>,
-    levels: Query<(&LevelIid, &GlobalTransform)>,
+    levels: Query<(Entity, &LevelIid, &GlobalTransform)>,
+    balance: Res<GameBalance>,
+    tiles: Query<(&GridCoords, &IntGridCell, &Parent)>,
+    layers: Query<(&LayerMetadata, &Parent)>,
+    paths: Query<&mut NpcPath>,
 ){
-    if res.ready {return;}
-    for (level_iid, level_transform) in levels.iter() {
-        let ldtk_project = ldtk_project_assets
-            .get(ldtk_projects.single())
-            .expect("ldtk project should be loaded before player is spawned");
-        let level = ldtk_project
-            .get_raw_level_by_iid(level_iid.get())
-            .expect("level should exist in only project");
-        commands.insert_resource(TransformToGrid{
-            height: level.px_hei as f32,
-            transform: level_transform.translation().xy(),
-            cell_size: vec2(16., 16.),
-            ready: true,
-            grid_size: ivec2(level.px_wid / 16, level.px_hei / 16)
-        });
-        return;
+    let transformed: Vec<LevelIid> = level_events.read().filter_map(|e| match e {
+        LevelEvent::Transformed(iid) => Some(iid.clone()),
+        _ => None,
+    }).collect();
+    if transformed.is_empty() {return}
+    let Some(project) = ldtk_projects.get_single().ok().and_then(|handle| ldtk_project_assets.get(handle)) else {return};
+
+    for (level_entity, iid, level_transform) in levels.iter() {
+        if !transformed.contains(iid) {continue}
+        let Some(level) = project.get_raw_level_by_iid(iid.get()) else {continue};
+        let grid = LevelGrid::new(level, level_transform.translation().xy());
+        let mut cells = vec![vec![true; grid.grid_size.y as usize]; grid.grid_size.x as usize];
+        for (coords, cell, layer) in tiles.iter() {
+            // an intgrid tile's parent is its layer, the layer's parent is the level
+            let Ok((metadata, level)) = layers.get(layer.get()) else {continue};
+            if level.get() != level_entity || metadata.grid_size as f32 != grid.cell_size.x {continue}
+            if !balance.terrain.get(&metadata.identifier, cell.value).is_some_and(|t| t.blocks_path) {continue}
+            let pos = ivec2(coords.x, grid.grid_size.y - coords.y - 1);
+            if let Some(cell) = cells.get_mut(pos.x as usize).and_then(|column| column.get_mut(pos.y as usize)) {
+                *cell = false;
+            }
+        }
+        transformer.levels.insert(iid.clone(), grid);
+        transformer.navigation.insert(iid.clone(), Navigation::of(level));
+        trespassable.levels.insert(iid.clone(), cells);
     }
+    transformer.current = project.iter_raw_levels_with_indices()
+        .find(|(indices, level)| level_selection.is_match(indices, level))
+        .map(|(_, level)| LevelIid::new(level.iid.clone()));
+    stitch_level_grids(&mut transformer, &mut trespassable, paths);
 }
 
-#[derive(Resource)]
-pub struct TransformToGrid{
+/// Lays the cells of every loaded level out on one grid that covers them all, so paths can
+/// cross level borders. Levels with another cell size than the current one are left out,
+/// so are the gaps between levels. Paths found on the old grid are dropped when it moves.
+pub fn stitch_level_grids(
+    transformer: &mut TransformToGrid,
+    trespassable: &mut TrespassableCells,
+    mut paths: Query<&mut NpcPath>,
+){
+    let stitched = transformer.stitch(trespassable);
+    transformer.ready = stitched.is_some();
+    trespassable.ready = stitched.is_some();
+    let (grid, cells) = stitched.unwrap_or_default();
+    trespassable.cells = Arc::new(cells);
+    if transformer.stitched != grid {
+        for mut path in paths.iter_mut() {
+            path.path = None;
+        }
+    }
+    if transformer.ready {
+        info!("Trespassable cells stitched from {} levels", transformer.levels.len());
+    }
+    transformer.stitched = grid;
+}
+
+/// Grid of one spawned level, or of every loaded level stitched together. Cells are counted from
+/// the top left corner, their size is the `grid_size` of the level's IntGrid layers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelGrid{
     height: f32,
     transform: Vec2,
-    cell_size: Vec2,
+    pub cell_size: Vec2,
     pub grid_size: IVec2,
-    pub ready: bool
 }
 
-impl TransformToGrid{
+impl Default for LevelGrid {
+    fn default() -> Self {
+        LevelGrid::from_corner(Vec2::ZERO, Vec2::splat(16.), IVec2::ZERO)
+    }
+}
+
+impl LevelGrid{
+    /// `transform` is the world position of the level's bottom left corner
+    pub fn new(level: &Level, transform: Vec2) -> Self {
+        let cell = level.layer_instances.iter().flatten()
+            .find(|layer| layer.layer_instance_type == Type::IntGrid)
+            .map_or(16, |layer| layer.grid_size);
+        LevelGrid{
+            height: level.px_hei as f32,
+            transform,
+            cell_size: Vec2::splat(cell as f32),
+            grid_size: ivec2(level.px_wid / cell, level.px_hei / cell),
+        }
+    }
+    /// `grid_size` cells of `cell_size` with the bottom left corner at `transform`
+    pub fn from_corner(transform: Vec2, cell_size: Vec2, grid_size: IVec2) -> Self {
+        LevelGrid{height: grid_size.y as f32 * cell_size.y, transform, cell_size, grid_size}
+    }
+    pub fn min(&self) -> Vec2{
+        self.transform
+    }
+    pub fn max(&self) -> Vec2{
+        self.transform + vec2(self.grid_size.x as f32 * self.cell_size.x, self.height)
+    }
+    pub fn contains(&self, position: Vec2) -> bool{
+        let (min, max) = (self.min(), self.max());
+        position.x >= min.x && position.y >= min.y && position.x < max.x && position.y < max.y
+    }
     pub fn from_world(&self, position: Vec2) -> Vec2{
         ((vec2(0., self.height) + self.transform) - position) / self.cell_size * vec2(-1., 1.)
     }
     pub fn from_world_i32(&self, position: Vec2) -> IVec2{
-        (((vec2(0., self.height) + self.transform) - position) / self.cell_size * vec2(-1., 1.)).floor().as_ivec2()
+        self.from_world(position).floor().as_ivec2()
     }
     pub fn to_world(&self, position: IVec2) -> Vec2{
         (vec2(0., self.height) + self.transform) - position.as_vec2() * self.cell_size * vec2(-1., 1.) + self.cell_size * vec2(0.5, -0.5)
-    } 
+    }
 }
 
-
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
-pub struct TileObsticle;
-
-#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
-pub struct TileObsticleBundle {
-    obsticle: TileObsticle,
+/// Grids of every loaded level. [`TrespassableCells`] and the conversions here are on the grid
+/// stitched from all of them, [`TransformToGrid::level_at`] finds the level a position lies in.
+#[derive(Resource, Default, Clone)]
+pub struct TransformToGrid{
+    pub levels: HashMap<LevelIid, LevelGrid>,
+    pub navigation: HashMap<LevelIid, Navigation>,
+    /// The selected level, its neighbors are loaded around it
+    pub current: Option<LevelIid>,
+    pub stitched: LevelGrid,
+    /// The current level's grid is built
+    pub ready: bool
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
-pub struct RaycastableTileObsticle;
-
-#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
-pub struct RaycastableTileObsticleBundle {
-    obsticle: RaycastableTileObsticle,
+impl TransformToGrid{
+    pub fn current_grid(&self) -> Option<&LevelGrid>{
+        self.current.as_ref().and_then(|iid| self.levels.get(iid))
+    }
+    /// How NPCs find paths on the stitched grid, as the current level asks
+    pub fn current_navigation(&self) -> Navigation{
+        self.current.as_ref().and_then(|iid| self.navigation.get(iid)).copied().unwrap_or_default()
+    }
+    pub fn level_at(&self, position: Vec2) -> Option<(&LevelIid, &LevelGrid)>{
+        self.levels.iter().find(|(_, grid)| grid.contains(position))
+    }
+    /// The grid covering every loaded level with the current level's cell size and its cells,
+    /// none until the current level is loaded
+    pub fn stitch(&self, trespassable: &TrespassableCells) -> Option<(LevelGrid, Vec<Vec<bool>>)>{
+        let cell_size = self.current_grid()?.cell_size;
+        let grids: Vec<(&LevelIid, &LevelGrid)> = self.levels.iter().filter(|(_, grid)| grid.cell_size == cell_size).collect();
+        let min = grids.iter().map(|(_, grid)| grid.min()).reduce(Vec2::min)?;
+        let max = grids.iter().map(|(_, grid)| grid.max()).reduce(Vec2::max)?;
+        let stitched = LevelGrid::from_corner(min, cell_size, ((max - min) / cell_size).round().as_ivec2());
+        let mut cells = vec![vec![false; stitched.grid_size.y as usize]; stitched.grid_size.x as usize];
+        for (iid, grid) in grids {
+            let Some(level_cells) = trespassable.levels.get(iid) else {continue};
+            let offset = stitched.from_world_i32(grid.to_world(IVec2::ZERO));
+            for (x, column) in level_cells.iter().enumerate() {
+                for (y, cell) in column.iter().enumerate() {
+                    let pos = offset + ivec2(x as i32, y as i32);
+                    if let Some(stitched_cell) = cells.get_mut(pos.x as usize).and_then(|column| column.get_mut(pos.y as usize)) {
+                        *stitched_cell = *cell;
+                    }
+                }
+            }
+        }
+        Some((stitched, cells))
+    }
+    pub fn from_world(&self, position: Vec2) -> Vec2{
+        self.stitched.from_world(position)
+    }
+    pub fn from_world_i32(&self, position: Vec2) -> IVec2{
+        self.stitched.from_world_i32(position)
+    }
+    pub fn to_world(&self, position: IVec2) -> Vec2{
+        self.stitched.to_world(position)
+    }
 }
 
+
+/// Trees collide through the [`super::terrain::TerrainTable`] like any other tile, this only adds the sprite
 #[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
 pub struct TiledTreeBundle {
-    obsticle: TileObsticle,
     tree: AnimatedTree
 }
 
@@ -164,10 +319,10 @@ pub fn update_emitter_tiles(
 
     mut emitters: Query<&mut PointLight2d>,
     
-    post_process: Query<&PostProcessUniform>,
     daycycle: Res<DayCycle>,
     asset_server: Res<AssetServer>,
-    time: Res<Time<Virtual>>
+    time: Res<Time<Virtual>>,
+    mut rng: ResMut<GameRng>,
 ){
     let max_intensity = 0.8;
     let default_radius = 150.;
@@ -220,7 +375,7 @@ pub fn update_emitter_tiles(
                 },
                 LightEmitter,
             )).insert(
-                fire_bundle(&asset_server, &mut handles, rand::thread_rng().gen_range(0..19))
+                fire_bundle(&asset_server, &mut handles, rng.cosmetics.gen_range(0..19))
             ).insert(Transform::from_translation(vec3(0., 10., 1.)));
         });
         commands.entity(e).insert(Transform::from_xyz(0., 0., 0.));
@@ -247,7 +402,7 @@ pub fn update_emitter_tiles(
                 },
                 LightEmitter,
             )).insert(
-                fire_bundle(&asset_server, &mut handles, rand::thread_rng().gen_range(0..19))
+                fire_bundle(&asset_server, &mut handles, rng.cosmetics.gen_range(0..19))
             ).insert(Transform::from_translation(vec3(0., 10., 1.)));
         });
         commands.entity(e).insert(Transform::from_xyz(0., 0., 0.));
@@ -273,18 +428,17 @@ pub fn update_emitter_tiles(
                 },
                 LightEmitter,
             )).insert(
-                fire_bundle(&asset_server, &mut handles, rand::thread_rng().gen_range(0..19))
+                fire_bundle(&asset_server, &mut handles, rng.cosmetics.gen_range(0..19))
             ).insert(Transform::from_translation(vec3(0., 12., -9.)));
         });
     }
 
-    let p = post_process.single();
     for mut l in emitters.iter_mut(){
 
-        l.intensity = p.daytime.powi(2) * max_intensity;
+        l.intensity = daycycle.daytime.powi(2) * max_intensity;
         
         if (time.elapsed_seconds() * 16.).round() as usize % 2 == 0 {
-            l.radius = default_radius + rand::thread_rng().gen_range(0..100) as f32 * 0.01 * 0.4 * default_radius;
+            l.radius = default_radius + rng.cosmetics.gen_range(0..100) as f32 * 0.01 * 0.4 * default_radius;
         }
     }
     if daycycle.is_night || daycycle.is_translating{
@@ -299,22 +453,21 @@ pub fn update_animated_trees(
     mut tree_q: Query<(&GlobalTransform, &mut Transform, &AnimatedTreePart, &mut Sprite), Without<Player>>,
     time: Res<Time<Virtual>>,
     mut perlin: Local<Option<Perlin>>,
-    mut is_static: Local<bool>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    player: Query<&Transform, (With<Player>, Without<AnimatedTree>)>
+    settings: Res<Settings>,
+    player: Query<&Transform, (With<Player>, Without<AnimatedTree>)>,
+    mut rng: ResMut<GameRng>,
 ){
-    if keyboard.just_pressed(KeyCode::F4){
-        *is_static = !*is_static;
-        if *is_static {
+    if !settings.tree_sway {
+        if settings.is_changed() {
             tree_q.par_iter_mut().for_each(|(_, mut transform, tree, _)|{
                 if tree.0 == 0 {return;}
                 transform.rotation = Quat::from_rotation_z(0.);
             });
         }
+        return;
     }
-    if *is_static {return;}
 
-    if perlin.is_none(){*perlin = Some(Perlin::new(rand::thread_rng().gen::<u32>()))}
+    if perlin.is_none(){*perlin = Some(Perlin::new(rng.cosmetics.gen::<u32>()))}
     
     let pos = player.get_single();
     let pos = if let Ok(pos) = pos {pos.translation.xy()} else {Vec2::ZERO};
@@ -344,10 +497,11 @@ pub fn spawn_tile_tree(
     mut commands: Commands,
     tree_q: Query<Entity, Added<AnimatedTree>>,
     asset_server: Res<AssetServer>,
-    mut layout_handles: ResMut<TextureAtlasLayoutHandles>
+    mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
+    mut rng: ResMut<GameRng>,
 ) {
     if !tree_q.is_empty() {
-        let mut r = rand::thread_rng();
+        let r = &mut rng.cosmetics;
         for new_tree in tree_q.iter(){
             commands.entity(new_tree).with_children(|cmd|{
                 cmd.spawn((
@@ -399,444 +553,16 @@ pub fn spawn_tile_tree(
                 });
             });
         }
-        
-        /*level_query.iter().for_each(|(level_entity, level_iid)| {
-            let ldtk_project = ldtk_project_assets
-                .get(ldtk_projects.single())
-                .expect("Project should be loaded if level has spawned");
-
-            let level = ldtk_project
-                .as_standalone()
-                .get_loaded_level_by_iid(&level_iid.to_string())
-                .expect("Spawned level should exist in LDtk project");
-
-            let LayerInstance {
-                c_wid: width,
-                c_hei: height,
-                grid_size,
-                ..
-            } = level.layer_instances()[0];
-        });*/
     }
 }
 
+/// Keeps the camera inside the stitched grid of the levels loaded around the player, redone whenever it changes.
 pub fn setup_camera_bounds(
-    mut commands: Commands,
     mut cameras_q: Query<&mut CameraController>,
-    level_query: Query<(&Transform, &LevelIid)>,
-    ldtk_projects: Query<&Handle<LdtkProject>>,
-    level_selection: Res<LevelSelection>,
-    ldtk_project_assets: Res<Assets<LdtkProject>>,
-    mut done: Local<bool>
-) {
-    if *done {return}
-    for (level_transform, level_iid) in &level_query {
-        let ldtk_project = ldtk_project_assets
-            .get(ldtk_projects.single())
-            .expect("Project should be loaded if level has spawned");
-
-        let level = ldtk_project
-            .get_raw_level_by_iid(&level_iid.to_string())
-            .expect("Spawned level should exist in LDtk project");
-
-        if level_selection.is_match(&LevelIndices::default(), level) {
-            for mut cam in cameras_q.iter_mut(){
-                cam.corners = Some((
-                    level_transform.translation.xy(),
-                    level_transform.translation.xy() + vec2(level.px_wid as f32, level.px_hei as f32)
-                ));
-                *done = true;
-            }
-            
-            /*let level_ratio = level.px_wid as f32 / level.px_hei as f32;
-            orthographic_projection.viewport_origin = Vec2::ZERO;
-            if level_ratio > ASPECT_RATIO {
-                // level is wider than the screen
-                let height = (level.px_hei as f32 / 9.).round() * 9.;
-                let width = height * ASPECT_RATIO;
-                orthographic_projection.scaling_mode =
-                    bevy::render::camera::ScalingMode::Fixed { width, height };
-                camera_transform.translation.x =
-                    (player_translation.x - level_transform.translation.x - width / 2.)
-                        .clamp(0., level.px_wid as f32 - width);
-                camera_transform.translation.y = 0.;
-            } else {
-                // level is taller than the screen
-                let width = (level.px_wid as f32 / 16.).round() * 16.;
-                let height = width / ASPECT_RATIO;
-                orthographic_projection.scaling_mode =
-                    bevy::render::camera::ScalingMode::Fixed { width, height };
-                camera_transform.translation.y =
-                    (player_translation.y - level_transform.translation.y - height / 2.)
-                        .clamp(0., level.px_hei as f32 - height);
-                camera_transform.translation.x = 0.;
-            }
-
-            camera_transform.translation.x += level_transform.translation.x;
-            camera_transform.translation.y += level_transform.translation.y;*/
-        }
-    }
-}
-
-/// Spawns heron collisions for the walls of a level
-///
-/// You could just insert a ColliderBundle in to the WallBundle,
-/// but this spawns a different collider for EVERY wall tile.
-/// This approach leads to bad performance.
-///
-/// Instead, by flagging the wall tiles and spawning the collisions later,
-/// we can minimize the amount of colliding entities.
-///
-/// The algorithm used here is a nice compromise between simplicity, speed,
-/// and a small number of rectangle colliders.
-/// In basic terms, it will:
-/// 1. consider where the walls are
-/// 2. combine wall tiles into flat "plates" in each individual row
-/// 3. combine the plates into rectangles across multiple rows wherever possible
-/// 4. spawn colliders for each rectangle
-pub fn spawn_tile_collision(
-    mut commands: Commands,
-    wall_query: Query<(&GridCoords, &Parent), Added<TileObsticle>>,
-    parent_query: Query<&Parent, Without<TileObsticle>>,
-    level_query: Query<(Entity, &LevelIid)>,
-    ldtk_projects: Query<&Handle<LdtkProject>>,
-    ldtk_project_assets: Res<Assets<LdtkProject>>,
+    transformer: Res<TransformToGrid>,
 ) {
-    /// Represents a wide wall that is 1 tile tall
-    /// Used to spawn wall collisions
-    #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
-    struct Plate {
-        left: i32,
-        right: i32,
-    }
-
-    /// A simple rectangle type representing a wall of any size
-    struct Rect {
-        left: i32,
-        right: i32,
-        top: i32,
-        bottom: i32,
-    }
-
-    // Consider where the walls are
-    // storing them as GridCoords in a HashSet for quick, easy lookup
-    //
-    // The key of this map will be the entity of the level the wall belongs to.
-    // This has two consequences in the resulting collision entities:
-    // 1. it forces the walls to be split along level boundaries
-    // 2. it lets us easily add the collision entities as children of the appropriate level entity
-    let mut level_to_wall_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
-
-    wall_query.iter().for_each(|(&grid_coords, parent)| {
-        // An intgrid tile's direct parent will be a layer entity, not the level entity
-        // To get the level entity, you need the tile's grandparent.
-        // This is where parent_query comes in.
-        if let Ok(grandparent) = parent_query.get(parent.get()) {
-            level_to_wall_locations
-                .entry(grandparent.get())
-                .or_default()
-                .insert(grid_coords);
-        }
-    });
-
-    if !wall_query.is_empty() {
-        level_query.iter().for_each(|(level_entity, level_iid)| {
-            if let Some(level_walls) = level_to_wall_locations.get(&level_entity) {
-                let ldtk_project = ldtk_project_assets
-                    .get(ldtk_projects.single())
-                    .expect("Project should be loaded if level has spawned");
-
-                let level = ldtk_project
-                    .as_standalone()
-                    .get_loaded_level_by_iid(&level_iid.to_string())
-                    .expect("Spawned level should exist in LDtk project");
-
-                let LayerInstance {
-                    c_wid: width,
-                    c_hei: height,
-                    grid_size,
-                    ..
-                } = level.layer_instances()[0];
-                // combine wall tiles into flat "plates" in each individual row
-                let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
-
-                for y in 0..height {
-                    let mut row_plates: Vec<Plate> = Vec::new();
-                    let mut plate_start = None;
-
-                    // + 1 to the width so the algorithm "terminates" plates that touch the right edge
-                    for x in 0..width + 1 {
-                        match (plate_start, level_walls.contains(&GridCoords { x, y })) {
-                            (Some(s), false) => {
-                                row_plates.push(Plate {
-                                    left: s,
-                                    right: x - 1,
-                                });
-                                plate_start = None;
-                            }
-                            (None, true) => plate_start = Some(x),
-                            _ => (),
-                        }
-                    }
-
-                    plate_stack.push(row_plates);
-                }
-
-                // combine "plates" into rectangles across multiple rows
-                let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
-                let mut prev_row: Vec<Plate> = Vec::new();
-                let mut wall_rects: Vec<Rect> = Vec::new();
-
-                // an extra empty row so the algorithm "finishes" the rects that touch the top edge
-                plate_stack.push(Vec::new());
-
-                for (y, current_row) in plate_stack.into_iter().enumerate() {
-                    for prev_plate in &prev_row {
-                        if !current_row.contains(prev_plate) {
-                            // remove the finished rect so that the same plate in the future starts a new rect
-                            if let Some(rect) = rect_builder.remove(prev_plate) {
-                                wall_rects.push(rect);
-                            }
-                        }
-                    }
-                    for plate in &current_row {
-                        rect_builder
-                            .entry(plate.clone())
-                            .and_modify(|e| e.top += 1)
-                            .or_insert(Rect {
-                                bottom: y as i32,
-                                top: y as i32,
-                                left: plate.left,
-                                right: plate.right,
-                            });
-                    }
-                    prev_row = current_row;
-                }
-
-                commands.entity(level_entity).with_children(|level| {
-                    // Spawn colliders for every rectangle..
-                    // Making the collider a child of the level serves two purposes:
-                    // 1. Adjusts the transforms to be relative to the level for free
-                    // 2. the colliders will be despawned automatically when levels unload
-                    for wall_rect in wall_rects {
-                        level
-                            .spawn_empty()
-                            .insert(Collider::cuboid(
-                                (wall_rect.right as f32 - wall_rect.left as f32 + 1.)
-                                    * grid_size as f32
-                                    / 2.,
-                                (wall_rect.top as f32 - wall_rect.bottom as f32 + 1.)
-                                    * grid_size as f32
-                                    / 2.,
-                            ))
-                            .insert((
-                                RigidBody::Fixed,
-                                Structure,
-                                CollisionGroups::new(
-                                    Group::from_bits(STRUCTURES_CG).unwrap(),
-                                    Group::ALL,
-                                ),
-                            ))
-                            .insert(Friction::new(1.0))
-                            .insert(Transform::from_xyz(
-                                (wall_rect.left + wall_rect.right + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                (wall_rect.bottom + wall_rect.top + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                0.,
-                            ))
-                            .insert(Name::new("COLLIDER"))
-                            .insert(GlobalTransform::default());
-                    }
-                });
-            }
-        });
+    if !transformer.ready {return}
+    for mut cam in cameras_q.iter_mut(){
+        cam.corners = Some((transformer.stitched.min(), transformer.stitched.max()));
     }
 }
-
-#[derive(Component)]
-pub struct RaycastableHelp;
-
-pub fn spawn_raycastable_tile_collision(
-    mut commands: Commands,
-    wall_query: Query<(&GridCoords, &Parent), Added<RaycastableTileObsticle>>,
-    parent_query: Query<&Parent, Without<RaycastableTileObsticle>>,
-    level_query: Query<(Entity, &LevelIid)>,
-    ldtk_projects: Query<&Handle<LdtkProject>>,
-    ldtk_project_assets: Res<Assets<LdtkProject>>,
-) {
-    /// Represents a wide wall that is 1 tile tall
-    /// Used to spawn wall collisions
-    #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
-    struct Plate {
-        left: i32,
-        right: i32,
-    }
-
-    /// A simple rectangle type representing a wall of any size
-    struct Rect {
-        left: i32,
-        right: i32,
-        top: i32,
-        bottom: i32,
-    }
-
-    // Consider where the walls are
-    // storing them as GridCoords in a HashSet for quick, easy lookup
-    //
-    // The key of this map will be the entity of the level the wall belongs to.
-    // This has two consequences in the resulting collision entities:
-    // 1. it forces the walls to be split along level boundaries
-    // 2. it lets us easily add the collision entities as children of the appropriate level entity
-    let mut level_to_wall_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
-
-    wall_query.iter().for_each(|(&grid_coords, parent)| {
-        // An intgrid tile's direct parent will be a layer entity, not the level entity
-        // To get the level entity, you need the tile's grandparent.
-        // This is where parent_query comes in.
-        if let Ok(grandparent) = parent_query.get(parent.get()) {
-            level_to_wall_locations
-                .entry(grandparent.get())
-                .or_default()
-                .insert(grid_coords);
-        }
-    });
-
-    if !wall_query.is_empty() {
-        level_query.iter().for_each(|(level_entity, level_iid)| {
-            if let Some(level_walls) = level_to_wall_locations.get(&level_entity) {
-                let ldtk_project = ldtk_project_assets
-                    .get(ldtk_projects.single())
-                    .expect("Project should be loaded if level has spawned");
-
-                let level = ldtk_project
-                    .as_standalone()
-                    .get_loaded_level_by_iid(&level_iid.to_string())
-                    .expect("Spawned level should exist in LDtk project");
-
-                let LayerInstance {
-                    c_wid: width,
-                    c_hei: height,
-                    grid_size,
-                    ..
-                } = level.layer_instances()[0];
-                // combine wall tiles into flat "plates" in each individual row
-                let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
-
-                for y in 0..height {
-                    let mut row_plates: Vec<Plate> = Vec::new();
-                    let mut plate_start = None;
-
-                    // + 1 to the width so the algorithm "terminates" plates that touch the right edge
-                    for x in 0..width + 1 {
-                        match (plate_start, level_walls.contains(&GridCoords { x, y })) {
-                            (Some(s), false) => {
-                                row_plates.push(Plate {
-                                    left: s,
-                                    right: x - 1,
-                                });
-                                plate_start = None;
-                            }
-                            (None, true) => plate_start = Some(x),
-                            _ => (),
-                        }
-                    }
-
-                    plate_stack.push(row_plates);
-                }
-
-                // combine "plates" into rectangles across multiple rows
-                let mut rect_builder: HashMap<Plate, Rect> = HashMap::new();
-                let mut prev_row: Vec<Plate> = Vec::new();
-                let mut wall_rects: Vec<Rect> = Vec::new();
-
-                // an extra empty row so the algorithm "finishes" the rects that touch the top edge
-                plate_stack.push(Vec::new());
-
-                for (y, current_row) in plate_stack.into_iter().enumerate() {
-                    for prev_plate in &prev_row {
-                        if !current_row.contains(prev_plate) {
-                            // remove the finished rect so that the same plate in the future starts a new rect
-                            if let Some(rect) = rect_builder.remove(prev_plate) {
-                                wall_rects.push(rect);
-                            }
-                        }
-                    }
-                    for plate in &current_row {
-                        rect_builder
-                            .entry(plate.clone())
-                            .and_modify(|e| e.top += 1)
-                            .or_insert(Rect {
-                                bottom: y as i32,
-                                top: y as i32,
-                                left: plate.left,
-                                right: plate.right,
-                            });
-                    }
-                    prev_row = current_row;
-                }
-
-                commands.entity(level_entity).with_children(|level| {
-                    // Spawn colliders for every rectangle..
-                    // Making the collider a child of the level serves two purposes:
-                    // 1. Adjusts the transforms to be relative to the level for free
-                    // 2. the colliders will be despawned automatically when levels unload
-                    for wall_rect in wall_rects {
-                        level
-                            .spawn_empty()
-                            .insert(Collider::cuboid(
-                                (wall_rect.right as f32 - wall_rect.left as f32 + 1.)
-                                    * grid_size as f32
-                                    / 2.,
-                                (wall_rect.top as f32 - wall_rect.bottom as f32 + 1.)
-                                    * grid_size as f32
-                                    / 2.,
-                            ))
-                            .insert((
-                                RigidBody::Fixed,
-                                RaycastableHelp,
-                                CollisionGroups::new(
-                                    Group::from_bits(RAYCASTABLE_STRUCT_CG).unwrap(),
-                                    Group::ALL,
-                                ),
-                            ))
-                            .insert(Friction::new(1.0))
-                            .insert(Transform::from_xyz(
-                                (wall_rect.left + wall_rect.right + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                (wall_rect.bottom + wall_rect.top + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                0.,
-                            ))
-                            .insert(Name::new("COLLIDER"))
-                            .insert(GlobalTransform::default());
-                    }
-                });
-            }
-        });
-    }
-}
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
@@ -0,0 +1,119 @@
+use bevy::{prelude::*, ui::UiSystem};
+
+use crate::{controls::{Action, ActionState, ActionSystem}, sounds::components::PlaySoundEvent};
+
+/// Buttons that can be reached with the d-pad or the keyboard. Hovering with the mouse
+/// moves the same focus, and the focused button is the highlighted one.
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<MenuFocus>()
+        .add_event::<ButtonPressed>()
+        .add_systems(PreUpdate, (hover_focus, navigate_focus, press_focused).chain().after(ActionSystem).after(UiSystem::Focus))
+        .add_systems(PostUpdate, highlight_focus);
+    }
+}
+
+/// A menu button. Buttons are ordered top to bottom, then left to right, as laid out on screen.
+#[derive(Component)]
+pub struct Focusable;
+
+#[derive(Resource, Default, Debug)]
+pub struct MenuFocus(pub Option<Entity>);
+
+/// A [`Focusable`] was clicked, or was focused when `Interact` was pressed.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ButtonPressed(pub Entity);
+
+const BUTTON_IMAGE: &str = "button.png";
+const FOCUSED_BUTTON_IMAGE: &str = "select_button.png";
+
+fn hover_focus(
+    buttons: Query<(Entity, &Interaction), (With<Focusable>, Changed<Interaction>)>,
+    mut focus: ResMut<MenuFocus>,
+    mut pressed: EventWriter<ButtonPressed>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
+) {
+    for (entity, interaction) in buttons.iter() {
+        match *interaction {
+            Interaction::Pressed => {
+                focus.0 = Some(entity);
+                pressed.send(ButtonPressed(entity));
+            }
+            Interaction::Hovered => {
+                if focus.0 != Some(entity) {
+                    focus.0 = Some(entity);
+                    play_sound.send(PlaySoundEvent::Select);
+                }
+            }
+            Interaction::None => {}
+        }
+    }
+}
+
+fn navigate_focus(
+    buttons: Query<(Entity, &GlobalTransform, Option<&InheritedVisibility>), With<Focusable>>,
+    added: Query<(), Added<Focusable>>,
+    actions: Res<ActionState>,
+    mut focus: ResMut<MenuFocus>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
+) {
+    // buttons of a popup hidden under another one are skipped
+    let mut order: Vec<(Entity, Vec2)> = buttons.iter()
+        .filter(|(_, _, visibility)| visibility.map_or(true, |v| v.get()))
+        .map(|(e, t, _)| (e, t.translation().xy()))
+        .collect();
+    order.sort_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+    let current = focus.0.and_then(|focused| order.iter().position(|(e, _)| *e == focused));
+    // the focused button was despawned or hidden with its menu
+    let lost = current.is_none() && focus.0.is_some();
+    if lost {
+        focus.0 = None;
+    }
+    if order.is_empty() {return}
+
+    let step = if actions.just_pressed(Action::MoveDown) || actions.just_pressed(Action::MoveRight) {
+        1
+    } else if actions.just_pressed(Action::MoveUp) || actions.just_pressed(Action::MoveLeft) {
+        -1
+    } else {
+        0
+    };
+    let next = match current {
+        Some(i) if step != 0 => (i as i32 + step).rem_euclid(order.len() as i32) as usize,
+        Some(_) => return,
+        // a menu just opened or closed, or nothing was focused yet
+        None if step != 0 || lost || !added.is_empty() => 0,
+        None => return,
+    };
+    focus.0 = Some(order[next].0);
+    if step != 0 {
+        play_sound.send(PlaySoundEvent::Select);
+    }
+}
+
+fn press_focused(
+    actions: Res<ActionState>,
+    focus: Res<MenuFocus>,
+    mut pressed: EventWriter<ButtonPressed>,
+) {
+    if !actions.just_pressed(Action::Interact) {return}
+    if let Some(entity) = focus.0 {
+        pressed.send(ButtonPressed(entity));
+    }
+}
+
+fn highlight_focus(
+    mut buttons: Query<(Entity, &mut UiImage), With<Focusable>>,
+    added: Query<(), Added<Focusable>>,
+    focus: Res<MenuFocus>,
+    asset_server: Res<AssetServer>,
+) {
+    if !focus.is_changed() && added.is_empty() {return}
+    for (entity, mut image) in buttons.iter_mut() {
+        let path = if focus.0 == Some(entity) {FOCUSED_BUTTON_IMAGE} else {BUTTON_IMAGE};
+        *image = UiImage::from(asset_server.load(path));
+    }
+}
@@ -0,0 +1,240 @@
+use std::sync::{Arc, OnceLock};
+
+use bevy::prelude::*;
+
+use crate::{
+    balance::GameBalance, core::functions::TextureAtlasLayoutHandles, map::tilemap::TransformToGrid,
+    player::{components::Player, systems::PlayerController}, rng::GameRng,
+    stuff::{spawn_question_particle, spawn_warn_particle}, systems::DayCycle,
+};
+
+use super::{components::{NpcPath, NpcState}, perception::Perception, schedule::Activity, squad::Role};
+
+/// NPCs further than this from the player stand still and don't think
+pub const ACTIVE_DIST: f32 = 1000.;
+/// Civilians closer than this hit instead of chasing
+pub const MELEE_RANGE: f32 = 16.;
+
+/// What an NPC knows when its [`Brain`] decides, refreshed every frame by [`update_blackboards`]
+#[derive(Component, Default, Debug)]
+pub struct Blackboard {
+    pub pos: Vec2,
+    pub ipos: IVec2,
+    pub player_pos: Vec2,
+    pub player_ipos: IVec2,
+    pub player_vel: Vec2,
+    pub player_dist: f32,
+    pub sees_player: bool,
+    pub is_night: bool,
+    pub dead: bool,
+    /// The player is alive and close enough to bother
+    pub active: bool,
+    /// Where the player was last seen or heard, until a behavior gives up on it
+    pub last_known: Option<Vec2>,
+    /// How the player moved when last seen
+    pub last_vel: Vec2,
+    /// Where the player vanished, searched around until the search runs out
+    pub search_at: Option<Vec2>,
+    /// Where the squad wants this hunter to stand
+    pub slot: Option<IVec2>,
+    /// What the hunter does for its squad
+    pub role: Option<Role>,
+    /// The squad falls back before dawn
+    pub retreating: bool,
+    /// Where the civilian saw the player kill, until it is reported
+    pub witnessed: Option<Vec2>,
+    /// The civilian fights at night too
+    pub armed: bool,
+    /// What the civilian's schedule says now
+    pub activity: Activity,
+    /// Where the schedule wants the civilian
+    pub destination: Option<IVec2>,
+    /// The running behavior has to finish before another one is picked
+    pub busy: bool,
+}
+
+/// The leaves of the behavior trees, each runs as its own system
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Behavior {
+    #[default]
+    Wander,
+    Flee,
+    ChaseTo,
+    ThrowAt,
+    Melee,
+    Investigate,
+    Search,
+    Surround,
+    Retreat,
+    Report,
+    Commute,
+    Die,
+}
+
+impl Behavior {
+    /// What the pathfinding, steering and perception see the NPC doing
+    pub fn state(self) -> NpcState {
+        match self {
+            Behavior::Wander | Behavior::Commute => NpcState::Chill,
+            Behavior::Flee | Behavior::Retreat => NpcState::Escape,
+            Behavior::ChaseTo => NpcState::Chase,
+            Behavior::ThrowAt | Behavior::Melee | Behavior::Surround => NpcState::Attack,
+            Behavior::Investigate | Behavior::Search | Behavior::Report => NpcState::Look,
+            Behavior::Die => NpcState::Dead,
+        }
+    }
+}
+
+pub type Condition = fn(&Blackboard, &GameBalance) -> bool;
+
+/// A behavior tree that picks the behavior to run this frame
+pub enum Node {
+    /// The first child that picks something
+    Selector(Vec<Node>),
+    /// The child, while the condition holds
+    When(Condition, Box<Node>),
+    Leaf(Behavior),
+}
+
+impl Node {
+    pub fn when(condition: Condition, node: Node) -> Node {
+        Node::When(condition, Box::new(node))
+    }
+
+    pub fn pick(&self, blackboard: &Blackboard, balance: &GameBalance) -> Option<Behavior> {
+        match self {
+            Node::Selector(children) => children.iter().find_map(|child| child.pick(blackboard, balance)),
+            Node::When(condition, child) => condition(blackboard, balance).then(|| child.pick(blackboard, balance)).flatten(),
+            Node::Leaf(behavior) => Some(*behavior),
+        }
+    }
+}
+
+/// Runs away from the vampire at night and mobs them during the day, armed ones at any time.
+/// Witnesses of a kill go tell someone first. Otherwise follows the schedule.
+pub fn civilian_brain() -> Node {
+    Node::Selector(vec![
+        Node::when(|bb, _| bb.dead, Node::Leaf(Behavior::Die)),
+        Node::when(|bb, _| bb.witnessed.is_some(), Node::Leaf(Behavior::Report)),
+        Node::when(|bb, _| bb.is_night && !bb.armed && bb.last_known.is_some(), Node::Leaf(Behavior::Flee)),
+        Node::when(|bb, _| (!bb.is_night || bb.armed) && bb.sees_player, Node::Selector(vec![
+            Node::when(|bb, _| bb.player_dist < MELEE_RANGE, Node::Leaf(Behavior::Melee)),
+            Node::Leaf(Behavior::ChaseTo),
+        ])),
+        Node::when(away_from_destination, Node::Leaf(Behavior::Commute)),
+        Node::Leaf(Behavior::Wander),
+    ])
+}
+
+/// Going home, or further from the destination than civilians stroll around it
+fn away_from_destination(bb: &Blackboard, balance: &GameBalance) -> bool {
+    bb.destination.is_some_and(|destination| bb.activity == Activity::Home
+        || (destination - bb.ipos).abs().max_element() > balance.schedule.stray)
+}
+
+/// Keeps its distance and throws, or takes the spot its squad gave it. Goes where the player was
+/// last seen and searches around.
+pub fn hunter_brain() -> Node {
+    Node::Selector(vec![
+        Node::when(|bb, _| bb.dead, Node::Leaf(Behavior::Die)),
+        Node::when(|bb, _| bb.retreating, Node::Leaf(Behavior::Retreat)),
+        Node::when(|bb, _| bb.sees_player, Node::Selector(vec![
+            Node::when(|bb, balance| bb.player_dist < balance.hunter.threshold, Node::Leaf(Behavior::Flee)),
+            Node::when(|bb, _| bb.slot.is_some(), Node::Leaf(Behavior::Surround)),
+            Node::when(|bb, balance| bb.player_dist > balance.hunter.upp_threshold, Node::Leaf(Behavior::ChaseTo)),
+            Node::Leaf(Behavior::ThrowAt),
+        ])),
+        Node::when(|bb, _| bb.last_known.is_some(), Node::Leaf(Behavior::Investigate)),
+        Node::when(|bb, _| bb.search_at.is_some(), Node::Leaf(Behavior::Search)),
+        Node::Leaf(Behavior::Wander),
+    ])
+}
+
+/// An archetype's tree and the behavior it picked last
+#[derive(Component)]
+pub struct Brain {
+    pub tree: Arc<Node>,
+    pub current: Behavior,
+}
+
+impl Brain {
+    pub fn new(tree: Arc<Node>) -> Self {
+        Brain {tree, current: Behavior::default()}
+    }
+
+    /// Every civilian shares one [`civilian_brain`], built on the first spawn
+    pub fn civilian() -> Self {
+        static TREE: OnceLock<Arc<Node>> = OnceLock::new();
+        Brain::new(TREE.get_or_init(|| Arc::new(civilian_brain())).clone())
+    }
+
+    /// Every hunter shares one [`hunter_brain`], built on the first spawn
+    pub fn hunter() -> Self {
+        static TREE: OnceLock<Arc<Node>> = OnceLock::new();
+        Brain::new(TREE.get_or_init(|| Arc::new(hunter_brain())).clone())
+    }
+}
+
+pub fn update_blackboards(
+    mut npcs: Query<(&Transform, &NpcState, &mut Perception, &mut Blackboard)>,
+    player: Query<(&Transform, &PlayerController, &Player)>,
+    transformer: Res<TransformToGrid>,
+    day_cycle: Res<DayCycle>,
+) {
+    let player = player.get_single().ok();
+    for (transform, state, mut perception, mut bb) in npcs.iter_mut() {
+        bb.pos = transform.translation.xy();
+        bb.ipos = transformer.from_world_i32(bb.pos);
+        bb.dead = *state == NpcState::Dead;
+        bb.is_night = day_cycle.is_night;
+        bb.sees_player = perception.sees_player;
+        if let Some(alerted) = perception.alerted.take() {
+            bb.last_known = Some(alerted);
+        }
+        let Some((player_transform, controller, player)) = player else {
+            bb.active = false;
+            continue;
+        };
+        bb.player_pos = player_transform.translation.xy();
+        bb.player_ipos = transformer.from_world_i32(bb.player_pos);
+        bb.player_vel = controller.accumulated_velocity;
+        if bb.sees_player {
+            bb.last_vel = bb.player_vel;
+        }
+        bb.player_dist = bb.pos.distance(bb.player_pos);
+        bb.active = !player.is_dead && bb.player_dist <= ACTIVE_DIST;
+    }
+}
+
+/// Picks every NPC's behavior. Switching drops the old path and shows how the NPC feels about it.
+pub fn think(
+    mut commands: Commands,
+    mut npcs: Query<(&mut Brain, &Blackboard, &mut NpcState, &mut NpcPath)>,
+    mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
+    asset_server: Res<AssetServer>,
+    balance: Res<GameBalance>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (mut brain, bb, mut state, mut path) in npcs.iter_mut() {
+        // the dying keep dying out of sight
+        if !bb.active && !bb.dead {continue}
+        let next = if bb.busy && !bb.dead {brain.current} else {brain.tree.pick(bb, &balance).unwrap_or_default()};
+        if next != brain.current {
+            let pos = bb.pos.extend(0.);
+            match (brain.current, next) {
+                (Behavior::Wander | Behavior::Investigate | Behavior::Search, Behavior::Flee | Behavior::ChaseTo | Behavior::ThrowAt | Behavior::Surround | Behavior::Report) => {
+                    spawn_warn_particle(&mut commands, &mut layout_handles, &asset_server, pos, &mut rng.cosmetics);
+                }
+                (Behavior::ChaseTo | Behavior::Investigate | Behavior::Search, Behavior::Wander) | (Behavior::Wander, Behavior::Investigate) => {
+                    spawn_question_particle(&mut commands, &mut layout_handles, &asset_server, pos, &mut rng.cosmetics);
+                }
+                _ => {}
+            }
+            brain.current = next;
+            path.path = None;
+        }
+        if *state != next.state() {
+            *state = next.state();
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::{GridCoords, LayerMetadata, LevelIid};
+use rand::Rng;
+
+use crate::{
+    balance::{AlertBalance, GameBalance}, map::tilemap::{LightEmitterAlwaysCampfire, TransformToGrid}, player::components::{KillNpc, Player},
+    rng::GameRng, systems::DayCycle,
+};
+
+use super::{ai::{Behavior, Blackboard, Brain}, components::{Civilian, Hunter, NpcPath, NpcState, PathInFlight, PathRequest}, perception::Perception};
+
+/// How much the village knows about the vampire. Raised by witnesses reporting kills, lowered by
+/// calm days. More hunters come, NPCs stick together and civilians arm themselves as it rises.
+#[derive(Resource, Default, Debug)]
+pub struct AlertLevel {
+    pub level: f32,
+    /// A kill was reported since the last dawn
+    pub reported: bool,
+}
+
+impl AlertLevel {
+    pub fn raise(&mut self, balance: &AlertBalance) {
+        self.level = (self.level + balance.per_report).min(balance.max);
+        self.reported = true;
+    }
+
+    /// A day went by, it calms down unless someone reported a kill meanwhile
+    pub fn dawn(&mut self, balance: &AlertBalance) {
+        if !self.reported {
+            self.level = (self.level - balance.decay).max(0.);
+        }
+        self.reported = false;
+    }
+
+    /// The chance a spawner with `base` chance spawns a hunter
+    pub fn hunter_spawn_chance(&self, base: f64, balance: &AlertBalance) -> f64 {
+        (base * (1. + (self.level * balance.hunter_spawn) as f64)).min(1.)
+    }
+
+    /// The chance a new civilian is armed
+    pub fn armed_chance(&self, balance: &AlertBalance) -> f64 {
+        ((self.level * balance.armed_per_level) as f64).clamp(0., 1.)
+    }
+
+    /// NPCs wander close to each other
+    pub fn grouped(&self, balance: &AlertBalance) -> bool {
+        self.level >= balance.group_level
+    }
+}
+
+/// Where witnesses report kills when no hunter is closer
+#[derive(Component, Debug)]
+pub struct Campfire {
+    pub pos: Vec2,
+}
+
+/// Campfires are IntGrid cells, placed once the grid of their level is built
+pub fn locate_campfires(
+    mut commands: Commands,
+    campfires: Query<(Entity, &GridCoords, &Parent), (With<LightEmitterAlwaysCampfire>, Without<Campfire>)>,
+    layers: Query<(&LayerMetadata, &Parent)>,
+    levels: Query<&LevelIid>,
+    transformer: Res<TransformToGrid>,
+) {
+    for (entity, coords, layer) in campfires.iter() {
+        let Ok((metadata, level)) = layers.get(layer.get()) else {continue};
+        let Some(grid) = levels.get(level.get()).ok().and_then(|iid| transformer.levels.get(iid)) else {continue};
+        let cell = metadata.grid_size as f32;
+        let pos = grid.min() + (Vec2::new(coords.x as f32, coords.y as f32) + 0.5) * cell;
+        commands.entity(entity).insert(Campfire {pos});
+    }
+}
+
+/// Civilians that see the player kill someone and live become witnesses
+pub fn witness_kills(
+    mut kills: EventReader<KillNpc>,
+    mut civilians: Query<(&NpcState, &Perception, &mut Blackboard), With<Civilian>>,
+    player: Query<&Transform, With<Player>>,
+) {
+    if kills.is_empty() {return}
+    kills.clear();
+    let Ok(player) = player.get_single() else {return};
+    for (state, perception, mut bb) in civilians.iter_mut() {
+        if *state == NpcState::Dead || !perception.sees_player {continue}
+        bb.witnessed = Some(player.translation.xy());
+    }
+}
+
+/// Runs to the closest hunter or campfire and raises the alert there
+pub fn report(
+    mut commands: Commands,
+    mut npcs: Query<(Entity, &Brain, &mut Blackboard, &mut NpcPath, Has<PathInFlight>), With<Civilian>>,
+    hunters: Query<(&Transform, &NpcState), With<Hunter>>,
+    campfires: Query<&Campfire>,
+    mut alert: ResMut<AlertLevel>,
+    transformer: Res<TransformToGrid>,
+    balance: Res<GameBalance>,
+) {
+    let listeners: Vec<Vec2> = hunters.iter()
+        .filter(|(_, state)| **state != NpcState::Dead)
+        .map(|(transform, _)| transform.translation.xy())
+        .chain(campfires.iter().map(|campfire| campfire.pos))
+        .collect();
+    for (entity, brain, mut bb, mut path, in_flight) in npcs.iter_mut() {
+        if brain.current != Behavior::Report || !bb.active {continue}
+        let pos = bb.pos;
+        let Some(listener) = listeners.iter().copied().min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos))) else {
+            bb.witnessed = None;
+            continue;
+        };
+        if pos.distance(listener) < balance.alert.report_dist {
+            alert.raise(&balance.alert);
+            bb.witnessed = None;
+            continue;
+        }
+        // nobody to tell
+        if path.failed == Some(NpcState::Look) {
+            path.failed = None;
+            bb.witnessed = None;
+            continue;
+        }
+        let target = transformer.from_world_i32(listener);
+        // hunters walk around meanwhile
+        if path.path.as_ref().is_some_and(|path| path.last().is_some_and(|end| (*end - target).abs().max_element() > 2)) {
+            path.path = None;
+        }
+        if path.path.is_none() && !in_flight {
+            commands.entity(entity).insert(PathRequest::new(bb.ipos, target, NpcState::Look, false));
+        }
+    }
+}
+
+/// New civilians take up arms the more alert the village is
+pub fn arm_civilians(
+    mut civilians: Query<&mut Blackboard, Added<Civilian>>,
+    alert: Res<AlertLevel>,
+    balance: Res<GameBalance>,
+    mut rng: ResMut<GameRng>,
+) {
+    let chance = alert.armed_chance(&balance.alert);
+    if chance <= 0. {return}
+    for mut bb in civilians.iter_mut() {
+        bb.armed = rng.spawning.gen_bool(chance);
+    }
+}
+
+/// Every dawn the village calms down a little
+pub fn calm_down(
+    mut alert: ResMut<AlertLevel>,
+    mut was_night: Local<bool>,
+    day_cycle: Res<DayCycle>,
+    balance: Res<GameBalance>,
+) {
+    if *was_night && !day_cycle.is_night {
+        alert.dawn(&balance.alert);
+    }
+    *was_night = day_cycle.is_night;
+}
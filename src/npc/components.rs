@@ -1,4 +1,6 @@
-use bevy::{prelude::*, time::Stopwatch};
+use bevy::{ecs::{component::ComponentId, world::DeferredWorld}, prelude::*, tasks::Task, time::Stopwatch};
+
+use crate::map::tilemap::LevelGrid;
 
 #[derive(Component)]
 pub struct Civilian;
@@ -9,13 +11,26 @@ pub struct Hunter;
 #[derive(Component, Debug)]
 pub struct Projectile;
 
-#[derive(Component)]
-pub struct DespawnTimer {
-    pub timer: Timer
+/// Numbers NPCs in the order they spawn in a run. Entity ids change between runs, replays and
+/// the path budget go by this instead. Set by [`number_npc`] when inserted.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NpcId(pub u32);
+
+/// The next [`NpcId`], reset every run
+#[derive(Resource, Default)]
+pub struct NpcIds(pub u32);
+
+pub fn number_npc(mut world: DeferredWorld, entity: Entity, _: ComponentId) {
+    let mut ids = world.resource_mut::<NpcIds>();
+    let id = NpcId(ids.0);
+    ids.0 += 1;
+    if let Some(mut npc_id) = world.get_mut::<NpcId>(entity) {
+        *npc_id = id;
+    }
 }
 
 #[derive(Component)]
-pub struct HunterTimer {
+pub struct DespawnTimer {
     pub timer: Timer
 }
 
@@ -24,9 +39,55 @@ pub struct NpcVelAccum {
     pub v: Vec2,
 }
 
-#[derive(Component)]
+#[derive(Component, Default)]
 pub struct NpcPath {
     pub path: Option<Vec<IVec2>>,
+    /// The state whose last [`PathRequest`] found no path
+    pub failed: Option<NpcState>,
+}
+
+/// An A* query for [`NpcPath`], resolved on the compute task pool within a per-frame budget.
+/// A newer request replaces one that is still waiting.
+#[derive(Component, Clone, Debug)]
+pub struct PathRequest {
+    pub start: IVec2,
+    pub end: IVec2,
+    pub state: NpcState,
+    pub is_hunter: bool,
+    pub priority: PathPriority,
+}
+
+impl PathRequest {
+    pub fn new(start: IVec2, end: IVec2, state: NpcState, is_hunter: bool) -> Self {
+        PathRequest {start, end, state, is_hunter, priority: PathPriority::of(state)}
+    }
+}
+
+/// Requests of higher priority are computed first when the budget runs out
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PathPriority {
+    Wander,
+    Search,
+    Pursuit,
+}
+
+impl PathPriority {
+    pub fn of(state: NpcState) -> Self {
+        match state {
+            NpcState::Attack | NpcState::Chase | NpcState::Escape => PathPriority::Pursuit,
+            NpcState::Look => PathPriority::Search,
+            NpcState::Chill | NpcState::Dead => PathPriority::Wander,
+        }
+    }
+}
+
+/// A [`PathRequest`] being computed, the NPC keeps following its old path meanwhile
+#[derive(Component)]
+pub struct PathInFlight {
+    pub task: Task<Option<Vec<IVec2>>>,
+    pub state: NpcState,
+    /// The grid the path is found on, the result is dropped when the grid changes
+    pub grid: LevelGrid,
 }
 
 #[derive(Component, Clone, Copy, PartialEq, Debug)]
@@ -44,11 +105,6 @@ pub struct ChillTimer {
     pub timer: Timer
 }
 
-#[derive(Component)]
-pub struct PlayerLastPos {
-    pub pos: IVec2,
-}
-
 #[derive(Component)]
 pub struct AttackTimer {
     pub timer: Timer,
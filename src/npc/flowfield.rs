@@ -0,0 +1,181 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{math::ivec2, prelude::*};
+
+use crate::{map::{plugin::TrespassableCells, tilemap::TransformToGrid}, player::components::Player};
+
+use super::{components::NpcState, pathfinder::smooth_path};
+
+const MOVES: [IVec2; 4] = [
+    ivec2(1, 0),
+    ivec2(0, 1),
+    ivec2(-1, 0),
+    ivec2(0, -1),
+];
+
+const DIAGONALS: [IVec2; 4] = [
+    ivec2(1, 1),
+    ivec2(-1, 1),
+    ivec2(-1, -1),
+    ivec2(1, -1),
+];
+
+/// Longest path sampled from a field, farther NPCs walk the first part and sample again
+const MAX_PATH: usize = 256;
+
+/// A Dijkstra map over [`TrespassableCells`]: every cell holds the cost of walking to the goal,
+/// walking downhill from any cell is a shortest path. Moves cost like in [`super::pathfinder`],
+/// 10 straight and 14 diagonal, but other units are not avoided.
+#[derive(Clone, Debug, Default)]
+pub struct FlowField {
+    values: Vec<Vec<i32>>,
+}
+
+impl FlowField {
+    fn unreachable(cells: &TrespassableCells) -> Self {
+        FlowField {values: cells.cells.iter().map(|column| vec![i32::MAX; column.len()]).collect()}
+    }
+
+    /// Leads to `goal` from every cell connected to it
+    pub fn toward(cells: &TrespassableCells, goal: IVec2) -> Self {
+        let mut field = FlowField::unreachable(cells);
+        if !cells.is_trespassable(&goal) {return field}
+        field.set(goal, 0);
+        field.relax(cells, vec![(0, goal)]);
+        field
+    }
+
+    /// Leads away from the goal of `toward`. Scaling the costs past -1 and relaxing again makes
+    /// the field prefer open ground over the nearest dead end.
+    pub fn away(toward: &FlowField, cells: &TrespassableCells) -> Self {
+        let mut field = toward.clone();
+        let mut seeds = Vec::new();
+        for (x, column) in field.values.iter_mut().enumerate() {
+            for (y, value) in column.iter_mut().enumerate() {
+                if *value == i32::MAX {continue}
+                *value = -*value * 6 / 5;
+                seeds.push((*value, ivec2(x as i32, y as i32)));
+            }
+        }
+        field.relax(cells, seeds);
+        field
+    }
+
+    /// The value of a cell, none for cells not connected to the goal
+    pub fn get(&self, pos: IVec2) -> Option<i32> {
+        let value = *self.values.get(pos.x as usize)?.get(pos.y as usize)?;
+        (value != i32::MAX).then_some(value)
+    }
+
+    fn set(&mut self, pos: IVec2, value: i32) {
+        self.values[pos.x as usize][pos.y as usize] = value;
+    }
+
+    fn neighbors(cells: &TrespassableCells, pos: IVec2) -> impl Iterator<Item = (IVec2, i32)> + '_ {
+        let straight = MOVES.into_iter()
+            .map(move |mov| (pos + mov, 10))
+            .filter(move |(next, _)| cells.is_trespassable(next));
+        // no cutting corners, both sides have to be free
+        let diagonal = DIAGONALS.into_iter()
+            .filter(move |mov| cells.is_trespassable(&(pos + ivec2(mov.x, 0))) && cells.is_trespassable(&(pos + ivec2(0, mov.y))))
+            .map(move |mov| (pos + mov, 14))
+            .filter(move |(next, _)| cells.is_trespassable(next));
+        straight.chain(diagonal)
+    }
+
+    fn relax(&mut self, cells: &TrespassableCells, seeds: Vec<(i32, IVec2)>) {
+        let mut open: BinaryHeap<Reverse<(i32, i32, i32)>> = seeds.into_iter().map(|(value, pos)| Reverse((value, pos.x, pos.y))).collect();
+        while let Some(Reverse((value, x, y))) = open.pop() {
+            let pos = ivec2(x, y);
+            if self.get(pos).is_some_and(|current| current < value) {continue}
+            for (next, cost) in FlowField::neighbors(cells, pos) {
+                let next_value = value + cost;
+                if self.get(next).is_some_and(|current| current <= next_value) {continue}
+                self.set(next, next_value);
+                open.push(Reverse((next_value, next.x, next.y)));
+            }
+        }
+    }
+
+    /// The neighbor downhill from `pos`, none at the bottom
+    pub fn step(&self, cells: &TrespassableCells, pos: IVec2) -> Option<IVec2> {
+        let value = self.get(pos)?;
+        FlowField::neighbors(cells, pos)
+            .filter_map(|(next, _)| Some((self.get(next)?, next)))
+            .filter(|(next_value, _)| *next_value < value)
+            .min_by_key(|(next_value, next)| (*next_value, next.x, next.y))
+            .map(|(_, next)| next)
+    }
+
+    /// Walks downhill from `from` until `arrived`, the path starts with `from` like the ones of
+    /// [`super::pathfinder::pathfinder`]. Ends early at the bottom of the field.
+    pub fn path(&self, cells: &TrespassableCells, from: IVec2, arrived: impl Fn(IVec2) -> bool) -> Vec<IVec2> {
+        let mut path = vec![from];
+        let mut pos = from;
+        while !arrived(pos) && path.len() < MAX_PATH {
+            let Some(next) = self.step(cells, pos) else {break};
+            path.push(next);
+            pos = next;
+        }
+        path
+    }
+}
+
+/// Fields toward and away from the player's cell, shared by every chasing and escaping NPC.
+#[derive(Resource, Default)]
+pub struct FlowFields {
+    pub goal: Option<IVec2>,
+    pub toward: FlowField,
+    pub away: FlowField,
+}
+
+impl FlowFields {
+    /// What [`super::pathfinder::pathfinder`] finds toward or away from the player, smoothed
+    /// the same way. None in other states or when there is no way to go.
+    pub fn path(&self, state: NpcState, cells: &TrespassableCells, from: IVec2, is_hunter: bool) -> Option<Vec<IVec2>> {
+        self.grid_path(state, cells, from, is_hunter).map(|path| smooth_path(path, cells))
+    }
+
+    fn grid_path(&self, state: NpcState, cells: &TrespassableCells, from: IVec2, is_hunter: bool) -> Option<Vec<IVec2>> {
+        let goal = self.goal?;
+        match state {
+            NpcState::Chase if is_hunter => {
+                let path = self.toward.path(cells, from, |p| p.distance_squared(goal) < 10);
+                // keeps a throwing distance
+                (path.len() > 5).then(|| path[0..path.len() - 4].to_vec())
+            }
+            NpcState::Chase => {
+                let path = self.toward.path(cells, from, |p| p == goal);
+                (path.len() > 1).then_some(path)
+            }
+            NpcState::Escape => {
+                let distance = if is_hunter {25} else {100};
+                let path = self.away.path(cells, from, |p| p.distance_squared(goal) > distance);
+                (path.len() > 1).then_some(path)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Recomputes the fields when the player enters another cell or the grid changes,
+/// instead of a search per NPC and frame.
+pub fn update_flow_fields(
+    mut fields: ResMut<FlowFields>,
+    player: Query<&Transform, With<Player>>,
+    transformer: Res<TransformToGrid>,
+    trespassable: Res<TrespassableCells>,
+) {
+    if !trespassable.ready || !transformer.ready {
+        if fields.goal.is_some() {
+            *fields = FlowFields::default();
+        }
+        return;
+    }
+    let Ok(player) = player.get_single() else {return};
+    let goal = transformer.from_world_i32(player.translation.xy());
+    if fields.goal == Some(goal) && !transformer.is_changed() {return}
+    let toward = FlowField::toward(&trespassable, goal);
+    let away = FlowField::away(&toward, &trespassable);
+    *fields = FlowFields {goal: Some(goal), toward, away};
+}
@@ -1,22 +1,57 @@
 use bevy::prelude::*;
 use systems::*;
+use ai::*;
+use alert::*;
 use pathfinder::*;
+use flowfield::*;
+use navmesh::*;
+use steering::*;
+use perception::*;
+use schedule::*;
+use search::*;
+use squad::*;
 
 use crate::systems::GameState;
+use components::{number_npc, NpcId, NpcIds};
 
+pub mod ai;
+pub mod alert;
 pub mod components;
-mod pathfinder;
+pub mod flowfield;
+pub mod navmesh;
+pub mod pathfinder;
+pub mod perception;
+pub mod schedule;
+pub mod search;
+pub mod squad;
+pub mod steering;
 pub mod systems;
 
 pub struct NPCPlugin;
 
 impl Plugin for NPCPlugin {
     fn build(&self, app: &mut App) {
+        app.world_mut().register_component_hooks::<NpcId>().on_add(number_npc);
         app
         // .add_systems(Startup, (spawn_civilian, spawn_hunter))
         .add_event::<Win>()
-        .add_systems(Update, (manage_civilians, manage_hunters, manage_projectiles,
-            process_collisions, entity_spawner, victory).run_if(in_state(GameState::InGame)))
+        .add_event::<Noise>()
+        .add_event::<Alert>()
+        .init_resource::<FlowFields>()
+        .init_resource::<PathBudget>()
+        .init_resource::<PathGrids>()
+        .init_resource::<PathLandings>()
+        .init_resource::<NpcIds>()
+        .init_resource::<LevelNavMesh>()
+        .init_resource::<SpatialHash>()
+        .init_resource::<AlertLevel>()
+        .add_systems(Update, (
+            (perceive, update_flow_fields, collect_paths, process_collisions).before(update_blackboards),
+            (witness_kills.after(process_collisions).after(perceive), locate_campfires, calm_down).before(update_blackboards),
+            (update_blackboards, form_squads, follow_schedules, think, (wander, flee, chase_to, throw_at, surround, retreat, melee, investigate, search, report, commute, die).chain(), follow_paths).chain(),
+            update_navmesh.before(dispatch_path_requests),
+            (dispatch_path_requests, steer_npcs).after(follow_paths),
+            manage_projectiles, (entity_spawner, arm_civilians).chain(), victory).run_if(in_state(GameState::InGame)))
         ;
     }
 }
\ No newline at end of file
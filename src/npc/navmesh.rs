@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use bevy::{math::ivec2, prelude::*, utils::HashSet};
+use bevy_ecs_ldtk::{ldtk::Level, prelude::LdtkFields};
+use pathfinding::prelude::astar;
+
+use crate::map::{plugin::TrespassableCells, terrain::{merge_cells, CellRect}, tilemap::TransformToGrid};
+
+use super::{components::NpcState, pathfinder::{GridPathFinder, PathFinder}};
+
+/// How a level's NPCs find their way, picked with the LDtk level enum field `Navigation`.
+/// Levels without the field walk the grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Navigation {
+    #[default]
+    Grid,
+    NavMesh,
+}
+
+impl Navigation {
+    pub fn of(level: &Level) -> Self {
+        match level.get_enum_field("Navigation") {
+            Ok(value) if value == "NavMesh" => Navigation::NavMesh,
+            _ => Navigation::Grid,
+        }
+    }
+}
+
+/// Where two regions touch, the cells of `to` along the shared edge, ends included
+#[derive(Clone, Debug)]
+struct Portal {
+    to: usize,
+    ends: [IVec2; 2],
+    /// The step that crosses the edge
+    dir: IVec2,
+}
+
+impl Portal {
+    /// The cell of the portal closest to `pos`
+    fn closest(&self, pos: IVec2) -> IVec2 {
+        pos.clamp(self.ends[0].min(self.ends[1]), self.ends[0].max(self.ends[1]))
+    }
+
+    /// The ends as seen walking through it, left first
+    fn sides(&self) -> (Vec2, Vec2) {
+        let [a, b] = self.ends.map(|end| end.as_vec2());
+        if self.dir.as_vec2().perp_dot(b - a) > 0. {(b, a)} else {(a, b)}
+    }
+}
+
+/// The walkable cells merged into rectangles, the complement of the obstacles. Every rectangle is
+/// convex, so walking straight inside one never hits a wall. An open map is a handful of regions
+/// where the grid has a node per cell.
+#[derive(Clone, Debug, Default)]
+pub struct NavMesh {
+    pub regions: Vec<CellRect>,
+    region_of: Vec<Vec<Option<usize>>>,
+    portals: Vec<Vec<Portal>>,
+}
+
+impl NavMesh {
+    pub fn new(cells: &TrespassableCells) -> Self {
+        let walkable: HashSet<IVec2> = cells.cells.iter().enumerate()
+            .flat_map(|(x, column)| column.iter().enumerate()
+                .filter(|(_, free)| **free)
+                .map(move |(y, _)| ivec2(x as i32, y as i32)))
+            .collect();
+        let regions = merge_cells(&walkable);
+        let mut region_of: Vec<Vec<Option<usize>>> = cells.cells.iter().map(|column| vec![None; column.len()]).collect();
+        for (index, rect) in regions.iter().enumerate() {
+            for x in rect.left..=rect.right {
+                for y in rect.bottom..=rect.top {
+                    region_of[x as usize][y as usize] = Some(index);
+                }
+            }
+        }
+        let mut portals = vec![Vec::new(); regions.len()];
+        for i in 0..regions.len() {
+            for j in 0..regions.len() {
+                let (a, b) = (&regions[i], &regions[j]);
+                // b to the right of a
+                let (lo, hi) = (a.bottom.max(b.bottom), a.top.min(b.top));
+                if a.right + 1 == b.left && lo <= hi {
+                    portals[i].push(Portal {to: j, ends: [ivec2(b.left, lo), ivec2(b.left, hi)], dir: IVec2::X});
+                    portals[j].push(Portal {to: i, ends: [ivec2(a.right, lo), ivec2(a.right, hi)], dir: IVec2::NEG_X});
+                }
+                // b above a
+                let (lo, hi) = (a.left.max(b.left), a.right.min(b.right));
+                if a.top + 1 == b.bottom && lo <= hi {
+                    portals[i].push(Portal {to: j, ends: [ivec2(lo, b.bottom), ivec2(hi, b.bottom)], dir: IVec2::Y});
+                    portals[j].push(Portal {to: i, ends: [ivec2(lo, a.top), ivec2(hi, a.top)], dir: IVec2::NEG_Y});
+                }
+            }
+        }
+        NavMesh {regions, region_of, portals}
+    }
+
+    /// The region a cell belongs to, none for walls
+    pub fn region(&self, pos: IVec2) -> Option<usize> {
+        *self.region_of.get(pos.x as usize)?.get(pos.y as usize)?
+    }
+
+    /// A* over the regions, entering each at the portal cell closest to where the last one was
+    /// entered, then the funnel pulls the corridor straight. The waypoints are the start, the
+    /// corners it bends around and the end.
+    pub fn path(&self, start: IVec2, end: IVec2) -> Option<Vec<IVec2>> {
+        if start == end {return None}
+        let (from, to) = (self.region(start)?, self.region(end)?);
+        let cost = |a: IVec2, b: IVec2| (a.as_vec2().distance(b.as_vec2()) * 10.) as i32;
+        let (corridor, _) = astar(
+            &(from, start),
+            |&(region, pos)| self.portals[region].iter().map(move |portal| {
+                let entry = portal.closest(pos);
+                ((portal.to, entry), cost(pos, entry))
+            }),
+            |&(_, pos)| cost(pos, end),
+            |&(region, _)| region == to,
+        )?;
+        let portals: Vec<(Vec2, Vec2)> = corridor.windows(2)
+            .filter_map(|step| self.portals[step[0].0].iter().find(|portal| portal.to == step[1].0))
+            .map(Portal::sides)
+            .collect();
+        Some(funnel(start.as_vec2(), end.as_vec2(), &portals).into_iter().map(|p| p.round().as_ivec2()).collect())
+    }
+}
+
+impl PathFinder for NavMesh {
+    /// Walks to a cell over the mesh. Chasing and escaping keep the grid's goals, so do NPCs
+    /// pushed off the mesh.
+    fn find_path(
+        &self,
+        start: IVec2,
+        end: IVec2,
+        trespassable: &TrespassableCells,
+        transformer: &TransformToGrid,
+        state: NpcState,
+        is_hunter: bool,
+    ) -> Option<Vec<IVec2>> {
+        match state {
+            NpcState::Chill | NpcState::Look if self.region(start).is_some() => self.path(start, end),
+            _ => GridPathFinder.find_path(start, end, trespassable, transformer, state, is_hunter),
+        }
+    }
+}
+
+/// The simple stupid funnel: pulls a string from `start` to `end` through the portals, given as
+/// (left, right) ends. The corners the string bends around are the waypoints between them.
+pub fn funnel(start: Vec2, end: Vec2, portals: &[(Vec2, Vec2)]) -> Vec<Vec2> {
+    let portals: Vec<(Vec2, Vec2)> = std::iter::once((start, start))
+        .chain(portals.iter().copied())
+        .chain(std::iter::once((end, end)))
+        .collect();
+    let mut path = vec![start];
+    let (mut apex, mut left, mut right) = (start, start, start);
+    let (mut left_index, mut right_index) = (0, 0);
+    let mut i = 1;
+    while i < portals.len() {
+        let (l, r) = portals[i];
+        // the right side moves in
+        if (right - apex).perp_dot(r - apex) >= 0. {
+            if apex == right || (left - apex).perp_dot(r - apex) < 0. {
+                right = r;
+                right_index = i;
+            } else {
+                // crossed over the left side, its end is a corner to walk around
+                apex = left;
+                if path.last() != Some(&apex) {path.push(apex)}
+                i = left_index + 1;
+                (right, right_index) = (apex, left_index);
+                continue;
+            }
+        }
+        // the left side moves in
+        if (left - apex).perp_dot(l - apex) <= 0. {
+            if apex == left || (right - apex).perp_dot(l - apex) > 0. {
+                left = l;
+                left_index = i;
+            } else {
+                apex = right;
+                if path.last() != Some(&apex) {path.push(apex)}
+                i = right_index + 1;
+                (left, left_index) = (apex, right_index);
+                continue;
+            }
+        }
+        i += 1;
+    }
+    if path.last() != Some(&end) {path.push(end)}
+    path
+}
+
+/// The navmesh of the stitched grid while the current level asks for one
+#[derive(Resource, Default)]
+pub struct LevelNavMesh(pub Option<Arc<NavMesh>>);
+
+pub fn update_navmesh(
+    mut navmesh: ResMut<LevelNavMesh>,
+    transformer: Res<TransformToGrid>,
+    trespassable: Res<TrespassableCells>,
+) {
+    // the cells only change together with the grids
+    if !transformer.is_changed() {return}
+    let wanted = trespassable.ready && transformer.ready && transformer.current_navigation() == Navigation::NavMesh;
+    navmesh.0 = wanted.then(|| Arc::new(NavMesh::new(&trespassable)));
+}
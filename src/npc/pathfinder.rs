@@ -1,8 +1,145 @@
-use bevy::{math::ivec2, prelude::*, utils::{hashbrown::HashMap, HashSet}};
+use std::sync::Arc;
+
+use bevy::{math::ivec2, prelude::*, tasks::{block_on, futures_lite::future, AsyncComputeTaskPool}};
 use pathfinding::prelude::astar;
 use crate::map::{plugin::TrespassableCells, tilemap::TransformToGrid};
 
-use super::components::NpcState;
+use super::{components::{NpcId, NpcPath, NpcState, PathInFlight, PathRequest}, navmesh::LevelNavMesh};
+
+/// Finds an NPC's waypoints, on the tile grid or on the current level's [`super::navmesh::NavMesh`]
+pub trait PathFinder: Send + Sync {
+    fn find_path(
+        &self,
+        start: IVec2,
+        end: IVec2,
+        trespassable: &TrespassableCells,
+        transformer: &TransformToGrid,
+        state: NpcState,
+        is_hunter: bool,
+    ) -> Option<Vec<IVec2>>;
+}
+
+/// A* over the cells, see [`pathfinder`]
+pub struct GridPathFinder;
+
+impl PathFinder for GridPathFinder {
+    fn find_path(
+        &self,
+        start: IVec2,
+        end: IVec2,
+        trespassable: &TrespassableCells,
+        transformer: &TransformToGrid,
+        state: NpcState,
+        is_hunter: bool,
+    ) -> Option<Vec<IVec2>> {
+        pathfinder(start, end, trespassable, transformer, state, is_hunter)
+    }
+}
+
+/// How many [`PathRequest`]s start computing each frame
+#[derive(Resource)]
+pub struct PathBudget {
+    pub per_frame: usize,
+}
+
+impl Default for PathBudget {
+    fn default() -> Self {
+        PathBudget {per_frame: 24}
+    }
+}
+
+/// The grids the path tasks search, shared between them and rebuilt only when
+/// [`TrespassableCells`] or [`TransformToGrid`] change
+#[derive(Resource, Default)]
+pub struct PathGrids(pub Option<Arc<(TrespassableCells, TransformToGrid)>>);
+
+/// The NPCs whose paths were handed over this frame. A replay says which ones land on each of
+/// its frames, so its NPCs get their paths on the same frames as in the recording.
+#[derive(Resource, Default)]
+pub struct PathLandings {
+    /// Set while a replay plays, only these paths land and they are waited for
+    pub scripted: Option<Vec<NpcId>>,
+    pub landed: Vec<NpcId>,
+}
+
+/// Starts the most urgent [`PathRequest`]s on the compute task pool, the rest wait for the next frame.
+/// The tasks search a snapshot of the grids, so the game can change them meanwhile, and the
+/// current level's navmesh if it has one.
+pub fn dispatch_path_requests(
+    mut commands: Commands,
+    budget: Res<PathBudget>,
+    requests: Query<(Entity, &NpcId, &PathRequest), Without<PathInFlight>>,
+    trespassable: Res<TrespassableCells>,
+    transformer: Res<TransformToGrid>,
+    navmesh: Res<LevelNavMesh>,
+    mut grids: ResMut<PathGrids>,
+    mut paths: Query<&mut NpcPath>,
+) {
+    if grids.0.is_none() || trespassable.is_changed() || transformer.is_changed() {
+        // the cells are shared, the per-level grids are not searched
+        let cells = TrespassableCells {
+            cells: trespassable.cells.clone(),
+            units: trespassable.units.clone(),
+            ready: trespassable.ready,
+            ..default()
+        };
+        grids.0 = Some(Arc::new((cells, transformer.clone())));
+    }
+    if requests.is_empty() || !trespassable.ready || !transformer.ready {return}
+    let Some(grids) = grids.0.clone() else {return};
+    let mut requests: Vec<(Entity, &NpcId, &PathRequest)> = requests.iter().collect();
+    requests.sort_by_key(|(_, id, request)| (std::cmp::Reverse(request.priority), **id));
+    let finder: Arc<dyn PathFinder> = match &navmesh.0 {
+        Some(navmesh) => navmesh.clone(),
+        None => Arc::new(GridPathFinder),
+    };
+    let pool = AsyncComputeTaskPool::get();
+    for (entity, _, request) in requests.into_iter().take(budget.per_frame) {
+        let grids = grids.clone();
+        let finder = finder.clone();
+        let request = request.clone();
+        let task = pool.spawn(async move {
+            finder.find_path(request.start, request.end, &grids.0, &grids.1, request.state, request.is_hunter)
+        });
+        if let Ok(mut path) = paths.get_mut(entity) {
+            path.failed = None;
+        }
+        commands.entity(entity)
+            .remove::<PathRequest>()
+            .insert(PathInFlight {task, state: request.state, grid: transformer.stitched.clone()});
+    }
+}
+
+/// Hands the finished paths to their NPCs, the others keep computing. During a replay the
+/// paths recorded for this frame are waited for instead, see [`PathLandings`]. Only replays
+/// stall the frame on a search that is still running, a live game never waits for one.
+/// Results for a state the NPC left or an older grid are dropped.
+pub fn collect_paths(
+    mut commands: Commands,
+    mut npcs: Query<(Entity, &NpcId, &mut PathInFlight, &mut NpcPath, &NpcState)>,
+    mut landings: ResMut<PathLandings>,
+    transformer: Res<TransformToGrid>,
+) {
+    let landings = &mut *landings;
+    landings.landed.clear();
+    for (entity, id, mut in_flight, mut npc_path, state) in npcs.iter_mut() {
+        let path = match &landings.scripted {
+            Some(scripted) if scripted.contains(id) => block_on(&mut in_flight.task),
+            Some(_) => continue,
+            None => {
+                let Some(path) = block_on(future::poll_once(&mut in_flight.task)) else {continue};
+                path
+            }
+        };
+        landings.landed.push(*id);
+        commands.entity(entity).remove::<PathInFlight>();
+        if *state != in_flight.state || transformer.stitched != in_flight.grid {continue}
+        if path.is_none() {
+            npc_path.failed = Some(in_flight.state);
+        }
+        npc_path.path = path;
+    }
+}
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Pos(IVec2);
@@ -14,50 +151,34 @@ const MOVES: [IVec2; 4] = [
     ivec2(0, -1),
 ];
 
+const DIAGONALS: [IVec2; 4] = [
+    ivec2(1, 1),
+    ivec2(-1, 1),
+    ivec2(-1, -1),
+    ivec2(1, -1),
+];
+
 impl Pos {
     fn successors(&self, trespassable: &TrespassableCells) -> impl Iterator<Item = (Pos, i32)> {
-        let is_trasspassable = |pos: &IVec2| -> bool {
-            let Some(column) = trespassable.cells.get(pos.x as usize) else {return false};
-            let Some(value) = column.get(pos.y as usize) else {return false};
-            *value
-        };
-
         let &Pos(pos) = self;
-        let mut moves = Vec::with_capacity(4);
-        let mut moves_cost = Vec::with_capacity(4);
+        // other units are walked around when possible
+        let cost = |t: IVec2, base: i32| if trespassable.units.contains(&t) {base + 100} else {base};
+        let mut out = Vec::with_capacity(8);
         for mov in MOVES {
             let t = pos + mov;
-            if is_trasspassable(&t) {
-                if trespassable.units.contains(&t) {
-                    moves_cost.push(100);
-                } else {
-                    moves_cost.push(0);
-                }
-                moves.push(t)
+            if trespassable.is_trespassable(&t) {
+                out.push((Pos(t), cost(t, 10)));
             }
         }
-        let mut hardmoves = HashSet::new();
-        let mut hardmoves_cost = HashMap::new();
-        for i in 0..moves.len() {
-            for j in i + 1..moves.len() {
-                let hardmove = moves[i] + moves[j] - pos;
-                if is_trasspassable(&hardmove) {
-                    if trespassable.units.contains(&hardmove) {
-                        hardmoves_cost.insert(hardmove, 100);
-                    } else {
-                        hardmoves_cost.insert(hardmove, 0);
-                    }
-                    hardmoves.insert(hardmove);
-                }
+        for mov in DIAGONALS {
+            let t = pos + mov;
+            // no cutting corners, both sides have to be free
+            let sides_free = trespassable.is_trespassable(&(pos + ivec2(mov.x, 0)))
+                && trespassable.is_trespassable(&(pos + ivec2(0, mov.y)));
+            if sides_free && trespassable.is_trespassable(&t) {
+                out.push((Pos(t), cost(t, 14)));
             }
         }
-        let mut out = vec![];
-        for i in 0..moves.len() {
-            out.push((Pos(moves[i]), 10 + moves_cost[i]))
-        }
-        for i in hardmoves {
-            out.push((Pos(i), 14 + hardmoves_cost[&i]))
-        }
         out.into_iter()
     }
     fn weight(&self, end: &Pos) -> i32{
@@ -65,11 +186,40 @@ impl Pos {
     }
 }
 
+/// String pulling: drops every waypoint that can be walked past in a straight line,
+/// the first and the last cell are kept
+pub fn smooth_path(path: Vec<IVec2>, trespassable: &TrespassableCells) -> Vec<IVec2> {
+    if path.len() < 3 {return path}
+    let mut smooth = vec![path[0]];
+    let mut anchor = path[0];
+    for i in 2..path.len() {
+        if !trespassable.line_of_sight(anchor, path[i]) {
+            anchor = path[i - 1];
+            smooth.push(anchor);
+        }
+    }
+    smooth.push(path[path.len() - 1]);
+    smooth
+}
+
+/// A path for the NPC's state, smoothed so it walks straight lines between the waypoints
 pub fn pathfinder(
     start_ipos: IVec2,
     end_ipos: IVec2,
-    trespassable: &Res<TrespassableCells>,
-    transformer: &Res<TransformToGrid>,
+    trespassable: &TrespassableCells,
+    transformer: &TransformToGrid,
+    npc_state: NpcState,
+    is_hunter: bool,
+) -> Option<Vec<IVec2>> {
+    grid_path(start_ipos, end_ipos, trespassable, transformer, npc_state, is_hunter)
+        .map(|path| smooth_path(path, trespassable))
+}
+
+fn grid_path(
+    start_ipos: IVec2,
+    end_ipos: IVec2,
+    trespassable: &TrespassableCells,
+    transformer: &TransformToGrid,
     npc_state: NpcState,
     is_hunter: bool,
 ) -> Option<Vec<IVec2>> {
@@ -122,7 +272,8 @@ pub fn pathfinder(
                     }
                 }
             }
-            NpcState::Look => {
+            // investigating, or a hunter taking its squad's spot
+            NpcState::Look | NpcState::Attack => {
                 if let Some(path) = find_path_goto(&Pos(start_ipos), &Pos(end_ipos), trespassable) {
                     if path.len() > 1 {
                         return Some(path.into_iter().map(|x| x.0).collect());
@@ -140,7 +291,7 @@ pub fn pathfinder(
 fn find_path_hunesc(
     start: &Pos,
     end: &Pos,
-    trespassable: &Res<TrespassableCells>,
+    trespassable: &TrespassableCells,
 ) -> Option<Vec<Pos>>{
     if let Some(path) = astar(
     start,
@@ -156,7 +307,7 @@ fn find_path_hunesc(
 fn find_path_civesc(
     start: &Pos,
     end: &Pos,
-    trespassable: &Res<TrespassableCells>,
+    trespassable: &TrespassableCells,
 ) -> Option<Vec<Pos>>{
     if let Some(path) = astar(
     start,
@@ -172,7 +323,7 @@ fn find_path_civesc(
 fn find_path_huncha(
     start: &Pos,
     end: &Pos,
-    trespassable: &Res<TrespassableCells>,
+    trespassable: &TrespassableCells,
 ) -> Option<Vec<Pos>>{
     if let Some(path) = astar(
     start,
@@ -188,7 +339,7 @@ fn find_path_huncha(
 fn find_path_goto(
     start: &Pos,
     end: &Pos,
-    trespassable: &Res<TrespassableCells>,
+    trespassable: &TrespassableCells,
 ) -> Option<Vec<Pos>>{
     if let Some(path) = astar(
     start,
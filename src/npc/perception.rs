@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use bevy_light_2d::light::PointLight2d;
+use bevy_rapier2d::prelude::RapierContext;
+
+use crate::{balance::GameBalance, characters::animation::AnimationController, map::tilemap::LightEmitter, player::components::Player, systems::DayCycle};
+
+use super::{components::{Civilian, Hunter, NpcState}, systems::raycast};
+
+/// What an NPC knows about the player, updated by [`perceive`] before the NPCs act
+#[derive(Component, Default, Debug)]
+pub struct Perception {
+    /// In the view cone and range, nothing in between
+    pub sees_player: bool,
+    /// Where the player was last seen, heard or reported by another NPC. Taken by the NPC acting on it.
+    pub alerted: Option<Vec2>,
+}
+
+/// A sound NPCs can hear, `loudness` is the distance it carries before their hearing is applied
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Noise {
+    pub pos: Vec2,
+    pub loudness: f32,
+}
+
+/// An NPC spotted the player at `target` and told everyone around `from`
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Alert {
+    pub from: Vec2,
+    pub target: Vec2,
+}
+
+/// `target` lies in the cone of `fov` degrees around `facing`, closer than `range`.
+/// Closer than `feel` it is noticed whichever way the NPC faces.
+pub fn in_view(pos: Vec2, facing: Vec2, target: Vec2, range: f32, fov: f32, feel: f32) -> bool {
+    let offset = target - pos;
+    let dist = offset.length();
+    if dist < feel {return true}
+    dist < range && facing.dot(offset / dist) >= (fov.to_radians() / 2.).cos()
+}
+
+/// How much the closest of `lights`, given as (position, radius, intensity), lights up `pos`:
+/// its intensity right under it, fading to nothing at its radius
+pub fn lamp_light(pos: Vec2, lights: &[(Vec2, f32, f32)]) -> f32 {
+    lights.iter()
+        .min_by(|a, b| a.0.distance_squared(pos).total_cmp(&b.0.distance_squared(pos)))
+        .map_or(0., |&(light, radius, intensity)| intensity * (1. - light.distance(pos) / radius).max(0.))
+        .clamp(0., 1.)
+}
+
+/// Sight shrinks toward `night_sight` of the range as the night falls, a player standing in
+/// the light of a lamp or a fire is seen from further, up to the daylight range.
+/// An NPC that starts seeing the player alerts every NPC within `alert_radius`.
+pub fn perceive(
+    mut npcs: Query<(&Transform, &AnimationController, &NpcState, &mut Perception, Has<Hunter>), Or<(With<Civilian>, With<Hunter>)>>,
+    player: Query<(&Transform, Entity, &Player)>,
+    lights: Query<(&GlobalTransform, &PointLight2d), With<LightEmitter>>,
+    mut noises: EventReader<Noise>,
+    mut alerts: EventWriter<Alert>,
+    rapier_context: Res<RapierContext>,
+    day_cycle: Res<DayCycle>,
+    balance: Res<GameBalance>,
+) {
+    let noises: Vec<Noise> = noises.read().copied().collect();
+    let Ok((player_transform, player_entity, player)) = player.get_single() else {return};
+    let player_pos = player_transform.translation.xy();
+    let perception = &balance.perception;
+    let lights: Vec<(Vec2, f32, f32)> = lights.iter()
+        .map(|(transform, light)| (transform.translation().xy(), light.radius, light.intensity))
+        .collect();
+    let dark = 1. - day_cycle.daytime * (1. - perception.night_sight);
+    let light = dark + (1. - dark) * lamp_light(player_pos, &lights);
+    let mut spotters = Vec::new();
+    for (transform, animation_controller, state, mut npc, is_hunter) in npcs.iter_mut() {
+        if *state == NpcState::Dead {
+            *npc = Perception::default();
+            continue;
+        }
+        let pos = transform.translation.xy();
+        let (spot_dist, fov, hearing) = if is_hunter {
+            (balance.hunter.spot_dist, balance.hunter.fov, balance.hunter.hearing)
+        } else {
+            (balance.civilian.spot_dist, balance.civilian.fov, balance.civilian.hearing)
+        };
+        for noise in &noises {
+            if noise.pos.distance(pos) < noise.loudness * hearing {
+                npc.alerted = Some(noise.pos);
+            }
+        }
+        let was_seeing = npc.sees_player;
+        let length = pos.distance(player_pos);
+        npc.sees_player = !player.is_dead && length > 0.
+            && in_view(pos, animation_controller.facing(), player_pos, spot_dist * light, fov, perception.feel_dist)
+            && raycast(pos, (player_pos - pos) / length, length, &rapier_context) == Some(player_entity);
+        if npc.sees_player {
+            npc.alerted = Some(player_pos);
+            if !was_seeing {
+                spotters.push(pos);
+            }
+        }
+    }
+    if spotters.is_empty() {return}
+    for &from in &spotters {
+        alerts.send(Alert {from, target: player_pos});
+    }
+    for (transform, _, state, mut npc, _) in npcs.iter_mut() {
+        if *state == NpcState::Dead || npc.sees_player {continue}
+        let pos = transform.translation.xy();
+        if spotters.iter().any(|from| from.distance(pos) < perception.alert_radius) {
+            npc.alerted = Some(player_pos);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    balance::GameBalance, map::{plugin::{GatheringPoint, Home, Workplace}, tilemap::TransformToGrid}, rng::GameRng,
+    systems::{get_local_time_f, RunStart},
+};
+
+use super::{ai::{Behavior, Blackboard, Brain}, alert::Campfire, components::{Civilian, NpcPath, NpcState, PathInFlight, PathRequest}};
+
+/// What civilians are up to at a time of the day
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+pub enum Activity {
+    /// Strolls wherever it is
+    #[default]
+    Wander,
+    Work,
+    /// Meets the others at a campfire or a gathering point
+    Gather,
+    /// Goes indoors, gone until the next day
+    Home,
+}
+
+/// The activity of the entry that started last before `time`, where the entries are
+/// (start, activity) pairs over [`get_local_time_f`]. Before the first entry the last one goes on.
+pub fn activity_at(time: f32, activities: &[(f32, Activity)]) -> Activity {
+    activities.iter()
+        .filter(|(start, _)| *start <= time)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .or_else(|| activities.iter().max_by(|a, b| a.0.total_cmp(&b.0)))
+        .map_or(Activity::default(), |(_, activity)| *activity)
+}
+
+/// The home and the workplace a civilian was given, picked once there are any on the map
+#[derive(Component, Default, Debug)]
+pub struct Routine {
+    pub home: Option<Vec2>,
+    pub work: Option<Vec2>,
+    /// Spots it found no path to, never picked again
+    pub unreachable: Vec<Vec2>,
+}
+
+/// A random one of `spots` that isn't `unreachable`, none once they all are
+pub fn pick_spot(spots: &[Vec2], unreachable: &[Vec2], rng: &mut impl Rng) -> Option<Vec2> {
+    let spots: Vec<Vec2> = spots.iter().copied().filter(|spot| !unreachable.contains(spot)).collect();
+    (!spots.is_empty()).then(|| spots[rng.gen_range(0..spots.len())])
+}
+
+/// Tells every civilian what to do at this time of the day and where
+pub fn follow_schedules(
+    mut civilians: Query<(&mut Routine, &mut Blackboard), With<Civilian>>,
+    homes: Query<&GlobalTransform, With<Home>>,
+    workplaces: Query<&GlobalTransform, With<Workplace>>,
+    gathering_points: Query<&GlobalTransform, With<GatheringPoint>>,
+    campfires: Query<&Campfire>,
+    transformer: Res<TransformToGrid>,
+    time: Res<Time<Virtual>>,
+    run_start: Res<RunStart>,
+    balance: Res<GameBalance>,
+    mut rng: ResMut<GameRng>,
+) {
+    let activity = activity_at(get_local_time_f(run_start.elapsed(&time).as_secs_f32(), &balance.daycycle), &balance.schedule.activities);
+    let homes: Vec<Vec2> = homes.iter().map(|transform| transform.translation().xy()).collect();
+    let workplaces: Vec<Vec2> = workplaces.iter().map(|transform| transform.translation().xy()).collect();
+    let gathering_points: Vec<Vec2> = gathering_points.iter().map(|transform| transform.translation().xy())
+        .chain(campfires.iter().map(|campfire| campfire.pos))
+        .collect();
+    for (mut routine, mut bb) in civilians.iter_mut() {
+        if bb.dead {continue}
+        if routine.home.is_none() {
+            routine.home = pick_spot(&homes, &routine.unreachable, &mut rng.ai);
+        }
+        if routine.work.is_none() {
+            routine.work = pick_spot(&workplaces, &routine.unreachable, &mut rng.ai);
+        }
+        let pos = bb.pos;
+        let destination = match activity {
+            Activity::Wander => None,
+            Activity::Work => routine.work,
+            Activity::Gather => gathering_points.iter().copied()
+                .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos))),
+            Activity::Home => routine.home,
+        };
+        bb.activity = activity;
+        bb.destination = destination.map(|pos| transformer.from_world_i32(pos));
+    }
+}
+
+/// Walks to where the schedule says. Civilians reaching their door go inside.
+pub fn commute(
+    mut commands: Commands,
+    mut npcs: Query<(Entity, &Brain, &Blackboard, &mut Routine, &mut NpcPath, Has<PathInFlight>)>,
+) {
+    for (entity, brain, bb, mut routine, mut path, in_flight) in npcs.iter_mut() {
+        if brain.current != Behavior::Commute || !bb.active {continue}
+        let Some(destination) = bb.destination else {continue};
+        if bb.activity == Activity::Home && (bb.ipos - destination).abs().max_element() <= 1 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        // no way there, another one is picked
+        if path.failed == Some(NpcState::Chill) {
+            path.failed = None;
+            let failed = match bb.activity {
+                Activity::Home => routine.home.take(),
+                Activity::Work => routine.work.take(),
+                _ => None,
+            };
+            routine.unreachable.extend(failed);
+            continue;
+        }
+        if path.path.as_ref().is_some_and(|path| path.last() != Some(&destination)) {
+            path.path = None;
+        }
+        if path.path.is_none() && !in_flight {
+            commands.entity(entity).insert(PathRequest::new(bb.ipos, destination, NpcState::Chill, false));
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{math::ivec2, prelude::*, utils::HashSet};
+
+use crate::{balance::GameBalance, characters::animation::AnimationController, map::{plugin::TrespassableCells, tilemap::TransformToGrid}};
+
+use super::{ai::{Behavior, Blackboard, Brain}, components::{NpcPath, NpcState, PathInFlight, PathRequest}};
+
+/// Where a hunter that lost the player looks next, shared out between the hunters searching
+/// the same spot
+#[derive(Component, Default, Debug)]
+pub struct SearchPlan {
+    /// The spot the plan was made for
+    pub origin: Option<Vec2>,
+    pub points: VecDeque<IVec2>,
+    /// Gives up once finished
+    pub timer: Timer,
+    /// Looks around while running
+    pub look: Timer,
+}
+
+/// The cells worth checking around `origin`, closest first after the one the player was heading
+/// to: the furthest cell toward `lead` in line of sight, then corners and nooks within `radius`,
+/// the ones hidden from `origin` first. Candidates closer than two cells to a picked one are skipped.
+pub fn search_points(origin: IVec2, lead: IVec2, cells: &TrespassableCells, radius: i32, max: usize) -> Vec<IVec2> {
+    let mut points = Vec::new();
+    let mut ahead = origin;
+    let delta = lead - origin;
+    let steps = delta.x.abs().max(delta.y.abs());
+    for step in 1..=steps {
+        let cell = origin + (delta.as_vec2() * step as f32 / steps as f32).round().as_ivec2();
+        if !cells.is_trespassable(&cell) || !cells.line_of_sight(origin, cell) {break}
+        ahead = cell;
+    }
+    if ahead != origin {
+        points.push(ahead);
+    }
+    let mut spots = Vec::new();
+    for x in -radius..=radius {
+        for y in -radius..=radius {
+            let cell = origin + ivec2(x, y);
+            if x * x + y * y > radius * radius || !cells.is_trespassable(&cell) {continue}
+            let walls = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y].iter()
+                .filter(|&&dir| !cells.is_trespassable(&(cell + dir)))
+                .count();
+            if walls < 2 {continue}
+            spots.push((cells.line_of_sight(origin, cell), x * x + y * y, cell));
+        }
+    }
+    spots.sort_by_key(|&(visible, dist, cell)| (visible, dist, cell.x, cell.y));
+    for (_, _, cell) in spots {
+        if points.len() >= max {break}
+        if cell == origin || points.iter().any(|point: &IVec2| (*point - cell).abs().max_element() < 2) {continue}
+        points.push(cell);
+    }
+    points
+}
+
+/// Deals the points out to the searchers standing at `searchers`, one at a time to whoever has
+/// the fewest, each taking the point closest to the last one it got. Every searcher ends up
+/// walking its own round.
+pub fn split_points(points: &[IVec2], searchers: &[IVec2]) -> Vec<Vec<IVec2>> {
+    let mut shares = vec![Vec::new(); searchers.len()];
+    if searchers.is_empty() {return shares}
+    let mut cursors = searchers.to_vec();
+    let mut left = points.to_vec();
+    while !left.is_empty() {
+        let searcher = (0..shares.len()).min_by_key(|&i| shares[i].len()).unwrap();
+        let cursor = cursors[searcher];
+        let (index, _) = left.iter().enumerate()
+            .min_by_key(|(_, point)| (**point - cursor).length_squared())
+            .unwrap();
+        let point = left.remove(index);
+        shares[searcher].push(point);
+        cursors[searcher] = point;
+    }
+    shares
+}
+
+/// Plans a search for every hunter that just reached the spot the player vanished at, then walks
+/// it from point to point, looking around at each, until the search timer runs out.
+pub fn search(
+    mut commands: Commands,
+    mut npcs: Query<(Entity, &Brain, &mut Blackboard, &mut SearchPlan, &mut NpcPath, &mut AnimationController, Has<PathInFlight>)>,
+    trespassable: Res<TrespassableCells>,
+    transformer: Res<TransformToGrid>,
+    time: Res<Time>,
+    balance: Res<GameBalance>,
+) {
+    let balance = &balance.search;
+    let planning: Vec<Entity> = npcs.iter()
+        .filter(|(_, brain, bb, plan, ..)| brain.current == Behavior::Search && bb.active && plan.origin != bb.search_at)
+        .map(|(entity, ..)| entity)
+        .collect();
+    for entity in planning {
+        let Ok((_, _, bb, ..)) = npcs.get(entity) else {continue};
+        let Some(origin) = bb.search_at else {continue};
+        let lead = transformer.from_world_i32(origin + bb.last_vel * balance.lead);
+        let mut points = search_points(transformer.from_world_i32(origin), lead, &trespassable, balance.radius, balance.max_points);
+        // whoever searches around the same spot shares the points left
+        let mut squad = Vec::new();
+        for (other, brain, bb, plan, ..) in npcs.iter() {
+            if other == entity {
+                squad.push((other, bb.ipos));
+            } else if brain.current == Behavior::Search && plan.origin.is_some_and(|o| o.distance(origin) < balance.share_radius) {
+                squad.push((other, bb.ipos));
+                points.extend(plan.points.iter().copied());
+            }
+        }
+        let mut seen = HashSet::new();
+        points.retain(|point| seen.insert(*point));
+        let positions: Vec<IVec2> = squad.iter().map(|(_, pos)| *pos).collect();
+        for ((member, _), share) in squad.into_iter().zip(split_points(&points, &positions)) {
+            let Ok((_, _, _, mut plan, mut path, ..)) = npcs.get_mut(member) else {continue};
+            if member == entity {
+                plan.origin = Some(origin);
+                plan.timer = Timer::new(Duration::from_secs_f32(balance.duration), TimerMode::Once);
+                plan.look = Timer::new(Duration::from_secs_f32(balance.look_time), TimerMode::Once);
+            }
+            if plan.points.front() != share.first() {
+                path.path = None;
+            }
+            plan.points = share.into();
+        }
+    }
+
+    for (entity, brain, mut bb, mut plan, mut path, mut animation_controller, in_flight) in npcs.iter_mut() {
+        if brain.current != Behavior::Search || !bb.active || plan.origin.is_none() {continue}
+        plan.timer.tick(time.delta());
+        if plan.timer.finished() || (plan.points.is_empty() && plan.look.finished()) {
+            bb.search_at = None;
+            plan.origin = None;
+            plan.points.clear();
+            continue;
+        }
+        if !plan.look.finished() {
+            plan.look.tick(time.delta());
+            // a quarter turn every quarter of the look
+            let quarter = (plan.look.fraction() * 4.) as usize;
+            match quarter {
+                0 => animation_controller.turn_up(),
+                1 => animation_controller.turn_right(),
+                2 => animation_controller.turn_down(),
+                _ => animation_controller.turn_left(),
+            }
+            animation_controller.play_idle_priority(1);
+            continue;
+        }
+        let Some(&point) = plan.points.front() else {continue};
+        if bb.ipos == point || path.failed == Some(NpcState::Look) {
+            path.failed = None;
+            path.path = None;
+            plan.points.pop_front();
+            if bb.ipos == point {
+                plan.look.reset();
+            }
+            continue;
+        }
+        if path.path.is_none() && !in_flight {
+            commands.entity(entity).insert(PathRequest::new(bb.ipos, point, NpcState::Look, true));
+        }
+    }
+}
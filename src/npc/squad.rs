@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use bevy::{math::ivec2, prelude::*};
+
+use crate::{balance::{GameBalance, SquadBalance}, map::{plugin::TrespassableCells, tilemap::TransformToGrid}, systems::{until_dawn, DayCycle, RunStart}};
+
+use super::{ai::Blackboard, components::{AttackTimer, Hunter}};
+
+/// What a hunter does for its squad
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Role {
+    /// Comes at the player from the side
+    Flanker,
+    /// Keeps its distance and throws
+    Thrower,
+    /// Stands where the player is heading
+    Blocker,
+}
+
+impl Role {
+    /// How far from its spot, in cells, the hunter may stand
+    pub fn reach(&self, balance: &SquadBalance) -> i32 {
+        match self {
+            Role::Blocker => balance.block_reach,
+            Role::Flanker | Role::Thrower => balance.reach,
+        }
+    }
+
+    /// Seconds between throws, a blocker holds its spot instead
+    pub fn throw_cd(&self, balance: &GameBalance) -> Option<f32> {
+        match self {
+            Role::Thrower => Some(balance.hunter.throw_cd * balance.squad.thrower_cd),
+            Role::Flanker => Some(balance.hunter.throw_cd),
+            Role::Blocker => None,
+        }
+    }
+}
+
+/// A hunter's place in its squad, none while it fights alone
+#[derive(Component, Default, Debug)]
+pub struct SquadMember {
+    pub role: Option<Role>,
+    /// Its turn in the squad and the squad's size, the throws are staggered by it
+    pub turn: Option<(usize, usize)>,
+}
+
+/// Splits hunters that are closer than `radius` to each other, or to another hunter of the
+/// group, into squads. The squads are given as indices into `hunters`.
+pub fn group_squads(hunters: &[Vec2], radius: f32) -> Vec<Vec<usize>> {
+    let mut squad_of = vec![None; hunters.len()];
+    let mut squads = Vec::new();
+    for first in 0..hunters.len() {
+        if squad_of[first].is_some() {continue}
+        let mut squad = vec![first];
+        squad_of[first] = Some(squads.len());
+        let mut i = 0;
+        while i < squad.len() {
+            let pos = hunters[squad[i]];
+            for other in 0..hunters.len() {
+                if squad_of[other].is_none() && hunters[other].distance(pos) < radius {
+                    squad_of[other] = Some(squads.len());
+                    squad.push(other);
+                }
+            }
+            i += 1;
+        }
+        squad.sort();
+        squads.push(squad);
+    }
+    squads
+}
+
+/// The roles of a squad standing at `members` and where the flankers and the blocker should
+/// stand, `ring` away from the player. Squads of three or more get a blocker in the way the player
+/// is `heading`, then up to two flankers take the sides, the rest throw. Every spot goes to the
+/// closest hunter left.
+pub fn assign_roles(members: &[Vec2], player: Vec2, heading: Vec2, ring: f32) -> Vec<(Role, Option<Vec2>)> {
+    let mut roles = vec![(Role::Thrower, None); members.len()];
+    if members.len() < 2 {return roles}
+    let heading = heading.normalize_or_zero();
+    let mut spots = Vec::new();
+    if members.len() >= 3 {
+        spots.push((Role::Blocker, player + heading * ring));
+    }
+    for side in [heading.perp(), -heading.perp()].into_iter().take(members.len() - 1 - spots.len()) {
+        spots.push((Role::Flanker, player + side * ring));
+    }
+    let mut taken = vec![false; members.len()];
+    for (role, spot) in spots {
+        let Some(closest) = (0..members.len())
+            .filter(|&i| !taken[i])
+            .min_by(|&a, &b| members[a].distance_squared(spot).total_cmp(&members[b].distance_squared(spot)))
+            else {break};
+        taken[closest] = true;
+        roles[closest] = (role, Some(spot));
+    }
+    roles
+}
+
+/// The walkable cell closest to `spot`, no further than `reach` cells, that sees `target` in a
+/// straight line
+pub fn slot_cell(spot: IVec2, target: IVec2, cells: &TrespassableCells, reach: i32) -> Option<IVec2> {
+    let mut best: Option<((i32, i32, i32), IVec2)> = None;
+    for x in -reach..=reach {
+        for y in -reach..=reach {
+            let cell = spot + ivec2(x, y);
+            if !cells.is_trespassable(&cell) || !cells.line_of_sight(cell, target) {continue}
+            let key = ((cell - spot).length_squared(), cell.x, cell.y);
+            if best.map_or(true, |(best, _)| key < best) {
+                best = Some((key, cell));
+            }
+        }
+    }
+    best.map(|(_, cell)| cell)
+}
+
+/// Groups the hunters after the player into squads, hands out roles and the spots around the
+/// player, sets every role's throw cadence, staggers the throws of every squad and calls the
+/// retreat when dawn is close.
+/// Runs before the hunters think, lone hunters are left to their own brain.
+pub fn form_squads(
+    mut hunters: Query<(Entity, &mut Blackboard, &mut SquadMember, &mut AttackTimer), With<Hunter>>,
+    trespassable: Res<TrespassableCells>,
+    transformer: Res<TransformToGrid>,
+    day_cycle: Res<DayCycle>,
+    run_start: Res<RunStart>,
+    time: Res<Time<Virtual>>,
+    balance: Res<GameBalance>,
+) {
+    let engaged: Vec<(Entity, Vec2)> = hunters.iter()
+        .filter(|(_, bb, ..)| bb.active && !bb.dead && (bb.sees_player || bb.last_known.is_some()))
+        .map(|(entity, bb, ..)| (entity, bb.pos))
+        .collect();
+    for (_, mut bb, mut member, _) in hunters.iter_mut() {
+        bb.slot = None;
+        bb.role = None;
+        bb.retreating = false;
+        member.role = None;
+    }
+    let dawn = day_cycle.is_night
+        && until_dawn(run_start.elapsed(&time).as_secs_f32(), &balance.daycycle) < balance.squad.retreat_before;
+    let positions: Vec<Vec2> = engaged.iter().map(|(_, pos)| *pos).collect();
+    let mut turns = Vec::new();
+    for squad in group_squads(&positions, balance.squad.radius) {
+        if squad.len() < 2 {continue}
+        let members: Vec<Vec2> = squad.iter().map(|&i| positions[i]).collect();
+        let Ok((_, bb, ..)) = hunters.get(engaged[squad[0]].0) else {continue};
+        let (player, player_ipos) = (bb.player_pos, bb.player_ipos);
+        let centroid = members.iter().sum::<Vec2>() / members.len() as f32;
+        // a standing player is cut off from the side away from the squad
+        let heading = if bb.last_vel.length() > 1. {bb.last_vel} else {player - centroid};
+        let roles = assign_roles(&members, player, heading, balance.squad.ring);
+        let throwing = roles.iter().filter(|(role, _)| role.throw_cd(&balance).is_some()).count();
+        let mut turn = 0;
+        for (&i, (role, spot)) in squad.iter().zip(roles) {
+            let Ok((entity, mut bb, mut member, _)) = hunters.get_mut(engaged[i].0) else {continue};
+            member.role = Some(role);
+            bb.role = Some(role);
+            bb.slot = spot.and_then(|spot| slot_cell(transformer.from_world_i32(spot), player_ipos, &trespassable, role.reach(&balance.squad)));
+            bb.retreating = dawn;
+            if role.throw_cd(&balance).is_some() {
+                turns.push((entity, (turn, throwing)));
+                turn += 1;
+            }
+        }
+    }
+    for (entity, _, mut member, mut attack_timer) in hunters.iter_mut() {
+        let throw_cd = member.role.map_or(Some(balance.hunter.throw_cd), |role| role.throw_cd(&balance));
+        if let Some(throw_cd) = throw_cd.map(Duration::from_secs_f32) {
+            if attack_timer.timer.duration() != throw_cd {
+                attack_timer.timer.set_duration(throw_cd);
+            }
+        }
+        let turn = turns.iter().find(|(member, _)| *member == entity).map(|(_, turn)| *turn);
+        if member.turn == turn {continue}
+        // the squad changed, spread the throws over the cooldown again
+        if let Some((turn, size)) = turn {
+            let offset = attack_timer.timer.duration().mul_f32(turn as f32 / size as f32);
+            attack_timer.timer.set_elapsed(offset);
+        }
+        member.turn = turn;
+    }
+}
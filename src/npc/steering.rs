@@ -0,0 +1,117 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier2d::prelude::Velocity;
+
+use crate::{balance::{GameBalance, SteeringBalance}, player::components::Player};
+
+use super::components::{Civilian, Hunter, NpcState};
+
+/// A body steered around, or just avoided in the case of the player
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Agent {
+    pub entity: Entity,
+    pub pos: Vec2,
+    pub vel: Vec2,
+}
+
+/// NPCs and the player bucketed by position, rebuilt every frame so neighbors are found
+/// without comparing every pair.
+#[derive(Resource, Debug)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<Agent>>,
+}
+
+impl Default for SpatialHash {
+    fn default() -> Self {
+        SpatialHash::new(32.)
+    }
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialHash {cell_size, cells: HashMap::new()}
+    }
+
+    fn cell(&self, pos: Vec2) -> IVec2 {
+        (pos / self.cell_size).floor().as_ivec2()
+    }
+
+    pub fn clear(&mut self) {
+        // keeps the buckets' allocations around for the next frame
+        for agents in self.cells.values_mut() {
+            agents.clear();
+        }
+    }
+
+    pub fn insert(&mut self, agent: Agent) {
+        let cell = self.cell(agent.pos);
+        self.cells.entry(cell).or_default().push(agent);
+    }
+
+    /// Every agent closer than `radius` to `pos`
+    pub fn neighbors(&self, pos: Vec2, radius: f32) -> impl Iterator<Item = &Agent> {
+        let min = self.cell(pos - radius);
+        let max = self.cell(pos + radius);
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |agent| agent.pos.distance_squared(pos) < radius * radius)
+    }
+}
+
+/// Bends the velocity the path asks for: neighbors push each other apart and NPCs about to
+/// bump into someone sidestep the point of the closest approach, like velocity obstacles.
+/// Never faster than asked, but standing NPCs move up to `idle_speed` to make room.
+pub fn steer<'a>(agent: &Agent, neighbors: impl Iterator<Item = &'a Agent>, balance: &SteeringBalance) -> Vec2 {
+    let mut separation = Vec2::ZERO;
+    let mut avoidance = Vec2::ZERO;
+    for other in neighbors {
+        if other.entity == agent.entity {continue}
+        let offset = agent.pos - other.pos;
+        let dist = offset.length();
+        if dist >= balance.radius {continue}
+        // on the same spot, the entity order picks the sides
+        let away = if dist > 0.001 {offset / dist} else if agent.entity < other.entity {Vec2::X} else {Vec2::NEG_X};
+        separation += away * (1. - dist / balance.radius);
+
+        let rel_vel = agent.vel - other.vel;
+        let speed_sq = rel_vel.length_squared();
+        if speed_sq < 0.01 {continue}
+        let t = -offset.dot(rel_vel) / speed_sq;
+        if t <= 0. || t > balance.horizon {continue}
+        let closest = offset + rel_vel * t;
+        if closest.length() >= balance.body_radius * 2. {continue}
+        avoidance += closest.try_normalize().unwrap_or(away.perp()) * (1. - t / balance.horizon);
+    }
+    let speed = agent.vel.length().max(balance.idle_speed);
+    let push = (separation * balance.separation + avoidance * balance.avoidance) * speed;
+    (agent.vel + push).clamp_length_max(speed)
+}
+
+/// Runs after the NPCs picked their velocities from their paths, before physics moves them.
+pub fn steer_npcs(
+    mut hash: ResMut<SpatialHash>,
+    mut npcs: Query<(Entity, &Transform, &mut Velocity, &NpcState), Or<(With<Civilian>, With<Hunter>)>>,
+    player: Query<(Entity, &Transform, &Velocity), (With<Player>, Without<Civilian>, Without<Hunter>)>,
+    balance: Res<GameBalance>,
+) {
+    hash.clear();
+    for (entity, transform, velocity, state) in npcs.iter() {
+        if *state == NpcState::Dead {continue}
+        hash.insert(Agent {entity, pos: transform.translation.xy(), vel: velocity.linvel});
+    }
+    let player = player.get_single().ok().map(|(entity, transform, velocity)| {
+        hash.insert(Agent {entity, pos: transform.translation.xy(), vel: velocity.linvel});
+        entity
+    });
+    let balance = &balance.steering;
+    for (entity, transform, mut velocity, state) in npcs.iter_mut() {
+        if *state == NpcState::Dead {continue}
+        let agent = Agent {entity, pos: transform.translation.xy(), vel: velocity.linvel};
+        // whoever is after the player has to reach them
+        let chasing = matches!(state, NpcState::Chase | NpcState::Attack);
+        let neighbors = hash.neighbors(agent.pos, balance.radius).filter(|other| !chasing || Some(other.entity) != player);
+        velocity.linvel = steer(&agent, neighbors, balance);
+    }
+}
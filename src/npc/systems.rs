@@ -1,39 +1,31 @@
 use std::{f32::consts::PI, time::Duration};
 
 use bevy::{color::palettes::css::{BLUE, RED}, math::uvec2, prelude::*};
+use bevy_ecs_ldtk::prelude::LdtkProject;
 use bevy_rapier2d::prelude::*;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 use crate::{
-    characters::animation::*, core::functions::TextureAtlasLayoutHandles, map::{plugin::{CivilianSpawner, CollectableRose, CollectableRoseSpawner, HunterSpawner, RespawnRosesEvent, TrespassableCells}, 
-    tilemap::{RaycastableHelp, Structure, TransformToGrid}}, player::{components::{HitPlayer, KillNpc, KillPlayer, Player}, systems::{PlayerController, BULLET_CG, NPC_CG, PLAYER_CG, RAYCASTABLE_STRUCT_CG, STRUCTURES_CG}}, sounds::components::PlaySoundEvent, stuff::{spawn_angry_particle, spawn_cililian_body, spawn_hunter_body, spawn_question_particle, spawn_warn_particle}, systems::DayCycle
+    balance::GameBalance, characters::animation::*, core::functions::TextureAtlasLayoutHandles, map::{campaign::{Campaign, LevelCompleted}, plugin::{CivilianSpawner, CollectableRose, CollectableRoseSpawner, HunterSpawner, RespawnRosesEvent, TrespassableCells}, 
+    tilemap::{Structure, TransformToGrid}}, player::{components::{HitPlayer, KillNpc, KillPlayer, Player}, systems::{BULLET_CG, NPC_CG, PLAYER_CG, RAYCASTABLE_STRUCT_CG, STRUCTURES_CG}}, rng::GameRng, screen::Screen, sounds::components::PlaySoundEvent, stuff::{spawn_angry_particle, spawn_cililian_body, spawn_hunter_body}, systems::DayCycle
 };
 
-use super::{components::*, pathfinder};
-
-const SPOT_DIST: f32 = 200.0;
-const SPOT_DIST_CIV: f32 = 100.0;
-const THRESHOLD: f32 = 100.0;
-const UPP_THRESHOLD: f32 = THRESHOLD * 2.0;
-const CIV_MAXSPEED: f32 = 40.0;
-const CIV_ACCEL: f32 = 350.0;
-const PROJ_V: f32 = 150.0;
-const HUNTER_TIMER: f32 = 0.5;
-const HUNTER_MAXSPEED: f32 = 50.0;
-const HUNTER_ACCEL: f32 = 450.0;
+use super::{ai::*, alert::AlertLevel, components::*, flowfield::FlowFields, pathfinder::smooth_path, perception::{Noise, Perception}, schedule::Routine, search::SearchPlan, squad::{Role, SquadMember}};
 
 pub fn spawn_civilian(
     mut commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     pos: Vec2,
     layout_handles: &mut ResMut<TextureAtlasLayoutHandles>,
+    rng: &mut impl Rng,
 ) {
-    let entity = spawn_civilian_animation_bundle(&mut commands, asset_server, layout_handles);
+    let entity = spawn_civilian_animation_bundle(&mut commands, asset_server, layout_handles, rng);
     commands.entity(entity).insert((
         TransformBundle::from_transform(Transform::from_translation(pos.extend(-2.))),
         RigidBody::Dynamic,
         Velocity::zero(),
         Civilian,
+        NpcId::default(),
         Sleeping::disabled(),
         LockedAxes::ROTATION_LOCKED_Z,
         Collider::ball(4.5),
@@ -42,207 +34,20 @@ pub fn spawn_civilian(
             Group::from_bits(PLAYER_CG | RAYCASTABLE_STRUCT_CG  | STRUCTURES_CG).unwrap()
         ),
         NpcVelAccum {v: Vec2::ZERO},
-        NpcPath {path: None},
-        NpcState::Chill,
+        NpcPath::default(),
+        (NpcState::Chill, Brain::civilian(), Blackboard::default(), Perception::default(), Routine::default()),
         ChillTimer {timer: Timer::new(Duration::from_secs(1), TimerMode::Repeating)},
         AttackTimer {timer: Timer::new(Duration::from_secs_f32(0.5), TimerMode::Repeating)},
         ParticleTimer {timer: Timer::new(Duration::from_secs_f32(1.), TimerMode::Repeating)},
     ));
 }
 
-pub fn manage_civilians(
-    mut commands: Commands,
-    mut civilians_data: Query<(&Transform, &mut Velocity, &mut NpcVelAccum, &mut NpcPath, &mut NpcState,
-        &mut ChillTimer, &mut AnimationController, &mut AttackTimer, &mut ParticleTimer, Entity), With<Civilian>>,
-    mut player_data: Query<(&Transform, Entity, &mut Player)>,
-    time: Res<Time>,
-    day_cycle: Res<DayCycle>,
-    transformer: Res<TransformToGrid>,
-    trespassable: Res<TrespassableCells>,
-    rapier_context: Res<RapierContext>,
-    mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
-    asset_server: Res<AssetServer>,
-    mut hit_player: EventWriter<HitPlayer>,
-    mut play_sound: EventWriter<PlaySoundEvent>,
-) {
-    if let Ok((player_transform, player_entity, mut player)) = player_data.get_single_mut() {
-    if player.is_dead {return;}
-    let player_pos = player_transform.translation.xy();
-    let player_ipos = transformer.from_world_i32(player_pos);
-    let dt = time.delta_seconds();
-    let mut rng = thread_rng();
-    for (civ_transform, mut civ_controller,
-        mut vel_accum , mut civ_path,
-        mut civ_state, mut chill_timer,
-        mut animation_controller,
-        mut attack_timer, mut particle_timer,
-        civ_entity) in civilians_data.iter_mut() {
-        let civ_pos = civ_transform.translation.xy();
-        if civ_pos.distance(player_pos) > 1000. {
-            continue;
-        }
-        let civ_ipos = transformer.from_world_i32(civ_pos);
-        let direction = player_pos - civ_pos;
-        let length = direction.length();
-        let mut player_in_sight = false;
-        if let Some(last_seen_entity) = raycast(civ_pos, direction / length, length, &rapier_context) {
-        if last_seen_entity == player_entity && length < SPOT_DIST_CIV {
-            player_in_sight = true;
-        }
-        // println!("{:?} {}", civ_state, player_in_sight);
-        match *civ_state {
-            NpcState::Look => {},
-            NpcState::Dead => {
-                attack_timer.timer.tick(Duration::from_secs_f32(dt));
-                animation_controller.play_hurt();
-                commands.entity(civ_entity).remove::<Collider>();
-                if attack_timer.timer.finished() {
-                    spawn_cililian_body(&mut commands, &mut layout_handles, &asset_server, civ_pos.extend(0.));
-                    commands.entity(civ_entity).despawn_recursive();
-                }
-            },
-            NpcState::Attack => {
-                particle_timer.timer.tick(Duration::from_secs_f32(dt));
-                if particle_timer.timer.finished() {
-                    spawn_angry_particle(&mut commands, &mut layout_handles, &asset_server, civ_pos.extend(0.));
-                }
-                if attack_timer.timer.elapsed_secs() == 0. {
-                    play_sound.send(PlaySoundEvent::Hit);
-                    animation_controller.play_civil_attack();
-                }
-                attack_timer.timer.tick(Duration::from_secs_f32(dt));
-                if attack_timer.timer.finished() {
-                    if player_pos.distance(civ_pos) < 16. {
-                        hit_player.send(HitPlayer { dmg_type: 1,});
-                    }
-                    *civ_state = NpcState::Chase;
-                    attack_timer.timer.set_elapsed(Duration::from_secs(0))
-                }
-            },
-            state => { // esc cha chi
-                let mut stop = false;
-                if state == NpcState::Chill {
-                    animation_controller.disarm();
-                    animation_controller.play_idle_priority(1);
-
-                    if civ_path.path.is_none() {
-                        chill_timer.timer.tick(Duration::from_secs_f32(dt));
-                        if chill_timer.timer.finished() {
-                            let end = civ_ipos + IVec2::new(rng.gen_range(-2..2), rng.gen_range(-2..2));
-                            if trespassable.is_trespassable(&end) {
-                                civ_path.path = pathfinder(civ_ipos, end, &trespassable, &transformer, state, false);
-                            }
-                        }
-                    }
-                    if player_in_sight {
-                        spawn_warn_particle(&mut commands, &mut layout_handles, &asset_server, civ_pos.extend(0.));
-                        if day_cycle.is_night {
-                            *civ_state = NpcState::Escape;
-                        } else {
-                            *civ_state = NpcState::Chase;
-                        }
-                    }
-                } else if state == NpcState::Escape {
-                    animation_controller.disarm();
-                    civ_path.path = pathfinder(civ_ipos, player_ipos, &trespassable, &transformer, state, false);
-                    if !day_cycle.is_night {
-                        if player_in_sight {
-                            *civ_state = NpcState::Chase;
-                        } else {
-                            *civ_state = NpcState::Chill;
-                        }
-                    }
-                } else { // chase
-                    particle_timer.timer.tick(Duration::from_secs_f32(dt));
-                    if particle_timer.timer.finished() {
-                        spawn_angry_particle(&mut commands, &mut layout_handles, &asset_server, civ_pos.extend(0.));
-                    }
-                    animation_controller.arm();
-                    civ_path.path = pathfinder(civ_ipos, player_ipos, &trespassable, &transformer, state, false);
-                    if player_in_sight {
-                        if day_cycle.is_night {
-                            *civ_state = NpcState::Escape;
-                        }
-                    } else {
-                        spawn_question_particle(&mut commands, &mut layout_handles, &asset_server, civ_pos.extend(0.));
-                        *civ_state = NpcState::Chill;
-                        civ_path.path = None;
-                    }
-                    if player_pos.distance(civ_pos) < 16. {
-                        *civ_state = NpcState::Attack;
-                        stop = true;
-                    }
-                    // println!("{:?}", civ_path.path);
-                }
-                
-                let mut del = false;
-                if let Some(path) = &mut civ_path.path {
-                    if civ_ipos == path[1] {
-                        path.remove(0);
-                    }
-                    if path.len() < 2 {
-                        del = true;
-                    }
-                }
-                if del {
-                    civ_path.path = None;
-                }
-                
-                if let Some(path) = &civ_path.path {
-                    let move_dir = transformer.to_world(path[1]) - civ_pos;
-
-                    if move_dir.x.abs() < 0.1 { // x axis is priotirized 
-                        if move_dir.y.abs() > 0.1 {
-                            if move_dir.y.is_sign_positive(){animation_controller.turn_up()}
-                            if move_dir.y.is_sign_negative(){animation_controller.turn_down()}
-                        }
-                    } else {
-                        if move_dir.x.is_sign_positive(){animation_controller.turn_right()}
-                        if move_dir.x.is_sign_negative(){animation_controller.turn_left()}
-                    }
-                    if vel_accum.v.length() > 0.1 {
-                        animation_controller.play_walk_unlooped();
-                    } else {
-                        animation_controller.play_idle_priority(1);
-                    }
-                    vel_accum.v = vel_accum.v.move_towards(move_dir.normalize_or_zero() * CIV_MAXSPEED, dt * CIV_ACCEL);
-                    if vel_accum.v.length() > CIV_MAXSPEED {
-                        vel_accum.v = vel_accum.v.normalize() * CIV_MAXSPEED
-                    }
-                    civ_controller.linvel = vel_accum.v;
-                } else {
-                    civ_controller.linvel = Vec2::ZERO;
-                    if civ_pos.distance(player_pos) > THRESHOLD {
-                        *civ_state = NpcState::Chill
-                    } else {
-                        if player_in_sight {
-                            if day_cycle.is_night {
-                                *civ_state = NpcState::Escape
-                            } else {
-                                *civ_state = NpcState::Chase
-                            }
-                        } else {
-                            *civ_state = NpcState::Chill;
-                        }
-                    }
-                }
-                if stop {
-                    civ_controller.linvel = Vec2::ZERO;
-                    animation_controller.play_idle_priority(1);
-                }
-            }
-        }
-    }
-    }
-    }
-}
-
-
 pub fn spawn_hunter(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     pos: Vec2,
     layout_handles: &mut ResMut<TextureAtlasLayoutHandles>,
+    balance: &Res<GameBalance>,
 ) {
     let entity = spawn_hunter_animation_bundle(commands, asset_server, layout_handles);
     commands.entity(entity).insert((
@@ -255,232 +60,411 @@ pub fn spawn_hunter(
             Sleeping::disabled(),
         ),
         Hunter,
+        NpcId::default(),
         LockedAxes::ROTATION_LOCKED_Z,
         NpcVelAccum {v: Vec2::ZERO},
-        NpcPath {path: None},
+        NpcPath::default(),
         Velocity::zero(),
         CollisionGroups::new(
             Group::from_bits(NPC_CG).unwrap(),
             Group::from_bits(PLAYER_CG | RAYCASTABLE_STRUCT_CG  | STRUCTURES_CG).unwrap(),
         ),
-        HunterTimer { timer: Timer::new(Duration::from_secs_f32(HUNTER_TIMER), TimerMode::Repeating) },
-        NpcState::Chill,
+        AttackTimer {timer: Timer::new(Duration::from_secs_f32(balance.hunter.throw_cd), TimerMode::Repeating)},
+        (NpcState::Chill, Brain::hunter(), Blackboard::default(), Perception::default(), SearchPlan::default(), SquadMember::default()),
         ChillTimer {timer: Timer::new(Duration::from_secs(1), TimerMode::Repeating)},
-        PlayerLastPos {pos: IVec2::ZERO},
     ));
 }
 
-pub fn manage_hunters(
+/// Strolls to a random cell nearby now and then, or around where the schedule wants it. Once the
+/// village is alert enough, strolls over to the closest NPC of its kind instead.
+pub fn wander(
+    mut commands: Commands,
+    mut npcs: Query<(Entity, &Brain, &mut Blackboard, &NpcPath, &mut ChillTimer, &mut AnimationController, Has<Hunter>, Has<PathInFlight>)>,
+    trespassable: Res<TrespassableCells>,
+    alert: Res<AlertLevel>,
+    time: Res<Time>,
+    balance: Res<GameBalance>,
+    mut rng: ResMut<GameRng>,
+) {
+    let company: Vec<(Entity, Vec2, IVec2, bool)> = if alert.grouped(&balance.alert) {
+        npcs.iter()
+            .filter(|(_, brain, bb, ..)| brain.current == Behavior::Wander && bb.active)
+            .map(|(entity, _, bb, _, _, _, is_hunter, _)| (entity, bb.pos, bb.ipos, is_hunter))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    for (entity, brain, mut bb, path, mut chill_timer, mut animation_controller, is_hunter, in_flight) in npcs.iter_mut() {
+        if brain.current != Behavior::Wander || !bb.active {continue}
+        // nothing left to look into
+        bb.last_known = None;
+        animation_controller.disarm();
+        animation_controller.play_idle_priority(1);
+        if path.path.is_some() || in_flight {continue}
+        chill_timer.timer.tick(time.delta());
+        if chill_timer.timer.finished() {
+            let pos = bb.pos;
+            let buddy = company.iter()
+                .filter(|(other, other_pos, _, other_is_hunter)| *other != entity && *other_is_hunter == is_hunter
+                    && other_pos.distance(pos) < balance.alert.group_radius)
+                .min_by(|a, b| a.1.distance_squared(pos).total_cmp(&b.1.distance_squared(pos)));
+            let around = bb.destination.or(buddy.map(|(_, _, ipos, _)| *ipos)).unwrap_or(bb.ipos);
+            let end = around + IVec2::new(rng.ai.gen_range(-2..2), rng.ai.gen_range(-2..2));
+            if trespassable.is_trespassable(&end) {
+                commands.entity(entity).insert(PathRequest::new(bb.ipos, end, NpcState::Chill, is_hunter));
+            }
+        }
+    }
+}
+
+/// Runs down the flow field away from the player. Civilians calm down once out of reach,
+/// cornered hunters fight back.
+pub fn flee(
     mut commands: Commands,
+    mut npcs: Query<(&Brain, &mut Blackboard, &mut NpcPath, &mut AttackTimer, &mut AnimationController, Has<Hunter>)>,
+    flow_fields: Res<FlowFields>,
+    trespassable: Res<TrespassableCells>,
     asset_server: Res<AssetServer>,
-    mut hunters_data: Query<(&Transform, &mut Velocity,
-        &mut NpcVelAccum, &mut NpcPath, &mut HunterTimer, &mut NpcState,
-        &mut ChillTimer, &mut AnimationController, &mut PlayerLastPos, Entity), Without<Player>>,
-    player_data: Query<(&Transform, &PlayerController, Entity, &Player)>,
-    transformer: Res<TransformToGrid>,
+    mut atlas_handles: ResMut<TextureAtlasLayoutHandles>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
+    time: Res<Time>,
+    balance: Res<GameBalance>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (brain, mut bb, mut path, mut attack_timer, mut animation_controller, is_hunter) in npcs.iter_mut() {
+        if brain.current != Behavior::Flee || !bb.active {continue}
+        animation_controller.disarm();
+        path.path = flow_fields.path(NpcState::Escape, &trespassable, bb.ipos, is_hunter);
+        if path.path.is_some() {continue}
+        if is_hunter {
+            throw_at_player(&mut commands, &asset_server, &mut atlas_handles, &mut play_sound, &mut rng,
+                &bb, &mut attack_timer, &mut animation_controller, time.delta(), balance.hunter.projectile_speed);
+        } else if !bb.sees_player || bb.player_dist > balance.civilian.threshold {
+            bb.last_known = None;
+        }
+    }
+}
+
+/// Walks down the flow field toward the player
+pub fn chase_to(
+    mut commands: Commands,
+    mut npcs: Query<(&Brain, &Blackboard, &mut NpcPath, &mut AnimationController, Option<&mut ParticleTimer>, Has<Hunter>)>,
+    flow_fields: Res<FlowFields>,
     trespassable: Res<TrespassableCells>,
-    rapier_context: Res<RapierContext>,
+    mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
+    asset_server: Res<AssetServer>,
     time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (brain, bb, mut path, mut animation_controller, particle_timer, is_hunter) in npcs.iter_mut() {
+        if brain.current != Behavior::ChaseTo || !bb.active {continue}
+        if let Some(mut particle_timer) = particle_timer {
+            particle_timer.timer.tick(time.delta());
+            if particle_timer.timer.finished() {
+                spawn_angry_particle(&mut commands, &mut layout_handles, &asset_server, bb.pos.extend(0.), &mut rng.cosmetics);
+            }
+        }
+        if !is_hunter {
+            animation_controller.arm();
+        }
+        path.path = flow_fields.path(NpcState::Chase, &trespassable, bb.ipos, is_hunter);
+    }
+}
+
+/// Stands and throws at the player whenever the cooldown allows
+pub fn throw_at(
+    mut commands: Commands,
+    mut npcs: Query<(&Brain, &Blackboard, &mut AttackTimer, &mut AnimationController)>,
+    asset_server: Res<AssetServer>,
     mut atlas_handles: ResMut<TextureAtlasLayoutHandles>,
     mut play_sound: EventWriter<PlaySoundEvent>,
+    time: Res<Time>,
+    balance: Res<GameBalance>,
+    mut rng: ResMut<GameRng>,
 ) {
-    if let Ok(player_data) = player_data.get_single() {
-    if player_data.3.is_dead {return;}
-    let player_pos = player_data.0.translation.xy();
-    let player_ipos = transformer.from_world_i32(player_pos);
-    let player_vel = player_data.1.accumulated_velocity;
-    let player_entity = player_data.2;
-    let dt = time.delta_seconds();
-    for (hunter_transform, mut hunter_controller,
-        mut vel_accum , mut hunter_path,
-        mut hunter_timer, mut hunter_state, mut chill_timer,
-        mut animation_controller, mut player_last_pos,
-        hunter_entity) in hunters_data.iter_mut() {
-        hunter_controller.linvel = Vec2::ZERO;
-        let hunter_pos = hunter_transform.translation.xy();
-        let hunter_ipos = transformer.from_world_i32(hunter_pos);
-        if hunter_pos.distance(player_pos) > 1000. {
+    for (brain, bb, mut attack_timer, mut animation_controller) in npcs.iter_mut() {
+        if brain.current != Behavior::ThrowAt || !bb.active {continue}
+        throw_at_player(&mut commands, &asset_server, &mut atlas_handles, &mut play_sound, &mut rng,
+            bb, &mut attack_timer, &mut animation_controller, time.delta(), balance.hunter.projectile_speed);
+    }
+}
+
+/// Swings at the player, the swing finishes before anything else is picked
+pub fn melee(
+    mut commands: Commands,
+    mut npcs: Query<(&Brain, &mut Blackboard, &mut AttackTimer, &mut ParticleTimer, &mut AnimationController)>,
+    mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
+    asset_server: Res<AssetServer>,
+    mut hit_player: EventWriter<HitPlayer>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
+    mut noises: EventWriter<Noise>,
+    time: Res<Time>,
+    balance: Res<GameBalance>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (brain, mut bb, mut attack_timer, mut particle_timer, mut animation_controller) in npcs.iter_mut() {
+        if brain.current != Behavior::Melee || !bb.active {continue}
+        particle_timer.timer.tick(time.delta());
+        if particle_timer.timer.finished() {
+            spawn_angry_particle(&mut commands, &mut layout_handles, &asset_server, bb.pos.extend(0.), &mut rng.cosmetics);
+        }
+        if attack_timer.timer.elapsed_secs() == 0. {
+            play_sound.send(PlaySoundEvent::Hit);
+            noises.send(Noise {pos: bb.pos, loudness: balance.perception.hit_noise});
+            animation_controller.play_civil_attack();
+        }
+        bb.busy = true;
+        attack_timer.timer.tick(time.delta());
+        if attack_timer.timer.finished() {
+            if bb.player_dist < MELEE_RANGE {
+                hit_player.send(HitPlayer { dmg_type: 1,});
+            }
+            attack_timer.timer.set_elapsed(Duration::ZERO);
+            bb.busy = false;
+        }
+    }
+}
+
+/// Walks to the spot the squad gave the hunter, then throws from there, or holds it for a blocker
+pub fn surround(
+    mut commands: Commands,
+    mut npcs: Query<(Entity, &Brain, &Blackboard, &mut NpcPath, &mut AttackTimer, &mut AnimationController, Has<PathInFlight>)>,
+    asset_server: Res<AssetServer>,
+    mut atlas_handles: ResMut<TextureAtlasLayoutHandles>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
+    time: Res<Time>,
+    balance: Res<GameBalance>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (entity, brain, bb, mut path, mut attack_timer, mut animation_controller, in_flight) in npcs.iter_mut() {
+        if brain.current != Behavior::Surround || !bb.active {continue}
+        let Some(slot) = bb.slot else {continue};
+        let reached = (bb.ipos - slot).abs().max_element() <= 1;
+        // a blocker stands in the way and keeps its hands free
+        if reached && bb.role == Some(Role::Blocker) {
+            path.path = None;
+            face_player(bb, &mut animation_controller);
+            animation_controller.play_idle_priority(1);
+            continue;
+        }
+        // no way there, throws from here until the next try
+        if reached || path.failed == Some(NpcState::Attack) {
+            path.failed = None;
+            if reached {
+                path.path = None;
+            }
+            throw_at_player(&mut commands, &asset_server, &mut atlas_handles, &mut play_sound, &mut rng,
+                bb, &mut attack_timer, &mut animation_controller, time.delta(), balance.hunter.projectile_speed);
             continue;
         }
-        let direction = player_pos - hunter_pos;
-        let length = direction.length();
-        let mut player_in_sight = false;
-        if let Some(last_seen_entity) = raycast(hunter_pos, direction / length, length, &rapier_context) {
-        if last_seen_entity == player_entity && length < SPOT_DIST{
-            player_in_sight = true;
+        // the spot follows the player, a path to where it was is good enough nearby
+        if path.path.as_ref().is_some_and(|path| path.last().is_some_and(|end| (*end - slot).abs().max_element() > 2)) {
+            path.path = None;
         }
+        if path.path.is_none() && !in_flight {
+            commands.entity(entity).insert(PathRequest::new(bb.ipos, slot, NpcState::Attack, true));
+        }
+    }
+}
 
-        match *hunter_state {
-            NpcState::Attack => {
-                hunter_timer.timer.tick(Duration::from_secs_f32(dt));
-                let dir = player_pos - hunter_pos;
-                if dir.x.abs() > dir.y.abs() {
-                    if dir.x > 0. {
-                        animation_controller.turn_right()
-                    } else {
-                        animation_controller.turn_left()
-                    }
-                } else {
-                    if dir.y > 0. {
-                        animation_controller.turn_up()
-                    } else {
-                        animation_controller.turn_down()
-                    }
-                }
-                if player_in_sight {
-                if hunter_timer.timer.finished() {
-                animation_controller.play_hunter_throw();
-                play_sound.send(PlaySoundEvent::Throw);
-                if let Some(intercept) = calculate_intercept(hunter_pos, player_pos, player_vel, PROJ_V) {
-                    let dir = intercept - hunter_pos;
-                    let dir = dir / dir.length();
-                    let throwable_variant = rand::thread_rng().gen_range(0..4);
-                    
+/// Falls back with the squad, down the flow field until far enough from the player
+pub fn retreat(
+    mut npcs: Query<(&Brain, &Blackboard, &mut NpcPath, &mut AnimationController)>,
+    flow_fields: Res<FlowFields>,
+    trespassable: Res<TrespassableCells>,
+    balance: Res<GameBalance>,
+) {
+    let dist = balance.squad.retreat_dist;
+    for (brain, bb, mut path, mut animation_controller) in npcs.iter_mut() {
+        if brain.current != Behavior::Retreat || !bb.active {continue}
+        animation_controller.disarm();
+        if path.path.is_some() {continue}
+        let Some(goal) = flow_fields.goal else {continue};
+        let away = flow_fields.away.path(&trespassable, bb.ipos, |p| p.distance_squared(goal) > dist * dist);
+        path.path = (away.len() > 1).then(|| smooth_path(away, &trespassable));
+    }
+}
 
+/// Walks to where the player was last seen or heard, gives up there or when there is no way
+pub fn investigate(
+    mut commands: Commands,
+    mut npcs: Query<(Entity, &Brain, &mut Blackboard, &mut NpcPath, Has<PathInFlight>, Has<Hunter>)>,
+    transformer: Res<TransformToGrid>,
+) {
+    for (entity, brain, mut bb, mut path, in_flight, is_hunter) in npcs.iter_mut() {
+        if brain.current != Behavior::Investigate || !bb.active {continue}
+        let Some(target) = bb.last_known.map(|pos| transformer.from_world_i32(pos)) else {continue};
+        if bb.ipos == target || path.failed == Some(NpcState::Look) {
+            path.failed = None;
+            bb.search_at = bb.last_known.take();
+            continue;
+        }
+        // heard of a newer spot meanwhile
+        if path.path.as_ref().is_some_and(|path| path.last() != Some(&target)) {
+            path.path = None;
+        }
+        if path.path.is_none() && !in_flight {
+            commands.entity(entity).insert(PathRequest::new(bb.ipos, target, NpcState::Look, is_hunter));
+        }
+    }
+}
 
-                    commands.spawn((TransformBundle::default(), VisibilityBundle::default())).insert((
-                        Transform::from_translation(hunter_pos.extend(0.)),
-                        RigidBody::Dynamic,
-                        Collider::cuboid(3., 3.),
-                        CollisionGroups::new(
-                            Group::from_bits(BULLET_CG).unwrap(),
-                            Group::from_bits(PLAYER_CG | STRUCTURES_CG).unwrap()
-                        ),
-                        LockedAxes::ROTATION_LOCKED_Z,
-                        Velocity {
-                            linvel: PROJ_V * dir,
-                            angvel: 0.0,
-                        },
-                        DespawnTimer { timer: Timer::new(Duration::from_secs(6), TimerMode::Once) },
-                        Projectile,
-                        Sensor,
-                        ActiveEvents::COLLISION_EVENTS,
-                        Sleeping::disabled(),
-                    )).with_children(|commands|{
-                        match throwable_variant {
-                            0 => {commands.spawn(crate::stuff::animated_fork_bundle(&asset_server, &mut atlas_handles));},
-                            1 => {commands.spawn(crate::stuff::animated_knife_bundle(&asset_server, &mut atlas_handles));},
-                            2 => {commands.spawn(crate::stuff::animated_garlic_bundle(&asset_server, &mut atlas_handles));},
-                            _ => {commands.spawn(crate::stuff::stake_bundle(&asset_server, &mut atlas_handles, dir)).insert(
-                                Transform::from_rotation(Quat::from_rotation_z(if throwable_variant != 3 {0.} else {dir.to_angle() + PI * 0.75}),
-                            ));},
-                        };
-                        commands.spawn(
-                            SpriteBundle{
-                                transform: Transform::from_xyz(0.,-6., 0.),
-                                texture: asset_server.load("particles/minishadow.png"),
-                                ..default()
-                            }
-                        );
-                    });
-                }
-            }
-            let dist = player_pos.distance(hunter_pos);
-            if dist < THRESHOLD {
-                *hunter_state = NpcState::Escape;
-            } else if dist < UPP_THRESHOLD {
-                *hunter_state = NpcState::Chase;
-            }
+/// Lies hurt for a moment, then leaves a body behind
+pub fn die(
+    mut commands: Commands,
+    mut npcs: Query<(Entity, &Brain, &Blackboard, &mut AttackTimer, &mut AnimationController, Has<Hunter>)>,
+    mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (entity, brain, bb, mut attack_timer, mut animation_controller, is_hunter) in npcs.iter_mut() {
+        if brain.current != Behavior::Die {continue}
+        attack_timer.timer.tick(time.delta());
+        animation_controller.play_hurt();
+        commands.entity(entity).remove::<Collider>();
+        if attack_timer.timer.finished() {
+            let pos = bb.pos.extend(0.);
+            if is_hunter {
+                spawn_hunter_body(&mut commands, &mut layout_handles, &asset_server, pos, &mut rng.cosmetics);
             } else {
-            *hunter_state = NpcState::Look;
-            player_last_pos.pos = player_ipos;
+                spawn_cililian_body(&mut commands, &mut layout_handles, &asset_server, pos, &mut rng.cosmetics);
             }
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Walks every NPC toward the next waypoint of its path, the ones without a path stand
+pub fn follow_paths(
+    mut npcs: Query<(&Blackboard, &mut NpcPath, &mut NpcVelAccum, &mut Velocity, &mut AnimationController, &NpcState, Has<Hunter>)>,
+    transformer: Res<TransformToGrid>,
+    time: Res<Time>,
+    balance: Res<GameBalance>,
+) {
+    let dt = time.delta_seconds();
+    for (bb, mut npc_path, mut vel_accum, mut controller, mut animation_controller, state, is_hunter) in npcs.iter_mut() {
+        controller.linvel = Vec2::ZERO;
+        if !bb.active || *state == NpcState::Dead {continue}
+        let (max_speed, accel, arrival_radius) = if is_hunter {
+            (balance.hunter.max_speed, balance.hunter.accel, balance.hunter.arrival_radius)
+        } else {
+            (balance.civilian.max_speed, balance.civilian.accel, balance.civilian.arrival_radius)
+        };
+        let mut del = false;
+        if let Some(path) = &mut npc_path.path {
+            if bb.pos.distance(transformer.to_world(path[1])) < arrival_radius {
+                path.remove(0);
             }
-            NpcState::Dead => {
-                hunter_timer.timer.tick(Duration::from_secs_f32(dt));
-                animation_controller.play_hurt();
-                commands.entity(hunter_entity).remove::<Collider>();
-                if hunter_timer.timer.finished() {
-                    spawn_hunter_body(&mut commands, &mut atlas_handles, &asset_server, hunter_pos.extend(0.));
-                    commands.entity(hunter_entity).despawn_recursive();
-                }
+            if path.len() < 2 {
+                del = true;
             }
-            state => {
-                if state == NpcState::Chill {
-                    animation_controller.play_idle_priority(1);
-                    if player_in_sight {
-                        *hunter_state = NpcState::Chase;
-                        spawn_warn_particle(&mut commands, &mut atlas_handles, &asset_server, hunter_pos.extend(0.));
-                    } else {
-                        if hunter_path.path.is_none() {
-                            let mut rng = thread_rng();
-                            chill_timer.timer.tick(Duration::from_secs_f32(dt));
-                            if chill_timer.timer.finished() {
-                                let end = hunter_ipos + IVec2::new(rng.gen_range(-2..2), rng.gen_range(-2..2));
-                                if trespassable.is_trespassable(&end) {
-                                    hunter_path.path = pathfinder(hunter_ipos, end, &trespassable, &transformer, state, true);
-                                }
-                            }
-                        }
-                    }
-                } else if state == NpcState::Look {
-                    if player_in_sight {
-                        *hunter_state = NpcState::Chase;
-                        spawn_warn_particle(&mut commands, &mut atlas_handles, &asset_server, hunter_pos.extend(0.));
-                    } else {
-                        hunter_path.path = pathfinder(hunter_ipos, player_last_pos.pos, &trespassable, &transformer, state, true);
-                        if hunter_path.path.is_none() {
-                            spawn_question_particle(&mut commands, &mut atlas_handles, &asset_server, hunter_pos.extend(0.));
-                            *hunter_state = NpcState::Chill;
-                        }
-                    }
-                    
-                } else { // chase & escape
-                    if player_in_sight {
-                        hunter_path.path = pathfinder(hunter_ipos, player_ipos, &trespassable, &transformer, state, true);
-                        if hunter_path.path.is_none() {
-                        *hunter_state = NpcState::Attack;
-                        }
-                    } else {
-                        *hunter_state = NpcState::Look;
-                        player_last_pos.pos = player_ipos;
-                    }
-                }
-                
-                let mut del = false;
-                if let Some(path) = &mut hunter_path.path {
-                    if hunter_ipos == path[1] {
-                        path.remove(0);
-                    }
-                    if path.len() < 2 {
-                        del = true;
-                    }
-                }
-                if del {
-                    hunter_path.path = None;
-                }
-                
-                if let Some(path) = &hunter_path.path {
-                    let move_dir = transformer.to_world(path[1]) - hunter_pos;
+        }
+        if del {
+            npc_path.path = None;
+        }
+        let Some(path) = &npc_path.path else {continue};
+        let move_dir = transformer.to_world(path[1]) - bb.pos;
 
-                    if move_dir.x.abs() < 0.1 { // x axis is priotirized 
-                        if move_dir.y.abs() > 0.1 {
-                            if move_dir.y.is_sign_positive(){animation_controller.turn_up()}
-                            if move_dir.y.is_sign_negative(){animation_controller.turn_down()}
-                        }
-                    } else {
-                        if move_dir.x.is_sign_positive(){animation_controller.turn_right()}
-                        if move_dir.x.is_sign_negative(){animation_controller.turn_left()}
-                    }
-                    if vel_accum.v.length() > 0.1 {
-                        animation_controller.play_walk_unlooped();
-                    } else {
-                        animation_controller.play_idle_priority(1);
-                    }
-                    
-                    vel_accum.v = vel_accum.v.move_towards(move_dir.normalize_or_zero() * HUNTER_MAXSPEED, dt * HUNTER_ACCEL);
-                    if vel_accum.v.length() > HUNTER_MAXSPEED {
-                        vel_accum.v = vel_accum.v.normalize() * HUNTER_MAXSPEED
-                    }
-                    hunter_controller.linvel = vel_accum.v;
-                }
+        if move_dir.x.abs() < 0.1 { // x axis is priotirized 
+            if move_dir.y.abs() > 0.1 {
+                if move_dir.y.is_sign_positive(){animation_controller.turn_up()}
+                if move_dir.y.is_sign_negative(){animation_controller.turn_down()}
             }
+        } else {
+            if move_dir.x.is_sign_positive(){animation_controller.turn_right()}
+            if move_dir.x.is_sign_negative(){animation_controller.turn_left()}
         }
+        if vel_accum.v.length() > 0.1 {
+            animation_controller.play_walk_unlooped();
+        } else {
+            animation_controller.play_idle_priority(1);
+        }
+        vel_accum.v = vel_accum.v.move_towards(move_dir.normalize_or_zero() * max_speed, dt * accel);
+        if vel_accum.v.length() > max_speed {
+            vel_accum.v = vel_accum.v.normalize() * max_speed
+        }
+        controller.linvel = vel_accum.v;
     }
-    }
+}
+
+fn face_player(bb: &Blackboard, animation_controller: &mut AnimationController) {
+    let dir = bb.player_pos - bb.pos;
+    if dir.x.abs() > dir.y.abs() {
+        if dir.x > 0. {
+            animation_controller.turn_right()
+        } else {
+            animation_controller.turn_left()
+        }
+    } else {
+        if dir.y > 0. {
+            animation_controller.turn_up()
+        } else {
+            animation_controller.turn_down()
+        }
     }
 }
 
+/// Turns to the player and, once the cooldown is over, throws where they will be
+fn throw_at_player(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    atlas_handles: &mut ResMut<TextureAtlasLayoutHandles>,
+    play_sound: &mut EventWriter<PlaySoundEvent>,
+    rng: &mut ResMut<GameRng>,
+    bb: &Blackboard,
+    attack_timer: &mut AttackTimer,
+    animation_controller: &mut AnimationController,
+    dt: Duration,
+    projectile_speed: f32,
+) {
+    attack_timer.timer.tick(dt);
+    face_player(bb, animation_controller);
+    if !attack_timer.timer.finished() {return}
+    animation_controller.play_hunter_throw();
+    play_sound.send(PlaySoundEvent::Throw);
+    let Some(intercept) = calculate_intercept(bb.pos, bb.player_pos, bb.player_vel, projectile_speed) else {return};
+    let dir = intercept - bb.pos;
+    let dir = dir / dir.length();
+    let throwable_variant = rng.ai.gen_range(0..4);
+
+    commands.spawn((TransformBundle::default(), VisibilityBundle::default(), StateScoped(Screen::Playing))).insert((
+        Transform::from_translation(bb.pos.extend(0.)),
+        RigidBody::Dynamic,
+        Collider::cuboid(3., 3.),
+        CollisionGroups::new(
+            Group::from_bits(BULLET_CG).unwrap(),
+            Group::from_bits(PLAYER_CG | STRUCTURES_CG).unwrap()
+        ),
+        LockedAxes::ROTATION_LOCKED_Z,
+        Velocity {
+            linvel: projectile_speed * dir,
+            angvel: 0.0,
+        },
+        DespawnTimer { timer: Timer::new(Duration::from_secs(6), TimerMode::Once) },
+        Projectile,
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        Sleeping::disabled(),
+    )).with_children(|commands|{
+        match throwable_variant {
+            0 => {commands.spawn(crate::stuff::animated_fork_bundle(asset_server, atlas_handles));},
+            1 => {commands.spawn(crate::stuff::animated_knife_bundle(asset_server, atlas_handles));},
+            2 => {commands.spawn(crate::stuff::animated_garlic_bundle(asset_server, atlas_handles));},
+            _ => {commands.spawn(crate::stuff::stake_bundle(asset_server, atlas_handles, dir)).insert(
+                Transform::from_rotation(Quat::from_rotation_z(if throwable_variant != 3 {0.} else {dir.to_angle() + PI * 0.75}),
+            ));},
+        };
+        commands.spawn(
+            SpriteBundle{
+                transform: Transform::from_xyz(0.,-6., 0.),
+                texture: asset_server.load("particles/minishadow.png"),
+                ..default()
+            }
+        );
+    });
+}
+
 fn calculate_intercept(shooter_pos: Vec2, target_pos: Vec2, target_vel: Vec2, proj_vel: f32) -> Option<Vec2> {
     let direction = target_pos - shooter_pos;
     let a = target_vel.dot(target_vel) - proj_vel * proj_vel;
@@ -521,21 +505,23 @@ pub struct RosesCollected {
 pub fn process_collisions(
     mut commands: Commands,
     mut collision_events: EventReader<CollisionEvent>,
-    mut player: Query<(Entity, &Player)>,
+    mut player: Query<(Entity, &Player, &Transform)>,
     mut hunters: Query<&mut NpcState, (With<Hunter>, Without<Civilian>)>,
     mut civilians: Query<&mut NpcState, With<Civilian>>,
     projectiles: Query<&Projectile>,
     structures: Query<&Structure>,
-    help: Query<&RaycastableHelp>,
     roses: Query<Entity, With<CollectableRose>>,
     mut roses_collected: ResMut<RosesCollected>,
     day_cycle: Res<DayCycle>,
     mut hit_player: EventWriter<HitPlayer>,
     mut kill_npc: EventWriter<KillNpc>,
     mut play_sound: EventWriter<PlaySoundEvent>,
-    mut win: EventWriter<Win>
+    mut noises: EventWriter<Noise>,
+    mut win: EventWriter<Win>,
+    balance: Res<GameBalance>,
 ) {
-    if let Ok((player_entity, player)) = player.get_single_mut() {
+    if let Ok((player_entity, player, player_transform)) = player.get_single_mut() {
+        let kill_noise = Noise {pos: player_transform.translation.xy(), loudness: balance.perception.kill_noise};
         for collision_event in collision_events.read() {
             if let CollisionEvent::Started(reciever_entity, sender_entity, _) = collision_event {
                 // player appears to always be reciever
@@ -551,6 +537,7 @@ pub fn process_collisions(
                         *state = NpcState::Dead;
                         kill_npc.send(KillNpc { npc_type: 0 });
                         play_sound.send(PlaySoundEvent::Kill);
+                        noises.send(kill_noise);
                     }
                 } else if let Ok(mut state) = hunters.get_mut(sender_entity) {
                     if day_cycle.is_night {
@@ -558,6 +545,7 @@ pub fn process_collisions(
                         *state = NpcState::Dead;
                         kill_npc.send(KillNpc { npc_type: 1 });
                         play_sound.send(PlaySoundEvent::Kill);
+                        noises.send(kill_noise);
                     } else {
                         if *reciever_entity == player_entity {
                             hit_player.send(HitPlayer { dmg_type: 2});
@@ -565,8 +553,6 @@ pub fn process_collisions(
                     }
                 } else if let Ok(_) = structures.get(sender_entity) {
                     commands.entity(player_entity).remove::<Sensor>();
-                } else if let Ok(_) = help.get(sender_entity) {
-                    commands.entity(player_entity).remove::<Sensor>();
                 } else if let Ok(rose_entity) = roses.get(sender_entity) {
                     if player.is_dead {return;}
                     commands.entity(rose_entity).despawn_recursive();
@@ -580,18 +566,29 @@ pub fn process_collisions(
     }
 }
 
+/// Every rose of the current level was collected
 #[derive(Event)]
 pub struct Win;
 
+/// Moves the campaign on to its next level, the last level wins the run
 pub fn victory(
     mut win: EventReader<Win>,
     mut kill_player: EventWriter<KillPlayer>,
+    mut level_completed: EventWriter<LevelCompleted>,
     mut roses_collected: ResMut<RosesCollected>,
     mut event: EventWriter<RespawnRosesEvent>,
+    campaign: Res<Campaign>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
 ) {
     for _ in win.read() {
-        kill_player.send(KillPlayer { won: true });
         roses_collected.collected = 0;
+        let project = ldtk_projects.get_single().ok().and_then(|handle| ldtk_project_assets.get(handle));
+        if project.is_some_and(|project| campaign.has_next_level(project)) {
+            level_completed.send(LevelCompleted);
+            continue;
+        }
+        kill_player.send(KillPlayer { won: true });
         event.send(RespawnRosesEvent);
     }
 }
@@ -606,16 +603,18 @@ pub fn entity_spawner(
     asset_server: Res<AssetServer>,
     time: Res<Time>,
     day_cycle: Res<DayCycle>,
+    alert: Res<AlertLevel>,
+    balance: Res<GameBalance>,
+    mut rng: ResMut<GameRng>,
 ) {
     let dt = time.delta_seconds();
-    let mut rand = thread_rng();
     for (mut spawner, spawner_gpos) in civilian_spawners.iter_mut() {
         spawner.timer.tick(Duration::from_secs_f32(dt));
         if spawner.timer.finished() {
             let spawner_pos = spawner_gpos.translation().xy();
-            if rand.gen_bool(0.15) {
-                if civilians.iter().len() < 200 && !day_cycle.is_night{
-                    spawn_civilian(&mut commands, &asset_server, spawner_pos, &mut layout_handles);
+            if rng.spawning.gen_bool(balance.civilian.spawn_chance) {
+                if civilians.iter().len() < balance.civilian.max_count && !day_cycle.is_night{
+                    spawn_civilian(&mut commands, &asset_server, spawner_pos, &mut layout_handles, &mut rng.cosmetics);
                 }
             }
         }
@@ -624,16 +623,16 @@ pub fn entity_spawner(
         spawner.timer.tick(Duration::from_secs_f32(dt));
         if spawner.timer.finished() {
             let spawner_pos = spawner_gpos.translation().xy();
-            if rand.gen_bool(0.15) {
-                if hunters.iter().len() < 200 && day_cycle.is_night{
-                    spawn_hunter(&mut commands, &asset_server, spawner_pos, &mut layout_handles);
+            if rng.spawning.gen_bool(alert.hunter_spawn_chance(balance.hunter.spawn_chance, &balance.alert)) {
+                if hunters.iter().len() < balance.hunter.max_count && day_cycle.is_night{
+                    spawn_hunter(&mut commands, &asset_server, spawner_pos, &mut layout_handles, &balance);
                 }
             }
         }
     }
 }
 
-fn raycast(
+pub fn raycast(
     origin: Vec2,
     dir: Vec2,
     max_toi: f32,
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use crate::balance::PlayerBalance;
+
 #[derive(Component)]
 pub struct Player {
     pub hp: f32,
@@ -18,27 +20,33 @@ pub struct Player {
     pub is_dead: bool,
 } 
 
-impl Default for Player {
-    fn default() -> Self {
+impl Player {
+    pub fn new(balance: &PlayerBalance) -> Self {
         Player {
-            hp: 80.,
+            hp: balance.hp,
             xp: 0., 
             score: 0., 
-            max_speed: 60., 
-            accumulation_gain: 500., 
-            phys_res: 0.2, 
-            dash_cd: 1.5,
-            dash_tick: 1.,
-            hp_gain: 5., 
-            xp_gain: 10.,
-            hunger_rate: 2.,
-            max_xp: 100., 
-            max_hp: 80., 
+            max_speed: balance.max_speed, 
+            accumulation_gain: balance.accumulation_gain, 
+            phys_res: balance.phys_res, 
+            dash_cd: balance.dash_cd,
+            dash_tick: balance.dash_tick,
+            hp_gain: balance.hp_gain, 
+            xp_gain: balance.xp_gain,
+            hunger_rate: balance.hunger_rate,
+            max_xp: balance.max_xp, 
+            max_hp: balance.max_hp, 
             is_dead: false
         }
     }
 }
 
+impl Default for Player {
+    fn default() -> Self {
+        Player::new(&PlayerBalance::default())
+    }
+}
+
 
 // armor (phys res); speed; hp gain; xp gain; max hp;
 
@@ -57,7 +65,7 @@ pub struct KillNpc {
     pub npc_type: u8,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum UpgradeButton {
     MaxHp,
     Armor,
@@ -69,6 +77,44 @@ pub enum UpgradeButton {
     DashTick
 }
 
+impl UpgradeButton {
+    pub const ALL: [UpgradeButton; 8] = [
+        UpgradeButton::MaxHp,
+        UpgradeButton::Armor,
+        UpgradeButton::HpGain,
+        UpgradeButton::XpGain,
+        UpgradeButton::Speed,
+        UpgradeButton::HungerRate,
+        UpgradeButton::DashCD,
+        UpgradeButton::DashTick,
+    ];
+
+    pub fn index(&self) -> u8 {
+        UpgradeButton::ALL.iter().position(|u| u == self).unwrap() as u8
+    }
+
+    pub fn from_index(index: u8) -> Option<Self> {
+        UpgradeButton::ALL.get(index as usize).copied()
+    }
+}
+
+#[derive(Component)]
+pub struct LvlUpScreen;
+
+/// Everything the player decided this frame. Filled from the keyboard, or from a replay.
+/// Gameplay systems read this instead of polling devices.
+#[derive(Resource, Clone, Default, Debug, PartialEq)]
+pub struct PlayerInput {
+    /// Not normalized
+    pub move_dir: Vec2,
+    /// Dash was pressed this frame
+    pub dash: bool,
+    /// Upgrade picked on the level up screen this frame
+    pub upgrade: Option<UpgradeButton>,
+    /// The pause menu was opened or closed this frame
+    pub pause: bool,
+}
+
 #[derive(Component)]
 pub struct ParentEntity {
     pub entity: Entity
@@ -1,13 +1,15 @@
-use bevy::prelude::*;
-use components::{DeathTimer, HitPlayer, KillNpc, KillPlayer};
+use bevy::{prelude::*, time::TimeSystem};
+use components::{DeathTimer, HitPlayer, KillNpc, KillPlayer, PlayerInput};
+use replay::*;
 use systems::*;
-use upgrade_ui::interact_upgrade_button;
+use upgrade_ui::{apply_upgrade, interact_upgrade_button};
 
-use crate::{spawn_score, systems::GameState};
+use crate::{controls::ActionSystem, screen::Screen, spawn_score, systems::GameState};
 
 pub mod systems;
 pub mod components;
 pub mod upgrade_ui;
+pub mod replay;
 
 pub struct PlayerPlugin;
 
@@ -18,8 +20,17 @@ impl Plugin for PlayerPlugin {
         .add_event::<KillNpc>()
         .add_event::<KillPlayer>()
         .insert_resource(DeathTimer {timer: Timer::from_seconds(5., TimerMode::Repeating)})
-        .add_systems(Startup, (spawn_player_first_time, spawn_score).chain())
-        .add_systems(Update, ((player_controller, (hit_player, kill_player).chain(), kill_npc, manage_xp).run_if(in_state(GameState::InGame)), interact_upgrade_button))
+        .add_systems(OnEnter(Screen::Playing), (spawn_player_first_time, spawn_score).chain())
+        .init_resource::<PlayerInput>()
+        .add_systems(First, (
+            start_recording.run_if(resource_exists::<ReplayRecorder>),
+            play_replay.run_if(resource_exists::<ReplayPlayer>),
+        ).before(TimeSystem))
+        .add_systems(PreUpdate, read_player_input.after(ActionSystem).run_if(not(resource_exists::<ReplayPlayer>)))
+        .add_systems(Update, ((player_controller, (hit_player, kill_player).chain(), kill_npc, manage_xp).run_if(in_state(GameState::InGame)),
+            (interact_upgrade_button.run_if(not(resource_exists::<ReplayPlayer>)), apply_upgrade).chain()))
+        .add_systems(Last, (record_frame, save_recording).chain().run_if(resource_exists::<ReplayRecorder>))
+        .add_systems(OnExit(Screen::Playing), stop_recording.run_if(resource_exists::<ReplayRecorder>))
         ;
     }
 }
\ No newline at end of file
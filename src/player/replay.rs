@@ -0,0 +1,328 @@
+use std::{fs::File, io::{self, BufReader, BufWriter, Read, Write}, path::{Path, PathBuf}, time::Duration};
+
+use bevy::{app::AppExit, prelude::*, time::TimeUpdateStrategy};
+use thiserror::Error;
+
+use crate::{core::functions::arg_value, map::{plugin::{MapPath, TrespassableCells}, tilemap::TransformToGrid}, npc::{components::NpcId, pathfinder::PathLandings}, rng::GameRng, systems::{GameState, RunStart}};
+
+use super::components::{KillPlayer, PlayerInput, UpgradeButton};
+
+const MAGIC: &[u8; 4] = b"VRPL";
+const VERSION: u8 = 3;
+
+const DASH_FLAG: u8 = 1;
+const UPGRADE_FLAG: u8 = 1 << 1;
+const PAUSE_FLAG: u8 = 1 << 2;
+
+/// A recorded run: everything needed to play it again without a human.
+///
+/// Frames start at the first frame of gameplay, once the level is ready and the game
+/// is not paused, and from there on every frame is stored, paused or not.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub map: String,
+    /// Time since the start of the run at the first recorded frame
+    pub start_elapsed: Duration,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayFrame {
+    pub dt: Duration,
+    pub input: PlayerInput,
+    /// NPCs whose paths came back from the task pool this frame
+    pub paths: Vec<NpcId>,
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("could not access replay file: {0}")]
+    Io(#[from] io::Error),
+    #[error("not a replay file")]
+    BadMagic,
+    #[error("replay version {0} is not supported")]
+    Version(u8),
+    #[error("replay is corrupted: {0}")]
+    Corrupted(&'static str),
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Replay::read_from(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Little endian, a header followed by 15 or 16 bytes per frame and 4 more per landed path.
+    pub fn write_to(&self, mut w: impl Write) -> Result<(), ReplayError> {
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION])?;
+        w.write_all(&self.seed.to_le_bytes())?;
+        let map = self.map.as_bytes();
+        let map_len = u16::try_from(map.len()).map_err(|_| ReplayError::Corrupted("map path is too long"))?;
+        w.write_all(&map_len.to_le_bytes())?;
+        w.write_all(map)?;
+        w.write_all(&(self.start_elapsed.as_nanos() as u64).to_le_bytes())?;
+        w.write_all(&(self.frames.len() as u32).to_le_bytes())?;
+        for frame in &self.frames {
+            // a single frame never lasts 4 seconds, Time<Virtual> clamps way before that
+            w.write_all(&(frame.dt.as_nanos().min(u32::MAX as u128) as u32).to_le_bytes())?;
+            w.write_all(&frame.input.move_dir.x.to_le_bytes())?;
+            w.write_all(&frame.input.move_dir.y.to_le_bytes())?;
+            let mut flags = 0;
+            if frame.input.dash {flags |= DASH_FLAG}
+            if frame.input.upgrade.is_some() {flags |= UPGRADE_FLAG}
+            if frame.input.pause {flags |= PAUSE_FLAG}
+            w.write_all(&[flags])?;
+            if let Some(upgrade) = frame.input.upgrade {
+                w.write_all(&[upgrade.index()])?;
+            }
+            let paths = u16::try_from(frame.paths.len()).map_err(|_| ReplayError::Corrupted("too many paths in a frame"))?;
+            w.write_all(&paths.to_le_bytes())?;
+            for id in &frame.paths {
+                w.write_all(&id.0.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(mut r: impl Read) -> Result<Self, ReplayError> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {return Err(ReplayError::BadMagic)}
+        let version = read_u8(&mut r)?;
+        if version != VERSION {return Err(ReplayError::Version(version))}
+        let seed = u64::from_le_bytes(read_array(&mut r)?);
+        let map_len = u16::from_le_bytes(read_array(&mut r)?) as usize;
+        let mut map = vec![0; map_len];
+        r.read_exact(&mut map)?;
+        let map = String::from_utf8(map).map_err(|_| ReplayError::Corrupted("map path is not utf-8"))?;
+        let start_elapsed = Duration::from_nanos(u64::from_le_bytes(read_array(&mut r)?));
+        let count = u32::from_le_bytes(read_array(&mut r)?) as usize;
+        let mut frames = Vec::with_capacity(count);
+        for _ in 0..count {
+            let dt = Duration::from_nanos(u32::from_le_bytes(read_array(&mut r)?) as u64);
+            let x = f32::from_le_bytes(read_array(&mut r)?);
+            let y = f32::from_le_bytes(read_array(&mut r)?);
+            let flags = read_u8(&mut r)?;
+            let upgrade = if flags & UPGRADE_FLAG != 0 {
+                Some(UpgradeButton::from_index(read_u8(&mut r)?).ok_or(ReplayError::Corrupted("unknown upgrade"))?)
+            } else {
+                None
+            };
+            let path_count = u16::from_le_bytes(read_array(&mut r)?) as usize;
+            let mut paths = Vec::with_capacity(path_count);
+            for _ in 0..path_count {
+                paths.push(NpcId(u32::from_le_bytes(read_array(&mut r)?)));
+            }
+            frames.push(ReplayFrame {
+                dt,
+                input: PlayerInput {
+                    move_dir: Vec2::new(x, y),
+                    dash: flags & DASH_FLAG != 0,
+                    upgrade,
+                    pause: flags & PAUSE_FLAG != 0,
+                },
+                paths,
+            });
+        }
+        Ok(Replay { seed, map, start_elapsed, frames })
+    }
+}
+
+fn read_array<const N: usize>(r: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    Ok(read_array::<1>(r)?[0])
+}
+
+/// Records the run into `replay`, and writes it to `path` (if any) on every death and on exit.
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    pub path: Option<PathBuf>,
+    pub replay: Replay,
+    recording: bool,
+}
+
+impl ReplayRecorder {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        ReplayRecorder { path, replay: Replay::default(), recording: false }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    /// Writes the replay to `path`, if there is one
+    fn save(&self) {
+        let Some(path) = &self.path else {return};
+        match self.replay.save(path) {
+            Ok(()) => info!("Replay saved to {}", path.display()),
+            Err(e) => error!("Failed to save replay to {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Drives the game from `replay` instead of the keyboard. Removed once the replay is over.
+#[derive(Resource)]
+pub struct ReplayPlayer {
+    pub replay: Replay,
+    frame: usize,
+    playing: bool,
+    /// Time strategy to go back to after the replay
+    resume_strategy: Option<TimeUpdateStrategy>,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayer { replay, frame: 0, playing: false, resume_strategy: None }
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Every recorded frame has been fed
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.replay.frames.len()
+    }
+}
+
+/// `--record <path>` and `--replay <path>`
+pub fn replay_from_args() -> (Option<ReplayRecorder>, Option<ReplayPlayer>) {
+    let recorder = arg_value("record").map(|path| ReplayRecorder::new(Some(path.into())));
+    let player = arg_value("replay").and_then(|path| match Replay::load(&path) {
+        Ok(replay) => Some(ReplayPlayer::new(replay)),
+        Err(e) => {
+            error!("Failed to load replay {}: {}", path, e);
+            None
+        }
+    });
+    (recorder, player)
+}
+
+/// Gameplay runs this frame: a run is on, its level is ready and the game is (about to be) unpaused.
+/// Checked in `First`, before state transitions happen.
+fn gameplay_frame(
+    trespassable: &TrespassableCells,
+    transformer: Option<&TransformToGrid>,
+    state: Option<&State<GameState>>,
+    next_state: &NextState<GameState>,
+) -> bool {
+    let Some(state) = state else {return false};
+    let upcoming = match next_state {
+        NextState::Pending(state) => state,
+        NextState::Unchanged => state.get(),
+    };
+    trespassable.ready && transformer.is_some_and(|t| t.ready) && *upcoming == GameState::InGame
+}
+
+pub fn start_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    trespassable: Res<TrespassableCells>,
+    transformer: Option<Res<TransformToGrid>>,
+    state: Option<Res<State<GameState>>>,
+    next_state: Res<NextState<GameState>>,
+    time: Res<Time<Virtual>>,
+    run_start: Res<RunStart>,
+    rng: Res<GameRng>,
+    map: Res<MapPath>,
+) {
+    if recorder.recording {return}
+    if !gameplay_frame(&trespassable, transformer.as_deref(), state.as_deref(), &next_state) {return}
+    recorder.replay = Replay {
+        seed: rng.seed(),
+        map: map.0.clone(),
+        start_elapsed: run_start.elapsed(&time),
+        frames: vec![],
+    };
+    recorder.recording = true;
+    info!("Recording replay, seed {}", rng.seed());
+}
+
+pub fn record_frame(
+    mut recorder: ResMut<ReplayRecorder>,
+    time: Res<Time<Real>>,
+    input: Res<PlayerInput>,
+    mut landings: ResMut<PathLandings>,
+) {
+    let paths = std::mem::take(&mut landings.landed);
+    if !recorder.recording {return}
+    recorder.replay.frames.push(ReplayFrame {
+        dt: time.delta(),
+        input: input.clone(),
+        paths,
+    });
+}
+
+pub fn save_recording(
+    recorder: Res<ReplayRecorder>,
+    mut kill_player: EventReader<KillPlayer>,
+    mut exit: EventReader<AppExit>,
+) {
+    let died = kill_player.read().count() > 0;
+    let exiting = exit.read().count() > 0;
+    if !(died || exiting) || !recorder.recording {return}
+    recorder.save();
+}
+
+/// A run ended without a death, back to the title. The next run is a new recording.
+pub fn stop_recording(mut recorder: ResMut<ReplayRecorder>) {
+    if !recorder.recording {return}
+    recorder.save();
+    recorder.recording = false;
+}
+
+/// Feeds the next recorded frame: its duration goes to the clock, its input to [`PlayerInput`]
+/// and the paths that landed on it to [`PathLandings`]. Runs before the clock is advanced.
+pub fn play_replay(
+    mut commands: Commands,
+    mut player: ResMut<ReplayPlayer>,
+    mut input: ResMut<PlayerInput>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut landings: ResMut<PathLandings>,
+    trespassable: Res<TrespassableCells>,
+    transformer: Option<Res<TransformToGrid>>,
+    state: Option<Res<State<GameState>>>,
+    next_state: Res<NextState<GameState>>,
+    time: Res<Time<Virtual>>,
+    run_start: Res<RunStart>,
+) {
+    let player = &mut *player;
+    if player.resume_strategy.is_none() {
+        player.resume_strategy = Some(std::mem::take(&mut *strategy));
+    }
+    *input = PlayerInput::default();
+    landings.scripted = None;
+    if !player.playing {
+        // bring the clock to where the recording started, then wait for the level
+        let behind = player.replay.start_elapsed.saturating_sub(run_start.elapsed(&time));
+        if behind.is_zero() && gameplay_frame(&trespassable, transformer.as_deref(), state.as_deref(), &next_state) {
+            player.playing = true;
+            info!("Playing replay, {} frames", player.replay.frames.len());
+        } else {
+            *strategy = TimeUpdateStrategy::ManualDuration(behind.min(Duration::from_secs_f64(1. / 60.)));
+            return;
+        }
+    }
+    let Some(frame) = player.replay.frames.get(player.frame) else {
+        info!("Replay finished");
+        *strategy = player.resume_strategy.take().unwrap_or_default();
+        commands.remove_resource::<ReplayPlayer>();
+        return;
+    };
+    *strategy = TimeUpdateStrategy::ManualDuration(frame.dt);
+    *input = frame.input.clone();
+    landings.scripted = Some(frame.paths.clone());
+    player.frame += 1;
+}
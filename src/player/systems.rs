@@ -3,15 +3,19 @@ use std::time::Duration;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
+use crate::balance::GameBalance;
 use crate::characters::animation::{spawn_player_animation_bundle, AnimationController, PartType};
+use crate::controls::{Action, ActionState};
 use crate::core::camera::plugin::CameraFollow;
 use crate::core::functions::{ExpDecay, TextureAtlasLayoutHandles};
 use crate::core::ui::PlayerUINode;
+use crate::map::campaign::Campaign;
+use crate::npc::perception::Noise;
 use crate::npc::systems::RosesCollected;
+use crate::rng::GameRng;
 use crate::sounds::components::PlaySoundEvent;
-use crate::systems::DayCycle;
-use crate::PauseEvent;
-use bevy::math::{uvec2, vec2};
+use crate::systems::{DayCycle, PauseEvent, PauseReason};
+use bevy::math::uvec2;
 use pathfinding::num_traits::Signed;
 
 use super::components::*;
@@ -23,6 +27,9 @@ pub const STRUCTURES_CG: u32 = 0b0000_0000_0000_0100;
 pub const BULLET_CG: u32 = 0b0000_0000_0000_1000;
 pub const RAYCASTABLE_STRUCT_CG: u32 = 0b0000_0000_0001_0000;
 
+/// Where the player is spawned, moved to the level's start once the map is loaded
+pub const PLAYER_SPAWN: Vec2 = Vec2::new(16., 16.);
+
 #[derive(Component)]
 pub struct PlayerController{
     pub accumulated_velocity: Vec2,
@@ -51,22 +58,25 @@ pub fn spawn_player_first_time(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
+    balance: Res<GameBalance>,
 ) {
-    spawn_player(&mut commands, &asset_server, &mut layout_handles);
+    commands.insert_resource(DeathTimer {timer: Timer::from_seconds(5., TimerMode::Repeating)});
+    spawn_player(&mut commands, &asset_server, &mut layout_handles, &balance);
 }
 
 pub fn spawn_player(
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     layout_handles: &mut ResMut<TextureAtlasLayoutHandles>,
+    balance: &Res<GameBalance>,
 ){
     let entity = spawn_player_animation_bundle(commands, asset_server, layout_handles);
     commands.entity(entity).insert((
         VisibilityBundle::default(),
-        TransformBundle::from_transform(Transform::from_xyz(16., 16., -1.)),
+        TransformBundle::from_transform(Transform::from_translation(PLAYER_SPAWN.extend(-1.))),
         Name::new("Player"),
         CameraFollow{order: 0, speed: 10.},
-        Player::default(),
+        Player::new(&balance.player),
         AnimationController::default(),
         RigidBody::Dynamic,
         LockedAxes::ROTATION_LOCKED_Z,
@@ -74,7 +84,7 @@ pub fn spawn_player(
         ActiveEvents::COLLISION_EVENTS,
         Velocity::zero(),
         PlayerController::default(),
-        DashTimer {timer: Timer::new(Duration::from_secs_f32(0.35), TimerMode::Repeating)},
+        DashTimer {timer: Timer::new(Duration::from_secs_f32(balance.player.dash_duration), TimerMode::Repeating)},
         Sleeping::disabled(),
         CollisionGroups::new(
             Group::from_bits(PLAYER_CG).unwrap(),
@@ -83,20 +93,34 @@ pub fn spawn_player(
     ));
 }
 
+pub fn read_player_input(
+    actions: Res<ActionState>,
+    mut input: ResMut<PlayerInput>,
+) {
+    *input = PlayerInput {
+        move_dir: actions.move_axis(),
+        dash: actions.just_pressed(Action::Dash),
+        upgrade: None,
+        pause: actions.just_pressed(Action::Pause),
+    };
+}
+
 pub fn player_controller(
     mut commands: Commands,
     mut player_q: Query<(&mut Velocity, &mut PlayerController,
-        &mut AnimationController, &mut DashTimer, &mut Player, Entity)>,
-    keyboard: Res<ButtonInput<KeyCode>>,
+        &mut AnimationController, &mut DashTimer, &mut Player, &Transform, Entity)>,
+    input: Res<PlayerInput>,
     day_cycle: Res<DayCycle>,
+    balance: Res<GameBalance>,
     time: Res<Time>,
     mut dash_dir: Local<Vec2>,
     mut dash_cd: Local<f32>,
     mut play_sound: EventWriter<PlaySoundEvent>,
+    mut noises: EventWriter<Noise>,
 ) {
     if let Ok((mut character_controller, mut controller,
         mut animation_controller, mut dash_timer,
-        mut player, player_entity)) = player_q.get_single_mut() {
+        mut player, player_transform, player_entity)) = player_q.get_single_mut() {
     character_controller.linvel = Vec2::ZERO;
     if player.is_dead{return}
     let dt = time.delta_seconds();
@@ -104,12 +128,9 @@ pub fn player_controller(
 
     *dash_cd += dt;
     if dash_timer.timer.elapsed_secs() == 0. {
-        let input_dir = vec2(
-            keyboard.pressed(KeyCode::KeyD) as i32 as f32 - keyboard.pressed(KeyCode::KeyA) as i32 as f32,
-            keyboard.pressed(KeyCode::KeyW) as i32 as f32 - keyboard.pressed(KeyCode::KeyS) as i32 as f32
-        );
+        let input_dir = input.move_dir;
         
-        controller.accumulated_velocity = controller.accumulated_velocity.move_towards(input_dir.normalize_or_zero() * player.max_speed, dt * player.accumulation_gain);
+        controller.accumulated_velocity = controller.accumulated_velocity.move_towards(input_dir.clamp_length_max(1.) * player.max_speed, dt * player.accumulation_gain);
         if controller.accumulated_velocity.length() > player.max_speed {controller.accumulated_velocity = controller.accumulated_velocity.normalize() * player.max_speed}
         character_controller.linvel = controller.accumulated_velocity;
     
@@ -129,13 +150,14 @@ pub fn player_controller(
         }
         player.hp -= dt * player.hunger_rate;
         
-        if keyboard.just_pressed(KeyCode::ShiftLeft) {
+        if input.dash {
             if *dash_cd < player.dash_cd {
                 play_sound.send(PlaySoundEvent::DashCD);
                 return;
             }
             play_sound.send(PlaySoundEvent::Dash);
-            dash_timer.timer.set_duration(Duration::from_secs_f32(0.35));
+            noises.send(Noise {pos: player_transform.translation.xy(), loudness: balance.perception.dash_noise});
+            dash_timer.timer.set_duration(Duration::from_secs_f32(balance.player.dash_duration));
             dash_timer.timer.tick(Duration::from_secs_f32(dt * player.dash_tick));
             *dash_cd = 0.;
             *dash_cd += dt;
@@ -190,16 +212,18 @@ pub fn hit_player(
     mut hit_player: EventReader<HitPlayer>,
     mut player: Query<(&mut Player, &mut AnimationController)>,
     mut kill_player: EventWriter<KillPlayer>,
+    balance: Res<GameBalance>,
 ) {
+    let damage = &balance.damage;
     if let Ok((mut player, mut animation_controller)) = player.get_single_mut() {
         for hit in hit_player.read() {
             animation_controller.play_hurt();
             if hit.dmg_type == 0 { // proj
-                player.hp -= player.max_hp * 0.1 * (1. - player.phys_res)
+                player.hp -= player.max_hp * damage.projectile * (1. - player.phys_res)
             } else if hit.dmg_type == 1 { // civ
-                player.hp -= player.max_hp * 0.05 * (1. - player.phys_res)
+                player.hp -= player.max_hp * damage.civilian * (1. - player.phys_res)
             } else if hit.dmg_type == 2 { // hun
-                player.hp -= 15. * (1. - player.phys_res)
+                player.hp -= damage.hunter * (1. - player.phys_res)
             }
         }
         if player.hp < 0. && !player.is_dead {
@@ -219,6 +243,9 @@ pub fn kill_player(
     mut death_time: Query<&mut Text, With<DeathTime>>,
     death_text: Query<Entity, With<DeathText>>,
     roses: Res<RosesCollected>,
+    campaign: Res<Campaign>,
+    balance: Res<GameBalance>,
+    rng: Res<GameRng>,
 ) {
     let dt = time.delta_seconds();
     let t = death_timer.timer.duration().as_secs_f32() - death_timer.timer.elapsed_secs();
@@ -229,8 +256,9 @@ pub fn kill_player(
         }
         commands.entity(entity).insert(Visibility::Hidden);
         player.is_dead = true;
+        death_timer.timer.set_duration(Duration::from_secs_f32(balance.player.respawn_delay));
         death_timer.timer.tick(Duration::from_secs_f32(dt));
-        spawn_death_text(&mut commands, &asset_server, t, &roses, event.won);
+        spawn_death_text(&mut commands, &asset_server, t, &roses, event.won, rng.seed());
     }
     if death_timer.timer.elapsed_secs() != 0. {
         death_timer.timer.tick(Duration::from_secs_f32(dt));
@@ -238,8 +266,8 @@ pub fn kill_player(
         if death_timer.timer.finished() {
             commands.entity(entity).insert((
                 Visibility::Visible,
-                Transform::from_xyz(16., 16., 0.),
-                Player::default()
+                Transform::from_translation(campaign.respawn_point().extend(0.)),
+                Player::new(&balance.player)
             ));
             death_timer.timer.set_elapsed(Duration::ZERO);
             for entity in death_text.iter() {
@@ -252,16 +280,18 @@ pub fn kill_player(
 pub fn kill_npc(
     mut kill_npc: EventReader<KillNpc>,
     mut player: Query<&mut Player>,
+    balance: Res<GameBalance>,
 ) {
+    let rewards = &balance.rewards;
     if let Ok(mut player) = player.get_single_mut() {
         for kill in kill_npc.read() {
             player.hp = (player.hp + player.hp_gain).clamp(0.0, player.max_hp);
             if kill.npc_type == 0 { // civ
-                player.score += 100.;
+                player.score += rewards.civilian_score;
                 player.xp += player.xp_gain;
             } else if kill.npc_type == 1 { // hun
-                player.score += 500.;
-                player.xp += player.xp_gain * 3.;
+                player.score += rewards.hunter_score;
+                player.xp += player.xp_gain * rewards.hunter_xp_multiplier;
             }
         }
     }
@@ -273,15 +303,16 @@ pub fn manage_xp(
     mut commands: Commands,
     mut pause_event: EventWriter<PauseEvent>,
     asset_server: Res<AssetServer>,
+    balance: Res<GameBalance>,
     mut t: Local<bool>,
 ) {
     if let Ok(mut player) = player.get_single_mut() {
         if player.xp > player.max_xp {
             player.xp -= player.max_hp;
-            player.max_xp *= 1.2;
+            player.max_xp *= balance.player.max_xp_growth;
             play_sound.send(PlaySoundEvent::LvlUp);
             lvl_up(&mut commands, &asset_server);
-            pause_event.send(PauseEvent);
+            pause_event.send(PauseEvent::Pause(PauseReason::LevelUp));
             *t = true;
         }
     }
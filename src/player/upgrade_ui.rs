@@ -1,8 +1,8 @@
 use bevy::prelude::*;
 
-use crate::{npc::systems::RosesCollected, sounds::components::PlaySoundEvent, PauseEvent, NUM_ROSES};
+use crate::{menu::{ButtonPressed, Focusable}, npc::systems::RosesCollected, screen::Screen, sounds::components::PlaySoundEvent, systems::{PauseEvent, PauseReason}, NUM_ROSES};
 
-use super::components::{DeathText, DeathTime, ParentEntity, Player, UpgradeButton};
+use super::components::{DeathText, DeathTime, LvlUpScreen, ParentEntity, Player, PlayerInput, UpgradeButton};
 
 pub fn lvl_up(
     commands: &mut Commands,
@@ -25,6 +25,8 @@ pub fn lvl_up(
             ..default()
         },
         Name::new("LvlUpScreen"),
+        LvlUpScreen,
+        StateScoped(Screen::Playing),
     )).id();
     let mut children = vec![];
     
@@ -65,6 +67,7 @@ fn spawn_button(
         ..default()
     },
     upgrade_type,
+    Focusable,
     ParentEntity { entity: parent},
     )).with_children(|parent| {
         parent.spawn(TextBundle {
@@ -83,59 +86,61 @@ fn spawn_button(
 }
 
 pub fn interact_upgrade_button(
+    mut pressed: EventReader<ButtonPressed>,
+    button_q: Query<&UpgradeButton>,
+    mut input: ResMut<PlayerInput>,
+) {
+    for ButtonPressed(entity) in pressed.read() {
+        if let Ok(upgrade_type) = button_q.get(*entity) {
+            input.upgrade = Some(*upgrade_type);
+        }
+    }
+}
+
+/// Applies the upgrade picked this frame, by a click or by a replay, and closes the level up screen.
+pub fn apply_upgrade(
     mut commands: Commands,
-    mut button_q: Query<(&Interaction, &mut UiImage, &UpgradeButton, &ParentEntity), Changed<Interaction>>,
+    input: Res<PlayerInput>,
+    screen: Query<Entity, With<LvlUpScreen>>,
     mut player: Query<&mut Player>,
     mut pause_event: EventWriter<PauseEvent>,
     mut play_sound: EventWriter<PlaySoundEvent>,
-    asset_server: Res<AssetServer>,
 ) {
+    let Some(upgrade_type) = input.upgrade else {return};
+    if screen.is_empty() {return}
     if let Ok(mut player) = player.get_single_mut() {
-        if let Ok((interaction, mut image,
-            upgrade_type, parent_entity)) = button_q.get_single_mut() {
-            match *interaction {
-                Interaction::Pressed => {
-                    match *upgrade_type {
-                        UpgradeButton::MaxHp => {
-                            player.max_hp *= 1.1;
-                        },
-                        UpgradeButton::Armor => {
-                            player.phys_res *= 1.1;
-                        },
-                        UpgradeButton::HpGain => {
-                            player.hp_gain *= 1.1;
-                        },
-                        UpgradeButton::XpGain => {
-                            player.xp_gain *= 1.1;
-                        },
-                        UpgradeButton::Speed => {
-                            player.max_speed *= 1.1;
-                            player.accumulation_gain *= 1.1;
-                        },
-                        UpgradeButton::HungerRate => {
-                            player.hunger_rate *= 0.8;
-                        },
-                        UpgradeButton::DashCD => {
-                            player.dash_cd *= 0.92;
-                        },
-                        UpgradeButton::DashTick => {
-                            player.dash_tick *= 0.92;
-                        },
-                        
-                    }
-                    commands.entity(parent_entity.entity).despawn_recursive();
-                    play_sound.send(PlaySoundEvent::Selected);
-                    pause_event.send(PauseEvent);
-                }
-                Interaction::Hovered => {
-                    play_sound.send(PlaySoundEvent::Select);
-                    *image = UiImage::from(asset_server.load("select_button.png"));
-                }
-                Interaction::None => {
-                    *image = UiImage::from(asset_server.load("button.png"));
-                }
-            }
+        match upgrade_type {
+            UpgradeButton::MaxHp => {
+                player.max_hp *= 1.1;
+            },
+            UpgradeButton::Armor => {
+                player.phys_res *= 1.1;
+            },
+            UpgradeButton::HpGain => {
+                player.hp_gain *= 1.1;
+            },
+            UpgradeButton::XpGain => {
+                player.xp_gain *= 1.1;
+            },
+            UpgradeButton::Speed => {
+                player.max_speed *= 1.1;
+                player.accumulation_gain *= 1.1;
+            },
+            UpgradeButton::HungerRate => {
+                player.hunger_rate *= 0.8;
+            },
+            UpgradeButton::DashCD => {
+                player.dash_cd *= 0.92;
+            },
+            UpgradeButton::DashTick => {
+                player.dash_tick *= 0.92;
+            },
+        }
+        for entity in screen.iter() {
+            commands.entity(entity).despawn_recursive();
         }
+        play_sound.send(PlaySoundEvent::Selected);
+        pause_event.send(PauseEvent::Resume(PauseReason::LevelUp));
     }
 }
 
@@ -145,6 +150,7 @@ pub fn spawn_death_text(
     time: f32,
     roses_collected: &Res<RosesCollected>,
     won: bool,
+    seed: u64,
 ) {
     let font = asset_server.load("fonts/Monocraft.ttf");
     let text;
@@ -164,7 +170,7 @@ pub fn spawn_death_text(
             ..default()
         },
         ..default()
-    }, DeathText));
+    }, DeathText, StateScoped(Screen::Playing)));
     commands.spawn((TextBundle {
         style: Style {
             top: Val::Percent(40.),
@@ -176,7 +182,7 @@ pub fn spawn_death_text(
             ..default()
         },
         ..default()
-    }, DeathTime, DeathText));
+    }, DeathTime, DeathText, StateScoped(Screen::Playing)));
 
     if won {
         commands.spawn((TextBundle {
@@ -190,7 +196,7 @@ pub fn spawn_death_text(
                 ..default()
             },
             ..default()
-        }, DeathText));
+        }, DeathText, StateScoped(Screen::Playing)));
     } else {
         commands.spawn((TextBundle {
             style: Style {
@@ -203,7 +209,7 @@ pub fn spawn_death_text(
                 ..default()
             },
             ..default()
-        }, DeathText));
+        }, DeathText, StateScoped(Screen::Playing)));
     }
     
     let collected;
@@ -223,7 +229,19 @@ pub fn spawn_death_text(
             ..default()
         },
         ..default()
-    }, DeathText));
+    }, DeathText, StateScoped(Screen::Playing)));
+    commands.spawn((TextBundle {
+        style: Style {
+            top: Val::Percent(90.),
+            justify_self: JustifySelf::Center,
+            ..default()
+        },
+        text: Text {
+            sections: vec![TextSection::new(format!("Seed: {}", seed), TextStyle { font: font.clone(), font_size: 16. , color: Color::WHITE })],
+            ..default()
+        },
+        ..default()
+    }, DeathText, StateScoped(Screen::Playing)));
 }
 
 
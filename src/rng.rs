@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::core::functions::arg_value;
+
+pub type RngStream = ChaCha8Rng;
+
+/// All randomness of a run. Every stream is derived from the same `seed`, so two runs
+/// with the same seed and the same inputs play out identically.
+///
+/// Streams are independent: drawing an extra particle offset from `cosmetics`
+/// never shifts what `spawning` or `ai` produce next.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    /// Spawner rolls
+    pub spawning: RngStream,
+    /// Looks, particles, lights, sounds. Anything that doesn't change gameplay.
+    pub cosmetics: RngStream,
+    /// NPC decisions: wander targets, weapon choice
+    pub ai: RngStream,
+}
+
+const SPAWNING_STREAM: u64 = 0;
+const COSMETICS_STREAM: u64 = 1;
+const AI_STREAM: u64 = 2;
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        let stream = |id| {
+            let mut rng = RngStream::seed_from_u64(seed);
+            rng.set_stream(id);
+            rng
+        };
+        GameRng {
+            seed,
+            spawning: stream(SPAWNING_STREAM),
+            cosmetics: stream(COSMETICS_STREAM),
+            ai: stream(AI_STREAM),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for GameRng {
+    /// Seed from `--seed <u64>` if given, random otherwise.
+    fn default() -> Self {
+        GameRng::new(seed_from_args().unwrap_or_else(rand::random))
+    }
+}
+
+/// Parses `--seed <u64>` from the command line.
+pub fn seed_from_args() -> Option<u64> {
+    let value = arg_value("seed")?;
+    match value.parse() {
+        Ok(seed) => Some(seed),
+        Err(_) => {
+            warn!("Ignoring invalid --seed {:?}, expected an unsigned integer", value);
+            None
+        }
+    }
+}
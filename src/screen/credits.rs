@@ -3,14 +3,10 @@
 use bevy::prelude::*;
 
 use super::Screen;
-use crate::{
-    game::{assets::SoundtrackKey, audio::soundtrack::PlaySoundtrack},
-    ui::prelude::*,
-};
+use crate::{menu::ButtonPressed, sounds::components::PlaySoundEvent, ui::prelude::*};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(Screen::Credits), enter_credits);
-    app.add_systems(OnExit(Screen::Credits), exit_credits);
 
     app.add_systems(
         Update,
@@ -25,39 +21,36 @@ enum CreditsAction {
     Back,
 }
 
-fn enter_credits(mut commands: Commands) {
+fn enter_credits(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load(UI_FONT);
     commands
         .ui_root()
         .insert(StateScoped(Screen::Credits))
         .with_children(|children| {
-            children.header("Made by");
-            children.label("Alice - Foo");
-            children.label("Bob - Bar");
+            children.spawn(scroll(&asset_server, 400., 600.)).with_children(|children| {
+                children.header("Made by", &font);
+                children.label("yaroyanuo and kaiva-morphin\nfor Bevy Game Jam 5", &font);
 
-            children.header("Assets");
-            children.label("Bevy logo - All rights reserved by the Bevy Foundation. Permission granted for splash screen use when unmodified.");
-            children.label("Ducky sprite - CC0 by Caz Creates Games");
-            children.label("Music - CC BY 3.0 by Kevin MacLeod");
+                children.header("Assets", &font);
+                children.label("Bevy logo - All rights reserved by the Bevy Foundation. Permission granted for splash screen use when unmodified.", &font);
+                children.label("Monocraft font - OFL by IdreesInc", &font);
 
-            children.button("Back").insert(CreditsAction::Back);
+                children.button("Back", &font).insert(CreditsAction::Back);
+            });
         });
-
-    commands.trigger(PlaySoundtrack::Key(SoundtrackKey::Credits));
-}
-
-fn exit_credits(mut commands: Commands) {
-    commands.trigger(PlaySoundtrack::Disable);
 }
 
 fn handle_credits_action(
     mut next_screen: ResMut<NextState<Screen>>,
-    mut button_query: InteractionQuery<&CreditsAction>,
+    mut pressed: EventReader<ButtonPressed>,
+    button_query: Query<&CreditsAction>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
 ) {
-    for (interaction, action) in &mut button_query {
-        if matches!(interaction, Interaction::Pressed) {
-            match action {
-                CreditsAction::Back => next_screen.set(Screen::Title),
-            }
+    for ButtonPressed(entity) in pressed.read() {
+        let Ok(action) = button_query.get(*entity) else {continue};
+        play_sound.send(PlaySoundEvent::Selected);
+        match action {
+            CreditsAction::Back => next_screen.set(Screen::Title),
         }
     }
 }
@@ -1,40 +1,127 @@
 //! A loading screen during which game assets are loaded.
 //! This reduces stuttering, especially for audio on WASM.
 
-use bevy::prelude::*;
+use bevy::{asset::RecursiveDependencyLoadState, prelude::*};
+use bevy_ecs_ldtk::prelude::LdtkProject;
 
 use super::Screen;
-use crate::{
-    game::assets::{HandleMap, ImageKey, SfxKey, SoundtrackKey},
-    ui::prelude::*,
-};
+use crate::{balance::BalanceHandle, map::plugin::MapPath, sounds::components::AudioHandles, ui::prelude::*};
 
 pub(super) fn plugin(app: &mut App) {
-    app.add_systems(OnEnter(Screen::Loading), enter_loading);
+    app.init_resource::<GameAssets>();
+    app.add_systems(OnEnter(Screen::Loading), (load_game_assets, enter_loading));
     app.add_systems(
         Update,
-        continue_to_title.run_if(in_state(Screen::Loading).and_then(all_assets_loaded)),
+        (
+            show_failed_assets,
+            continue_to_title.run_if(all_assets_loaded),
+        )
+            .run_if(in_state(Screen::Loading)),
     );
 }
 
-fn enter_loading(mut commands: Commands) {
+/// Every texture a run shows. Held for the whole session, together with the font, the map,
+/// the balance and [`AudioHandles`], so gameplay never waits on the disk.
+/// A test checks every image loaded in `src` is listed here.
+pub const GAME_IMAGES: &[&str] = &[
+    "scroll.png",
+    "button.png",
+    "select_button.png",
+    "ui/new_ui.png",
+    "ui/new_ui_fg.png",
+    "ui/daynight.png",
+    "ui/blood.png",
+    "player/vampire.png",
+    "player/umbrella.png",
+    "civilian/body.png",
+    "civilian/eyes.png",
+    "civilian/hair.png",
+    "civilian/outfit.png",
+    "civilian/weapon.png",
+    "hunter/hunter.png",
+    "hunter/throwables.png",
+    "map/rose.png",
+    "map/trees.png",
+    "map/torch.png",
+    "map/campfire.png",
+    "map/light_emitters.png",
+    "particles/blood.png",
+    "particles/body_civilian.png",
+    "particles/body_hunter.png",
+    "particles/emotions.png",
+    "particles/fire.png",
+    "particles/heart.png",
+    "particles/minishadow.png",
+    "particles/shadow.png",
+    "particles/soul.png",
+];
+
+/// Tells what couldn't be loaded, the game stays on the loading screen then
+#[derive(Component)]
+struct LoadingLabel;
+
+#[derive(Resource, Default)]
+struct GameAssets(Vec<UntypedHandle>);
+
+fn load_game_assets(
+    mut assets: ResMut<GameAssets>,
+    asset_server: Res<AssetServer>,
+    map: Res<MapPath>,
+    balance: Res<BalanceHandle>,
+) {
+    assets.0 = GAME_IMAGES
+        .iter()
+        .map(|path| asset_server.load::<Image>(*path).untyped())
+        .collect();
+    assets.0.push(asset_server.load::<Font>(UI_FONT).untyped());
+    assets.0.push(asset_server.load::<LdtkProject>(map.0.clone()).untyped());
+    // a run shouldn't start on the compiled-in defaults and switch mid-night
+    assets.0.push(balance.0.clone().untyped());
+}
+
+fn enter_loading(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load(UI_FONT);
     commands
         .ui_root()
         .insert(StateScoped(Screen::Loading))
         .with_children(|children| {
-            children.label("Loading...");
+            children.label("Loading...", &font).insert(LoadingLabel);
         });
 }
 
 fn all_assets_loaded(
     asset_server: Res<AssetServer>,
-    image_handles: Res<HandleMap<ImageKey>>,
-    sfx_handles: Res<HandleMap<SfxKey>>,
-    soundtrack_handles: Res<HandleMap<SoundtrackKey>>,
+    assets: Res<GameAssets>,
+    audio: Res<AudioHandles>,
 ) -> bool {
-    image_handles.all_loaded(&asset_server)
-        && sfx_handles.all_loaded(&asset_server)
-        && soundtrack_handles.all_loaded(&asset_server)
+    let loaded = |id: UntypedAssetId| asset_server.get_recursive_dependency_load_state(id) == Some(RecursiveDependencyLoadState::Loaded);
+    assets.0.iter().all(|handle| loaded(handle.id()))
+        && audio.iter().all(|handle| loaded(handle.id().untyped()))
+}
+
+/// The failures are logged by the asset server too
+fn show_failed_assets(
+    asset_server: Res<AssetServer>,
+    assets: Res<GameAssets>,
+    audio: Res<AudioHandles>,
+    labels: Query<&Children, With<LoadingLabel>>,
+    mut texts: Query<&mut Text>,
+    mut shown: Local<usize>,
+) {
+    let failed: Vec<String> = assets.0.iter().map(|handle| (handle.id(), handle.path()))
+        .chain(audio.iter().map(|handle| (handle.id().untyped(), handle.path())))
+        .filter(|(id, _)| asset_server.get_recursive_dependency_load_state(*id) == Some(RecursiveDependencyLoadState::Failed))
+        .map(|(id, path)| path.map_or_else(|| format!("{id:?}"), ToString::to_string))
+        .collect();
+    if failed.len() == *shown {return}
+    *shown = failed.len();
+    for children in labels.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = format!("Couldn't load:\n{}", failed.join("\n"));
+            }
+        }
+    }
 }
 
 fn continue_to_title(mut next_screen: ResMut<NextState<Screen>>) {
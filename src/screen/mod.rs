@@ -1,33 +1,38 @@
 //! The game's main screen states and transitions between them.
 
 mod credits;
-mod loading;
-mod playing;
+pub mod loading;
+pub(crate) mod playing;
+mod settings;
 mod splash;
 mod title;
 
 use bevy::prelude::*;
 
+/// Splash, loading, title, credits and settings. [`Screen`] itself and the pause menu are set up by
+/// [`crate::GameplayPlugin`], the headless app has no menus and starts right in [`Screen::Playing`].
 pub(super) fn plugin(app: &mut App) {
-    app.init_state::<Screen>();
-    app.enable_state_scoped_entities::<Screen>();
-
     app.add_plugins((
         splash::plugin,
         loading::plugin,
         title::plugin,
         credits::plugin,
-        playing::plugin,
+        settings::plugin,
     ));
 }
 
 /// The game's main screen states.
-#[derive(States, Debug, Hash, PartialEq, Eq, Clone, Default)]
+#[derive(States, Debug, Hash, PartialEq, Eq, Clone, Copy, Default)]
 pub enum Screen {
     #[default]
     Splash,
     Loading,
     Title,
     Credits,
+    /// The settings panel, opened from the title screen. Inside a run it opens over the pause menu instead.
+    Settings,
+    /// A run. Everything spawned for it is [`StateScoped`] to this screen.
     Playing,
+    /// Passed through for a single frame when a run is restarted
+    Restarting,
 }
@@ -1,33 +1,124 @@
-//! The screen state for the main game loop.
+//! The screen state for the main game loop. The run itself is set up by
+//! [`crate::GameplayPlugin`] on entering [`Screen::Playing`], this adds the pause menu on top.
 
-use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy::prelude::*;
 
-use super::Screen;
-use crate::game::{
-    assets::SoundtrackKey, audio::soundtrack::PlaySoundtrack, spawn::level::SpawnLevel,
+use super::{settings::OpenSettings, Screen};
+use crate::{
+    controls::{key_name, Action, InputBindings},
+    menu::ButtonPressed,
+    player::{components::{LvlUpScreen, PlayerInput}, replay::ReplayPlayer},
+    sounds::components::PlaySoundEvent,
+    systems::{PauseEvent, PauseReason, PauseReasons},
+    ui::prelude::*,
 };
 
-pub(super) fn plugin(app: &mut App) {
-    app.add_systems(OnEnter(Screen::Playing), enter_playing);
-    app.add_systems(OnExit(Screen::Playing), exit_playing);
-
+/// Added by [`crate::GameplayPlugin`] rather than [`super::plugin`]: opening the menu pauses
+/// the game, so replays and the headless app need it too.
+pub(crate) fn plugin(app: &mut App) {
+    app.register_type::<PauseAction>();
     app.add_systems(
         Update,
-        return_to_title_screen
-            .run_if(in_state(Screen::Playing).and_then(input_just_pressed(KeyCode::Escape))),
+        (
+            handle_pause_action.run_if(not(resource_exists::<ReplayPlayer>)),
+            toggle_pause_menu,
+        )
+            .chain()
+            .run_if(in_state(Screen::Playing)),
     );
 }
 
-fn enter_playing(mut commands: Commands) {
-    commands.trigger(SpawnLevel);
-    commands.trigger(PlaySoundtrack::Key(SoundtrackKey::Gameplay));
+#[derive(Component)]
+pub struct PauseMenu;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+enum PauseAction {
+    Resume,
+    Restart,
+    Quit,
+}
+
+/// Opens or closes the menu when [`PlayerInput::pause`] is set. Popups already open,
+/// like the level up screen, are hidden meanwhile and keep the game paused once it closes.
+fn toggle_pause_menu(
+    mut commands: Commands,
+    input: Res<PlayerInput>,
+    reasons: Res<PauseReasons>,
+    menu: Query<Entity, With<PauseMenu>>,
+    mut popups: Query<&mut Visibility, With<LvlUpScreen>>,
+    asset_server: Res<AssetServer>,
+    bindings: Res<InputBindings>,
+    mut pause_event: EventWriter<PauseEvent>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
+) {
+    if !input.pause {return}
+    play_sound.send(PlaySoundEvent::Select);
+    if reasons.contains(PauseReason::Menu) {
+        for entity in menu.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        for mut visibility in popups.iter_mut() {
+            *visibility = Visibility::Inherited;
+        }
+        pause_event.send(PauseEvent::Resume(PauseReason::Menu));
+        return;
+    }
+    for mut visibility in popups.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+    pause_event.send(PauseEvent::Pause(PauseReason::Menu));
+
+    let font = asset_server.load(UI_FONT);
+    commands
+        .ui_root()
+        .insert((Name::new("Pause Menu"), PauseMenu, StateScoped(Screen::Playing)))
+        .with_children(|children| {
+            children.spawn(scroll(&asset_server, 400., 600.)).with_children(|children| {
+                children.header("Paused", &font);
+                children.button("Resume", &font).insert(PauseAction::Resume);
+                children.button("Restart Run", &font).insert(PauseAction::Restart);
+                children.button("Settings", &font).insert(OpenSettings);
+                children.button("Quit", &font).insert(PauseAction::Quit);
+                children.label(controls_reference(&bindings), &font);
+            });
+        });
 }
 
-fn exit_playing(mut commands: Commands) {
-    // We could use [`StateScoped`] on the sound playing entities instead.
-    commands.trigger(PlaySoundtrack::Disable);
+/// Every action with all of its keys and gamepad buttons, one per line
+fn controls_reference(bindings: &InputBindings) -> String {
+    Action::ALL
+        .iter()
+        .map(|action| {
+            let inputs: Vec<String> = bindings.keys(*action).iter().map(key_name)
+                .chain(bindings.buttons(*action).iter().map(|button| format!("{:?}", button)))
+                .collect();
+            format!("{} - {}", action.name(), inputs.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-fn return_to_title_screen(mut next_screen: ResMut<NextState<Screen>>) {
-    next_screen.set(Screen::Title);
+fn handle_pause_action(
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut pressed: EventReader<ButtonPressed>,
+    button_query: Query<&PauseAction>,
+    mut input: ResMut<PlayerInput>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
+) {
+    for ButtonPressed(entity) in pressed.read() {
+        let Ok(action) = button_query.get(*entity) else {continue};
+        match action {
+            // goes through the input so a replay sees the menu close
+            PauseAction::Resume => input.pause = true,
+            PauseAction::Restart => {
+                play_sound.send(PlaySoundEvent::Selected);
+                next_screen.set(Screen::Restarting);
+            }
+            PauseAction::Quit => {
+                play_sound.send(PlaySoundEvent::Selected);
+                next_screen.set(Screen::Title);
+            }
+        }
+    }
 }
@@ -0,0 +1,308 @@
+//! Audio, graphics, controls and gameplay settings. A screen of its own when opened from the title screen,
+//! a panel over the pause menu inside a run. Every change is applied and saved right away.
+
+use bevy::{prelude::*, window::PresentMode};
+
+use super::{playing::PauseMenu, Screen};
+use crate::{
+    controls::{key_name, Action, ActionState, ActionSystem, InputBindings, RebindAction, RebindConflict, Rebinding},
+    core::functions::ConfigDir,
+    menu::ButtonPressed,
+    player::{components::PlayerInput, replay::ReplayPlayer, systems::read_player_input},
+    settings::{Settings, PRESENT_MODES},
+    sounds::components::PlaySoundEvent,
+    systems::{PauseReason, PauseReasons},
+    ui::prelude::*,
+};
+
+pub(super) fn plugin(app: &mut App) {
+    app.add_systems(OnEnter(Screen::Settings), enter_settings);
+
+    app.register_type::<SettingsAction>();
+    app.add_systems(
+        PreUpdate,
+        settings_hotkey
+            .after(ActionSystem)
+            .after(read_player_input)
+            .run_if(not(resource_exists::<ReplayPlayer>)),
+    );
+    app.add_systems(
+        Update,
+        (
+            handle_settings_action,
+            (close_with_pause_menu, hide_pause_menu).run_if(in_state(Screen::Playing)),
+            update_setting_labels.run_if(resource_changed::<Settings>),
+            update_binding_labels.run_if(
+                resource_changed::<InputBindings>.or_else(resource_changed::<Rebinding>).or_else(resource_changed::<RebindConflict>),
+            ),
+        )
+            .chain(),
+    );
+}
+
+/// A button that opens the settings panel over the pause menu
+#[derive(Component)]
+pub(super) struct OpenSettings;
+
+/// The panel opened inside a run
+#[derive(Component)]
+struct SettingsPanel;
+
+/// Waits for the next key press to bind it to its action
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+struct RebindButton(Action);
+
+impl RebindButton {
+    fn label(&self, bindings: &InputBindings, rebinding: &Rebinding, conflict: &RebindConflict) -> String {
+        if rebinding.0 != Some(self.0) {
+            format!("{}: {}", self.0.name(), bindings.describe(self.0))
+        } else if let Some((key, other)) = conflict.0 {
+            format!("{}: {} is {}'s only key", self.0.name(), key_name(&key), other.name())
+        } else {
+            format!("{}: press a key", self.0.name())
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+enum SettingsAction {
+    DayVolume,
+    NightVolume,
+    SfxVolume,
+    Vignette,
+    Waves,
+    Bloom,
+    TreeSway,
+    PresentMode,
+    ResetControls,
+    Back,
+}
+
+impl SettingsAction {
+    const VOLUME: [SettingsAction; 3] = [SettingsAction::DayVolume, SettingsAction::NightVolume, SettingsAction::SfxVolume];
+    const GRAPHICS: [SettingsAction; 5] = [
+        SettingsAction::Vignette,
+        SettingsAction::Waves,
+        SettingsAction::Bloom,
+        SettingsAction::TreeSway,
+        SettingsAction::PresentMode,
+    ];
+
+    fn label(&self, settings: &Settings) -> String {
+        let on_off = |on: bool| if on {"On"} else {"Off"};
+        match self {
+            SettingsAction::DayVolume => format!("Day: {:.0}%", settings.day_volume * 100.),
+            SettingsAction::NightVolume => format!("Night: {:.0}%", settings.night_volume * 100.),
+            SettingsAction::SfxVolume => format!("Effects: {:.0}%", settings.sfx_volume * 100.),
+            SettingsAction::Vignette => format!("Vignette: {:.0}%", settings.vignette_strength * 100.),
+            SettingsAction::Waves => format!("Waves: {:.0}", settings.wave_strength),
+            SettingsAction::Bloom => format!("Bloom: {}", on_off(settings.bloom)),
+            SettingsAction::TreeSway => format!("Tree sway: {}", on_off(settings.tree_sway)),
+            SettingsAction::PresentMode => format!("VSync: {}", present_mode_name(settings.present_mode)),
+            SettingsAction::ResetControls => "Reset controls".to_string(),
+            SettingsAction::Back => "Back".to_string(),
+        }
+    }
+
+    /// Steps the setting to its next value, wrapping around
+    fn apply(&self, settings: &mut Settings) {
+        match self {
+            SettingsAction::DayVolume => settings.day_volume = step(settings.day_volume, 0.1, 1.),
+            SettingsAction::NightVolume => settings.night_volume = step(settings.night_volume, 0.1, 1.),
+            SettingsAction::SfxVolume => settings.sfx_volume = step(settings.sfx_volume, 0.1, 1.),
+            SettingsAction::Vignette => settings.vignette_strength = step(settings.vignette_strength as f64, 0.05, 0.6) as f32,
+            SettingsAction::Waves => settings.wave_strength = step(settings.wave_strength as f64, 5., 30.) as f32,
+            SettingsAction::Bloom => settings.bloom = !settings.bloom,
+            SettingsAction::TreeSway => settings.tree_sway = !settings.tree_sway,
+            SettingsAction::PresentMode => {
+                let current = PRESENT_MODES.iter().position(|mode| *mode == settings.present_mode);
+                settings.present_mode = PRESENT_MODES[current.map_or(0, |i| (i + 1) % PRESENT_MODES.len())];
+            }
+            SettingsAction::ResetControls | SettingsAction::Back => {}
+        }
+    }
+}
+
+fn step(value: f64, step: f64, max: f64) -> f64 {
+    // 3 * 0.1 isn't quite 0.3, the settings file should read 0.3
+    let next = (((value / step).round() + 1.) * step * 1000.).round() / 1000.;
+    if next > max + step * 0.5 {0.} else {next}
+}
+
+fn present_mode_name(mode: PresentMode) -> &'static str {
+    match mode {
+        PresentMode::AutoNoVsync => "Off",
+        PresentMode::AutoVsync => "On",
+        PresentMode::Fifo => "Fifo",
+        _ => "Custom",
+    }
+}
+
+fn settings_panel(children: &mut ChildBuilder, asset_server: &AssetServer, settings: &Settings, bindings: &InputBindings) {
+    let font = asset_server.load(UI_FONT);
+    children.spawn(scroll(asset_server, 400., 600.)).with_children(|children| {
+        children.header("Volume", &font);
+        for action in SettingsAction::VOLUME {
+            children.button(action.label(settings), &font).insert(action);
+        }
+        children.header("Graphics", &font);
+        for action in SettingsAction::GRAPHICS {
+            children.button(action.label(settings), &font).insert(action);
+        }
+        children.header("Controls", &font);
+        for action in Action::ALL {
+            let button = RebindButton(action);
+            children.button(button.label(bindings, &Rebinding::default(), &RebindConflict::default()), &font).insert(button);
+        }
+        children.button(SettingsAction::ResetControls.label(settings), &font).insert(SettingsAction::ResetControls);
+        children.button("Back", &font).insert(SettingsAction::Back);
+    });
+}
+
+fn enter_settings(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>, bindings: Res<InputBindings>) {
+    commands
+        .ui_root()
+        .insert(StateScoped(Screen::Settings))
+        .with_children(|children| settings_panel(children, &asset_server, &settings, &bindings));
+}
+
+fn open_panel(commands: &mut Commands, asset_server: &AssetServer, settings: &Settings, bindings: &InputBindings) {
+    commands
+        .ui_root()
+        .insert((Name::new("Settings Panel"), SettingsPanel, StateScoped(Screen::Playing)))
+        .with_children(|children| settings_panel(children, asset_server, settings, bindings));
+}
+
+/// Opens the settings from anywhere. Inside a run the pause menu is opened along with them,
+/// through [`PlayerInput::pause`] so a recording sees the game pause.
+fn settings_hotkey(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    screen: Res<State<Screen>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    reasons: Res<PauseReasons>,
+    panels: Query<Entity, With<SettingsPanel>>,
+    mut input: ResMut<PlayerInput>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    bindings: Res<InputBindings>,
+) {
+    if !actions.just_pressed(Action::Settings) {return}
+    match screen.get() {
+        Screen::Title | Screen::Credits => next_screen.set(Screen::Settings),
+        Screen::Settings => next_screen.set(Screen::Title),
+        Screen::Playing if panels.is_empty() => {
+            if !reasons.contains(PauseReason::Menu) {
+                input.pause = true;
+            }
+            open_panel(&mut commands, &asset_server, &settings, &bindings);
+        }
+        Screen::Playing => {
+            for entity in panels.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_settings_action(
+    mut commands: Commands,
+    mut pressed: EventReader<ButtonPressed>,
+    button_query: Query<&SettingsAction>,
+    rebind_query: Query<&RebindButton>,
+    open_query: Query<(), With<OpenSettings>>,
+    panels: Query<Entity, With<SettingsPanel>>,
+    screen: Res<State<Screen>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    mut settings: ResMut<Settings>,
+    mut bindings: ResMut<InputBindings>,
+    config_dir: Res<ConfigDir>,
+    asset_server: Res<AssetServer>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
+    mut rebind: EventWriter<RebindAction>,
+) {
+    for ButtonPressed(entity) in pressed.read() {
+        if open_query.contains(*entity) {
+            play_sound.send(PlaySoundEvent::Selected);
+            open_panel(&mut commands, &asset_server, &settings, &bindings);
+            continue;
+        }
+        if let Ok(RebindButton(action)) = rebind_query.get(*entity) {
+            play_sound.send(PlaySoundEvent::Selected);
+            rebind.send(RebindAction(*action));
+            continue;
+        }
+        let Ok(action) = button_query.get(*entity) else {continue};
+        play_sound.send(PlaySoundEvent::Selected);
+        if *action == SettingsAction::ResetControls {
+            *bindings = InputBindings::default();
+            bindings.save(&config_dir);
+            continue;
+        }
+        if *action != SettingsAction::Back {
+            action.apply(&mut settings);
+            settings.save(&config_dir);
+            continue;
+        }
+        if *screen.get() == Screen::Settings {
+            next_screen.set(Screen::Title);
+        }
+        for entity in panels.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Resuming from the pause menu closes the settings too
+fn close_with_pause_menu(
+    mut commands: Commands,
+    mut closed: RemovedComponents<PauseMenu>,
+    panels: Query<Entity, With<SettingsPanel>>,
+) {
+    if closed.read().count() == 0 {return}
+    for entity in panels.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn hide_pause_menu(
+    panels: Query<(), With<SettingsPanel>>,
+    mut menus: Query<&mut Visibility, With<PauseMenu>>,
+) {
+    let visibility = if panels.is_empty() {Visibility::Inherited} else {Visibility::Hidden};
+    for mut menu in menus.iter_mut() {
+        menu.set_if_neq(visibility);
+    }
+}
+
+fn update_setting_labels(
+    settings: Res<Settings>,
+    buttons: Query<(&SettingsAction, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (action, children) in buttons.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = action.label(&settings);
+            }
+        }
+    }
+}
+
+fn update_binding_labels(
+    bindings: Res<InputBindings>,
+    rebinding: Res<Rebinding>,
+    conflict: Res<RebindConflict>,
+    buttons: Query<(&RebindButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (button, children) in buttons.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = button.label(&bindings, &rebinding, &conflict);
+            }
+        }
+    }
+}
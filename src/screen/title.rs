@@ -3,7 +3,7 @@
 use bevy::prelude::*;
 
 use super::Screen;
-use crate::ui::prelude::*;
+use crate::{controls::{Action, InputBindings}, menu::ButtonPressed, sounds::components::PlaySoundEvent, ui::prelude::*};
 
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(OnEnter(Screen::Title), enter_title);
@@ -16,40 +16,61 @@ pub(super) fn plugin(app: &mut App) {
 #[reflect(Component)]
 enum TitleAction {
     Play,
+    Settings,
     Credits,
     /// Exit doesn't work well with embedded applications.
     #[cfg(not(target_family = "wasm"))]
     Exit,
 }
 
-fn enter_title(mut commands: Commands) {
+fn enter_title(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bindings: Res<InputBindings>,
+) {
+    let font = asset_server.load(UI_FONT);
+    let controls = format!(
+        "{}{}{}{} or left stick - movement\n{} - dash\n{} - settings\nIf you are experiencing lags, press {}",
+        bindings.describe(Action::MoveUp), bindings.describe(Action::MoveLeft),
+        bindings.describe(Action::MoveDown), bindings.describe(Action::MoveRight),
+        bindings.describe(Action::Dash), bindings.describe(Action::Settings),
+        bindings.describe(Action::ToggleLowFx),
+    );
     commands
         .ui_root()
         .insert(StateScoped(Screen::Title))
         .with_children(|children| {
-            children.button("Play").insert(TitleAction::Play);
-            children.button("Credits").insert(TitleAction::Credits);
+            children.spawn(scroll(&asset_server, 400., 600.)).with_children(|children| {
+                children.header("Bloody Night", &font);
+                children.label(controls, &font);
+                children.button("Play", &font).insert(TitleAction::Play);
+                children.button("Settings", &font).insert(TitleAction::Settings);
+                children.button("Credits", &font).insert(TitleAction::Credits);
 
-            #[cfg(not(target_family = "wasm"))]
-            children.button("Exit").insert(TitleAction::Exit);
+                #[cfg(not(target_family = "wasm"))]
+                children.button("Exit", &font).insert(TitleAction::Exit);
+            });
         });
 }
 
 fn handle_title_action(
     mut next_screen: ResMut<NextState<Screen>>,
-    mut button_query: InteractionQuery<&TitleAction>,
+    mut pressed: EventReader<ButtonPressed>,
+    button_query: Query<&TitleAction>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
     #[cfg(not(target_family = "wasm"))] mut app_exit: EventWriter<AppExit>,
 ) {
-    for (interaction, action) in &mut button_query {
-        if matches!(interaction, Interaction::Pressed) {
-            match action {
-                TitleAction::Play => next_screen.set(Screen::Playing),
-                TitleAction::Credits => next_screen.set(Screen::Credits),
+    for ButtonPressed(entity) in pressed.read() {
+        let Ok(action) = button_query.get(*entity) else {continue};
+        play_sound.send(PlaySoundEvent::Selected);
+        match action {
+            TitleAction::Play => next_screen.set(Screen::Playing),
+            TitleAction::Settings => next_screen.set(Screen::Settings),
+            TitleAction::Credits => next_screen.set(Screen::Credits),
 
-                #[cfg(not(target_family = "wasm"))]
-                TitleAction::Exit => {
-                    app_exit.send(AppExit::Success);
-                }
+            #[cfg(not(target_family = "wasm"))]
+            TitleAction::Exit => {
+                app_exit.send(AppExit::Success);
             }
         }
     }
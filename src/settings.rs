@@ -0,0 +1,138 @@
+use std::{fs, path::PathBuf};
+
+use bevy::{asset::ron, prelude::*, window::{PresentMode, PrimaryWindow}};
+use serde::{Deserialize, Serialize};
+
+use crate::{controls::{Action, ActionState}, core::{camera::plugin::{bloom_settings, MainCamera}, functions::ConfigDir, post_processing::PostProcessUniform}};
+
+pub const SETTINGS_FILE: &str = "settings.ron";
+
+/// Present modes offered in the settings, all of them fall back gracefully where unsupported
+pub const PRESENT_MODES: [PresentMode; 3] = [PresentMode::AutoNoVsync, PresentMode::AutoVsync, PresentMode::Fifo];
+
+/// Applies [`Settings`] as soon as they change. The resource itself is loaded by
+/// [`crate::core::default::plugin::DefaultPlugin`], the window is created with its present mode.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConfigDir>();
+        if !app.world().contains_resource::<Settings>() {
+            let settings = Settings::load(app.world().resource::<ConfigDir>());
+            app.insert_resource(settings);
+        }
+        app.add_systems(Update, (
+            toggle_tree_sway,
+            (apply_post_process, apply_bloom, apply_present_mode).run_if(resource_changed::<Settings>),
+        ).chain());
+    }
+}
+
+/// Player preferences, saved next to the controls.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// 0 to 1, multiplies the channel's own volume
+    pub day_volume: f64,
+    pub night_volume: f64,
+    pub sfx_volume: f64,
+    pub vignette_strength: f32,
+    pub wave_strength: f32,
+    pub bloom: bool,
+    /// Trees sway in the wind. Toggled with `Low FX` too.
+    pub tree_sway: bool,
+    pub present_mode: PresentMode,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            day_volume: 1.,
+            night_volume: 1.,
+            sfx_volume: 1.,
+            vignette_strength: 0.35,
+            wave_strength: 15.,
+            bloom: true,
+            tree_sway: true,
+            present_mode: PresentMode::AutoNoVsync,
+        }
+    }
+}
+
+impl Settings {
+    pub fn path(config_dir: &ConfigDir) -> PathBuf {
+        config_dir.path(SETTINGS_FILE)
+    }
+
+    /// Saved settings, defaults for anything missing or unreadable.
+    pub fn load(config_dir: &ConfigDir) -> Self {
+        let path = Settings::path(config_dir);
+        let Ok(text) = fs::read_to_string(&path) else {return Settings::default()};
+        match ron::from_str::<Settings>(&text) {
+            Ok(saved) => saved,
+            Err(e) => {
+                warn!("Ignoring {}: {}", path.display(), e);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self, config_dir: &ConfigDir) {
+        let path = Settings::path(config_dir);
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                }
+                fs::write(&path, text).map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            warn!("Failed to save settings to {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn toggle_tree_sway(
+    actions: Res<ActionState>,
+    mut settings: ResMut<Settings>,
+    config_dir: Res<ConfigDir>,
+) {
+    if !actions.just_pressed(Action::ToggleLowFx) {return}
+    settings.tree_sway = !settings.tree_sway;
+    settings.save(&config_dir);
+}
+
+fn apply_post_process(
+    settings: Res<Settings>,
+    mut post_process: Query<&mut PostProcessUniform>,
+) {
+    for mut post_process in post_process.iter_mut() {
+        post_process.vignette_strength = settings.vignette_strength;
+        post_process.wave_strength = settings.wave_strength;
+    }
+}
+
+fn apply_bloom(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    camera: Query<(Entity, Has<BloomSettings>), With<MainCamera>>,
+) {
+    for (entity, has_bloom) in camera.iter() {
+        if settings.bloom && !has_bloom {
+            commands.entity(entity).insert(bloom_settings());
+        } else if !settings.bloom && has_bloom {
+            commands.entity(entity).remove::<BloomSettings>();
+        }
+    }
+}
+
+fn apply_present_mode(
+    settings: Res<Settings>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = window.get_single_mut() else {return};
+    if window.present_mode != settings.present_mode {
+        window.present_mode = settings.present_mode;
+    }
+}
@@ -1,6 +1,18 @@
 use bevy_kira_audio::prelude::*;
 use bevy::prelude::*;
 
+/// Volume of the day music at full [`crate::settings::Settings::day_volume`]
+pub const DAY_VOLUME: f64 = 0.4;
+pub const NIGHT_VOLUME: f64 = 1.;
+
+/// The volumes last set on the channels, none before the settings are first applied
+#[derive(Resource, Default, Debug, PartialEq)]
+pub struct ChannelVolumes {
+    pub day: Option<f64>,
+    pub night: Option<f64>,
+    pub sfx: Option<f64>,
+}
+
 #[derive(Resource)]
 pub struct DayChannel;
 
@@ -24,6 +36,15 @@ pub struct AudioHandles {
     pub dash_cd: Handle<bevy_kira_audio::AudioSource>,
 }
 
+impl AudioHandles {
+    pub fn iter(&self) -> impl Iterator<Item = &Handle<bevy_kira_audio::AudioSource>> {
+        [&self.day, &self.night, &self.dash, &self.throw, &self.select, &self.selected, &self.dash_cd].into_iter()
+            .chain(self.lvlup.iter())
+            .chain(self.hit.iter())
+            .chain(self.kill.iter())
+    }
+}
+
 #[derive(Event)]
 pub enum PlaySoundEvent {
     LvlUp,
@@ -5,6 +5,7 @@ use bevy_kira_audio::prelude::*;
 use bevy::prelude::*;
 
 use components::*;
+use crate::settings::Settings;
 use systems::*;
 
 pub struct AudioPlugin;
@@ -17,9 +18,10 @@ impl Plugin for AudioPlugin {
         .add_audio_channel::<NightChannel>()
         .add_audio_channel::<SfxChannel>()
         .insert_resource(AudioHandles::default())
+        .init_resource::<ChannelVolumes>()
         .add_event::<PlaySoundEvent>()
         .add_systems(Startup, load_audio)
-        .add_systems(Update, (manage_background, play_sounds))
+        .add_systems(Update, (manage_background, play_sounds, apply_volume.run_if(resource_changed::<Settings>)))
         ;
     }
 }
\ No newline at end of file
@@ -2,9 +2,9 @@ use std::time::Duration;
 
 use bevy_kira_audio::prelude::*;
 use bevy::prelude::*;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
-use crate::{DayCycle, TRANSLATION_DURATION};
+use crate::{balance::GameBalance, rng::GameRng, settings::Settings, DayCycle};
 
 use super::components::*;
 
@@ -12,6 +12,7 @@ pub fn load_audio(
     asset_server: Res<AssetServer>,
     mut audio_handles: ResMut<AudioHandles>,
     night_channel: Res<AudioChannel<NightChannel>>,
+    settings: Res<Settings>,
 ) {
     audio_handles.day = asset_server.load("sounds/day.flac");
     audio_handles.night = asset_server.load("sounds/night.wav");
@@ -39,7 +40,7 @@ pub fn load_audio(
     night_channel.play(audio_handles.night.clone_weak())
     .start_from(0.)
     .fade_in(AudioTween::new(Duration::from_secs_f32(1.), AudioEasing::OutPowf(2.)))
-    .with_volume(1.)
+    .with_volume(NIGHT_VOLUME * settings.night_volume)
     ;
 }
 
@@ -48,10 +49,12 @@ pub fn manage_background(
     night_channel: Res<AudioChannel<NightChannel>>,
     day_cycle: Res<DayCycle>,
     audio_handles: Res<AudioHandles>,
+    balance: Res<GameBalance>,
+    settings: Res<Settings>,
     mut prev_state: Local<bool>
 ) {
     if day_cycle.is_translating && *prev_state != day_cycle.is_translating {
-        let dur = Duration::from_secs_f32(TRANSLATION_DURATION);
+        let dur = Duration::from_secs_f32(balance.daycycle.translation_duration);
         let easing = AudioEasing::OutPowf(2.);
         
         if day_cycle.is_night { // translating into day
@@ -62,7 +65,7 @@ pub fn manage_background(
             day_channel.play(audio_handles.day.clone_weak())
             .start_from(0.)
             .fade_in(AudioTween::new(dur, easing))
-            .with_volume(0.4)
+            .with_volume(DAY_VOLUME * settings.day_volume)
             ;
         } else { // translating into night
             day_channel.stop()
@@ -72,7 +75,7 @@ pub fn manage_background(
             night_channel.play(audio_handles.night.clone_weak())
             .start_from(0.)
             .fade_in(AudioTween::new(dur, easing))
-            .with_volume(1.)
+            .with_volume(NIGHT_VOLUME * settings.night_volume)
             ;
         }
     }
@@ -83,8 +86,9 @@ pub fn play_sounds(
     audio_handles: Res<AudioHandles>,
     sfx_channel: Res<AudioChannel<SfxChannel>>,
     mut sound_events: EventReader<PlaySoundEvent>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let mut rng = thread_rng();
+    let rng = &mut rng.cosmetics;
     for sound in sound_events.read() {
         match *sound {
             PlaySoundEvent::Dash => {
@@ -119,4 +123,29 @@ pub fn play_sounds(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Keeps playing sounds in line with the volume settings, only the channels whose volume changed
+/// are set again
+pub fn apply_volume(
+    settings: Res<Settings>,
+    mut volumes: ResMut<ChannelVolumes>,
+    day_channel: Res<AudioChannel<DayChannel>>,
+    night_channel: Res<AudioChannel<NightChannel>>,
+    sfx_channel: Res<AudioChannel<SfxChannel>>,
+) {
+    let day = DAY_VOLUME * settings.day_volume;
+    if volumes.day != Some(day) {
+        day_channel.set_volume(day);
+        volumes.day = Some(day);
+    }
+    let night = NIGHT_VOLUME * settings.night_volume;
+    if volumes.night != Some(night) {
+        night_channel.set_volume(night);
+        volumes.night = Some(night);
+    }
+    if volumes.sfx != Some(settings.sfx_volume) {
+        sfx_channel.set_volume(settings.sfx_volume);
+        volumes.sfx = Some(settings.sfx_volume);
+    }
+}
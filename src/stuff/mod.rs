@@ -5,7 +5,7 @@ use bevy_easings::*;
 use bevy_rapier2d::prelude::Velocity;
 use rand::Rng;
 
-use crate::core::{despawn_lifetime::DespawnTimer, functions::{ExpDecay, TextureAtlasLayoutHandles}};
+use crate::{core::{despawn_lifetime::DespawnTimer, functions::{ExpDecay, TextureAtlasLayoutHandles}}, screen::Screen};
 
 pub enum SimpleAnimatedTexture{
     HeartGain,
@@ -197,19 +197,20 @@ pub fn spawn_cililian_body(
     layout_handles: &mut ResMut<TextureAtlasLayoutHandles>,
     asset_server: &Res<AssetServer>,
     pos: Vec3,
+    rng: &mut impl Rng,
 ) -> Entity {
 
     let max_offset = 4.;
     let start = pos + vec3(
-        rand::random::<f32>() * max_offset * 2. - max_offset,
-        rand::random::<f32>() * max_offset * 2. - max_offset,
+        rng.gen::<f32>() * max_offset * 2. - max_offset,
+        rng.gen::<f32>() * max_offset * 2. - max_offset,
         0.
     );
-    let flipped = rand::thread_rng().gen_bool(0.5);
+    let flipped = rng.gen_bool(0.5);
     let offset = if flipped{vec3(-2., 0., 0.)} else {vec3(2., 0., 0.)};
-    commands.spawn(animated_soul_bundle(asset_server, layout_handles))
+    commands.spawn((animated_soul_bundle(asset_server, layout_handles), StateScoped(Screen::Playing)))
     .insert(Transform::from_translation(offset+vec3(0., 8., 10.) + start).ease_to(
-        Transform::from_translation(offset+start+vec3(0., 12. + rand::thread_rng().gen::<f32>() * 5., 1.)),
+        Transform::from_translation(offset+start+vec3(0., 12. + rng.gen::<f32>() * 5., 1.)),
         EaseFunction::ExponentialOut,
         EasingType::Once {
             duration: std::time::Duration::from_secs(1),
@@ -219,6 +220,7 @@ pub fn spawn_cililian_body(
     commands.spawn((
         TransformBundle::default(),
         VisibilityBundle::default(),
+        StateScoped(Screen::Playing),
         DespawnTimer::seconds(5.),
     ))
     .insert(Transform::from_translation(vec3(0., -1., -2.) + start).with_scale(vec3(if flipped{-1.} else {1.}, 1., 0.)))
@@ -246,18 +248,19 @@ pub fn spawn_hunter_body(
     layout_handles: &mut ResMut<TextureAtlasLayoutHandles>,
     asset_server: &Res<AssetServer>,
     pos: Vec3,
+    rng: &mut impl Rng,
 ) -> Entity {
     let max_offset = 4.;
     let start = pos + vec3(
-        rand::random::<f32>() * max_offset * 2. - max_offset,
-        rand::random::<f32>() * max_offset * 2. - max_offset,
+        rng.gen::<f32>() * max_offset * 2. - max_offset,
+        rng.gen::<f32>() * max_offset * 2. - max_offset,
         0.
     );
-    let flipped = rand::thread_rng().gen_bool(0.5);
+    let flipped = rng.gen_bool(0.5);
     let offset = if flipped{vec3(-2., 0., 0.)} else {vec3(2., 0., 0.)};
-    commands.spawn(animated_soul_bundle(asset_server, layout_handles))
+    commands.spawn((animated_soul_bundle(asset_server, layout_handles), StateScoped(Screen::Playing)))
     .insert(Transform::from_translation(offset+vec3(0., 8., 10.) + start).ease_to(
-        Transform::from_translation(offset+start+vec3(0., 12. + rand::thread_rng().gen::<f32>() * 5., 0.)),
+        Transform::from_translation(offset+start+vec3(0., 12. + rng.gen::<f32>() * 5., 0.)),
         EaseFunction::ExponentialOut,
         EasingType::Once {
             duration: std::time::Duration::from_secs(1),
@@ -267,6 +270,7 @@ pub fn spawn_hunter_body(
     commands.spawn((
         TransformBundle::default(),
         VisibilityBundle::default(),
+        StateScoped(Screen::Playing),
         DespawnTimer::seconds(5.),
     ))
     .insert(Transform::from_translation(vec3(0., -3., -2.) + start).with_scale(vec3(if flipped{-1.} else {1.}, 1., 1.)))
@@ -294,27 +298,29 @@ pub fn spawn_question_particle(
     layout_handles: &mut ResMut<TextureAtlasLayoutHandles>,
     asset_server: &Res<AssetServer>,
     pos: Vec3,
+    rng: &mut impl Rng,
 ){
     let lifetime = 2.;
     let max_offset = 2.;
     let start = pos+vec3(
-        rand::random::<f32>() * max_offset * 2. - max_offset,
-        rand::random::<f32>() * max_offset * 2. - max_offset,
+        rng.gen::<f32>() * max_offset * 2. - max_offset,
+        rng.gen::<f32>() * max_offset * 2. - max_offset,
         0.
     );
     commands.spawn((
         TransformBundle::default(),
         VisibilityBundle::default(),
+        StateScoped(Screen::Playing),
         DespawnTimer::seconds(lifetime),
     ))
     .insert(Transform::from_translation(vec3(0., 8., 8.) + start))
     .with_children(|commands| {
         commands.spawn((
             Name::new("Particle"),
-            emotion_bundle(asset_server, layout_handles, rand::thread_rng().gen_range(0..3) + 6),
-            Transform::from_translation(vec3(0., 0., 0.)).with_rotation(Quat::from_rotation_z(rand::thread_rng().gen::<f32>() - 0.5)).with_scale(Vec3::splat(0.5))
+            emotion_bundle(asset_server, layout_handles, rng.gen_range(0..3) + 6),
+            Transform::from_translation(vec3(0., 0., 0.)).with_rotation(Quat::from_rotation_z(rng.gen::<f32>() - 0.5)).with_scale(Vec3::splat(0.5))
                 .ease_to(
-                    Transform::from_translation(vec3(rand::thread_rng().gen::<f32>() * 3. - 1.5, 4. + rand::thread_rng().gen::<f32>() * 5., 0.)).with_rotation(Quat::from_rotation_z(rand::thread_rng().gen::<f32>() - 0.5))
+                    Transform::from_translation(vec3(rng.gen::<f32>() * 3. - 1.5, 4. + rng.gen::<f32>() * 5., 0.)).with_rotation(Quat::from_rotation_z(rng.gen::<f32>() - 0.5))
                     .with_scale(Vec3::splat(1.5)),
                     EaseFunction::ExponentialOut,
                     EasingType::Once {
@@ -338,28 +344,30 @@ pub fn spawn_angry_particle(
     layout_handles: &mut ResMut<TextureAtlasLayoutHandles>,
     asset_server: &Res<AssetServer>,
     pos: Vec3,
+    rng: &mut impl Rng,
 ){
     let max_offset = 2.;
     let start = pos+vec3(
-        rand::random::<f32>() * max_offset * 2. - max_offset,
-        rand::random::<f32>() * max_offset * 2. - max_offset,
+        rng.gen::<f32>() * max_offset * 2. - max_offset,
+        rng.gen::<f32>() * max_offset * 2. - max_offset,
         0.
     );
 
-    let flipped = rand::thread_rng().gen::<bool>();
+    let flipped = rng.gen::<bool>();
     commands.spawn((
         TransformBundle::default(),
         VisibilityBundle::default(),
+        StateScoped(Screen::Playing),
         DespawnTimer::seconds(1.),
     ))
     .insert(Transform::from_translation(vec3(0., 8., 8.) + start))
     .with_children(|commands| {
         commands.spawn((
             Name::new("Particle"),
-            emotion_bundle(asset_server, layout_handles, rand::thread_rng().gen_range(0..3)),
-            Transform::from_translation(vec3(0., 0., 0.)).with_rotation(Quat::from_rotation_z(rand::thread_rng().gen::<f32>() - 0.5)).with_scale(Vec3::splat(0.5) * vec3(if flipped{-1.} else {1.}, 1., 1.))
+            emotion_bundle(asset_server, layout_handles, rng.gen_range(0..3)),
+            Transform::from_translation(vec3(0., 0., 0.)).with_rotation(Quat::from_rotation_z(rng.gen::<f32>() - 0.5)).with_scale(Vec3::splat(0.5) * vec3(if flipped{-1.} else {1.}, 1., 1.))
                 .ease_to(
-                    Transform::from_translation(vec3(rand::thread_rng().gen::<f32>() * 3. - 1.5, 4. + rand::thread_rng().gen::<f32>() * 5., 0.)).with_rotation(Quat::from_rotation_z(rand::thread_rng().gen::<f32>() - 0.5)).with_scale(vec3(if flipped{-1.} else {1.}, 1., 1.)),
+                    Transform::from_translation(vec3(rng.gen::<f32>() * 3. - 1.5, 4. + rng.gen::<f32>() * 5., 0.)).with_rotation(Quat::from_rotation_z(rng.gen::<f32>() - 0.5)).with_scale(vec3(if flipped{-1.} else {1.}, 1., 1.)),
                     EaseFunction::ExponentialOut,
                     EasingType::Once {
                         duration: std::time::Duration::from_secs(1),
@@ -382,27 +390,29 @@ pub fn spawn_warn_particle(
     layout_handles: &mut ResMut<TextureAtlasLayoutHandles>,
     asset_server: &Res<AssetServer>,
     pos: Vec3,
+    rng: &mut impl Rng,
 ){
     let lifetime = 2.;
     let max_offset = 2.;
     let start = pos+vec3(
-        rand::random::<f32>() * max_offset * 2. - max_offset,
-        rand::random::<f32>() * max_offset * 2. - max_offset,
+        rng.gen::<f32>() * max_offset * 2. - max_offset,
+        rng.gen::<f32>() * max_offset * 2. - max_offset,
         0.
     );
     commands.spawn((
         TransformBundle::default(),
         VisibilityBundle::default(),
+        StateScoped(Screen::Playing),
         DespawnTimer::seconds(lifetime),
     ))
     .insert(Transform::from_translation(vec3(0., 8., 8.) + start))
     .with_children(|commands| {
         commands.spawn((
             Name::new("Particle"),
-            emotion_bundle(asset_server, layout_handles, rand::thread_rng().gen_range(0..3) + 3),
-            Transform::from_translation(vec3(0., 0., 0.)).with_rotation(Quat::from_rotation_z(rand::thread_rng().gen::<f32>() - 0.5)).with_scale(Vec3::splat(0.5))
+            emotion_bundle(asset_server, layout_handles, rng.gen_range(0..3) + 3),
+            Transform::from_translation(vec3(0., 0., 0.)).with_rotation(Quat::from_rotation_z(rng.gen::<f32>() - 0.5)).with_scale(Vec3::splat(0.5))
                 .ease_to(
-                    Transform::from_translation(vec3(rand::thread_rng().gen::<f32>() * 3. - 1.5, 4. + rand::thread_rng().gen::<f32>() * 5., 0.)).with_rotation(Quat::from_rotation_z(rand::thread_rng().gen::<f32>() - 0.5))
+                    Transform::from_translation(vec3(rng.gen::<f32>() * 3. - 1.5, 4. + rng.gen::<f32>() * 5., 0.)).with_rotation(Quat::from_rotation_z(rng.gen::<f32>() - 0.5))
                     .with_scale(Vec3::splat(1.5)),
                     EaseFunction::ExponentialOut,
                     EasingType::Once {
@@ -445,6 +455,7 @@ fn spawn_blood_particle(
     commands.spawn((
         TransformBundle::default(),
         VisibilityBundle::default(),
+        StateScoped(Screen::Playing),
     )).with_children(|commands|{
         commands.spawn((
             SpriteBundle{
@@ -1,51 +1,92 @@
-use std::time::Duration;
+use std::{collections::BTreeSet, time::Duration};
 
 use bevy::{color::palettes::css::{BLUE, RED}, math::uvec2, prelude::*};
 use bevy_light_2d::light::AmbientLight2d;
 use pathfinding::num_traits::{Euclid, Signed};
 
-use crate::{characters::animation::AnimationController, core::{camera::plugin::MainCamera, functions::TextureAtlasLayoutHandles, post_processing::PostProcessUniform}, npc::systems::RosesCollected, player::components::{ParentEntity, Player, UpgradeButton}, sounds::components::PlaySoundEvent};
-
-pub const TRANSLATION_DURATION: f32 = 1.0;
-pub const DAY_DURATION: f32 = 15.0;
+use crate::{balance::{DayCycleBalance, GameBalance}, characters::animation::AnimationController, core::{camera::plugin::MainCamera, functions::TextureAtlasLayoutHandles, post_processing::PostProcessUniform}, npc::{alert::AlertLevel, components::NpcIds, systems::RosesCollected}, player::components::Player, screen::Screen, NUM_ROSES};
 
 #[derive(Resource)]
 pub struct DayCycle {
     pub is_night: bool,
     pub is_translating: bool,
+    /// 0 is full day, 1 is full night
+    pub daytime: f32,
 }
 
 // 0 is morning
-pub fn get_local_time_f(elapsed: f32) -> f32{
-    ((elapsed + TRANSLATION_DURATION * 0.5) % (TRANSLATION_DURATION * 2. + DAY_DURATION * 2.)) / (TRANSLATION_DURATION * 2. + DAY_DURATION * 2.)
+pub fn get_local_time_f(elapsed: f32, day: &DayCycleBalance) -> f32{
+    ((elapsed + day.translation_duration * 0.5) % day.cycle_duration()) / day.cycle_duration()
+}
+
+/// Seconds until the next night ends
+pub fn until_dawn(elapsed: f32, day: &DayCycleBalance) -> f32 {
+    (day.day_duration + day.translation_duration * 0.5 - elapsed).rem_euclid(day.cycle_duration())
+}
+
+
+/// Game clock at the start of the current run. The day cycle and replays count from here.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct RunStart(pub Duration);
+
+impl RunStart {
+    pub fn elapsed(&self, time: &Time<Virtual>) -> Duration {
+        time.elapsed().saturating_sub(self.0)
+    }
+}
+
+/// Every run starts at night with no roses, the previous run's entities went away with `StateScoped`.
+pub fn start_run(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+) {
+    commands.insert_resource(RunStart(time.elapsed()));
+    commands.insert_resource(PauseReasons::default());
+    commands.insert_resource(DayCycle {
+        is_night: true,
+        is_translating: false,
+        daytime: 1.,
+    });
+    commands.insert_resource(AlertLevel::default());
+    commands.insert_resource(NpcIds::default());
+    commands.insert_resource(RosesCollected {
+        collected: 0,
+        max: NUM_ROSES,
+    });
 }
 
+/// Bounces straight back into a new run, `Restarting` only exists so `Playing` is exited and entered again.
+pub fn restart_run(mut next_screen: ResMut<NextState<Screen>>) {
+    next_screen.set(Screen::Playing);
+}
+
+/// Leaving a paused run shouldn't leave the clock stopped for the menus.
+pub fn end_run(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
 
 pub fn update_daycycle(
     mut cycle: ResMut<DayCycle>,
-    mut post_process: Query<&mut PostProcessUniform>,
-    mut cam: Query<&mut AmbientLight2d, With<MainCamera>>,
     time: Res<Time<Virtual>>,
-    mut pc_q: Query<&mut AnimationController, With<Player>>
+    run_start: Res<RunStart>,
+    mut pc_q: Query<&mut AnimationController, With<Player>>,
+    balance: Res<GameBalance>,
 ) {
-    let cycle_time = (time.elapsed_seconds() + TRANSLATION_DURATION * 2. + DAY_DURATION * 2.) % (TRANSLATION_DURATION * 2. + DAY_DURATION * 2.);
-    let is_night_raw = cycle_time < (TRANSLATION_DURATION + DAY_DURATION);
-    let local_time = cycle_time % (TRANSLATION_DURATION + DAY_DURATION);
+    let day = &balance.daycycle;
+    let cycle_time = (run_start.elapsed(&time).as_secs_f32() + day.cycle_duration()) % day.cycle_duration();
+    let is_night_raw = cycle_time < (day.translation_duration + day.day_duration);
+    let local_time = cycle_time % (day.translation_duration + day.day_duration);
     cycle.is_night = is_night_raw;
-    let mut light = cam.single_mut();
     cycle.is_translating = false;
-    if local_time > DAY_DURATION {
-        let translation = (local_time - DAY_DURATION) / TRANSLATION_DURATION;
-        let v = if is_night_raw {1.-translation} else {translation};
-        post_process.single_mut().daytime = v;
-        light.brightness = (1. - v) * 0.8 + 0.2;
+    if local_time > day.day_duration {
+        let translation = (local_time - day.day_duration) / day.translation_duration;
+        cycle.daytime = if is_night_raw {1.-translation} else {translation};
         cycle.is_translating = true;
         if translation > 0.5 {
             cycle.is_night = !cycle.is_night;
         }
     } else {
-        post_process.single_mut().daytime = if cycle.is_night {1.} else {0.};
-        light.brightness = if cycle.is_night {0.2} else {1.};
+        cycle.daytime = if cycle.is_night {1.} else {0.};
     }
     for mut pc in pc_q.iter_mut(){
         if cycle.is_night {
@@ -56,10 +97,47 @@ pub fn update_daycycle(
     }
 }
 
-#[derive(Event, Debug)]
-pub struct PauseEvent;
+pub fn apply_daycycle_fx(
+    cycle: Res<DayCycle>,
+    mut post_process: Query<&mut PostProcessUniform>,
+    mut cam: Query<&mut AmbientLight2d, With<MainCamera>>,
+) {
+    let Ok(mut light) = cam.get_single_mut() else {return};
+    let Ok(mut post_process) = post_process.get_single_mut() else {return};
+    post_process.daytime = cycle.daytime;
+    light.brightness = (1. - cycle.daytime) * 0.8 + 0.2;
+}
+
+/// Something that holds the game paused while it is on screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PauseReason {
+    Menu,
+    LevelUp,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub enum PauseEvent {
+    Pause(PauseReason),
+    Resume(PauseReason),
+}
+
+/// Reasons stack: the game only runs again once every popup that paused it is closed.
+#[derive(Resource, Default, Debug)]
+pub struct PauseReasons(BTreeSet<PauseReason>);
 
-#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+impl PauseReasons {
+    pub fn contains(&self, reason: PauseReason) -> bool {
+        self.0.contains(&reason)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Only exists while a run is on screen.
+#[derive(SubStates, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[source(Screen = Screen::Playing)]
 pub enum GameState {
     #[default]
     InGame,
@@ -67,24 +145,25 @@ pub enum GameState {
 }
 
 pub fn pause_game(
-    state: Res<State<GameState>>,
+    mut reasons: ResMut<PauseReasons>,
     mut next_state: ResMut<NextState<GameState>>,
     mut time: ResMut<Time<Virtual>>,
     mut pause_event: EventReader<PauseEvent>,
 ) {
-    let t = pause_event.read();
-    for _pause in t {
-        match state.get() {
-            GameState::InGame => {
-                next_state.set(GameState::Pause);
-                time.pause();
-            },
-            GameState::Pause => {
-                next_state.set(GameState::InGame);
-                time.unpause();
-            },
+    if pause_event.is_empty() {return}
+    for event in pause_event.read() {
+        match *event {
+            PauseEvent::Pause(reason) => {reasons.0.insert(reason);},
+            PauseEvent::Resume(reason) => {reasons.0.remove(&reason);},
         }
     }
+    if reasons.is_empty() {
+        next_state.set(GameState::InGame);
+        time.unpause();
+    } else {
+        next_state.set(GameState::Pause);
+        time.pause();
+    }
 }
 
 #[derive(Component)]
@@ -115,7 +194,7 @@ pub fn spawn_score(
                 ..default()
             },
             ..default()
-        }, Score));
+        }, Score, StateScoped(Screen::Playing)));
         commands.spawn((TextBundle {
             style: Style {
                 top: Val::Percent(5.),
@@ -130,7 +209,7 @@ pub fn spawn_score(
                 ..default()
             },
             ..default()
-        }, ScoreRoses));
+        }, ScoreRoses, StateScoped(Screen::Playing)));
     }
 }
 
@@ -147,111 +226,3 @@ pub fn update_score(
         score_roses.sections[0].value = format!("Roses: {:?} / {}", roses.collected, roses.max);
     }
 }
-
-#[derive(Component)]
-pub struct StartButton;
-
-pub fn spawn_starter_screen(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut pause_event: EventWriter<PauseEvent>,
-) {
-    pause_event.send(PauseEvent);
-    let font = asset_server.load("fonts/Monocraft.ttf");
-    let parent = commands.spawn((
-        ImageBundle {
-            style: Style {
-                align_self: AlignSelf::Center,
-                justify_self: JustifySelf::Center,
-                justify_content: JustifyContent::Center,
-                justify_items: JustifyItems::Center,
-                flex_direction: FlexDirection::Column,
-                width: Val::Px(400.),
-                height: Val::Px(600.),
-                ..default()
-            },
-            image: UiImage::from(asset_server.load("scroll.png")),
-            ..default()
-        },
-        Name::new("StarterScreen"),
-    )).with_children(|parent| {
-        parent.spawn(TextBundle {
-            style: Style {
-                width: Val::Percent(40.),
-                height: Val::Percent(40.),
-                align_self: AlignSelf::Center,
-                justify_self: JustifySelf::Center,
-                ..default()
-            },
-            text: Text {
-                sections: vec![TextSection::new(
-                    "   A game by yaroyanuo and kaiva-morphin 
-                                For Bevy Game Jam 5.
-
-                    Controls:
-                    WASD - movement, LShift - Dash.
-                    If you are experiencing  lags, press F4
-                    ",
-                    TextStyle { font: font.clone_weak(), font_size: 16., color: Color::srgb_u8(169, 96, 45) })],
-                ..default()
-            },
-            ..default()
-        });
-    }).id();
-    let child = commands.spawn((ButtonBundle {
-        style: Style {
-            width: Val::Px(150.),
-            height: Val::Px(30.),
-            justify_items: JustifyItems::Center,
-            justify_content: JustifyContent::Center,
-            align_self: AlignSelf::Center,
-            justify_self: JustifySelf::Center,
-            margin: UiRect::top(Val::Px(30.)),
-            ..default()
-        },
-        image: UiImage::from(asset_server.load("button.png")),
-        ..default()
-    },
-    StartButton,
-    ParentEntity {entity: parent},
-    )).with_children(|parent| {
-        parent.spawn(TextBundle {
-            style: Style {
-                align_self: AlignSelf::Center,
-                justify_self: JustifySelf::Center,
-                ..default()
-            },
-            text: Text {
-                sections: vec![TextSection::new("Start", TextStyle { font, font_size: 16., color: Color::srgb_u8(169, 96, 45) })],
-                ..default()
-            },
-            ..default()
-        });
-    }).id();
-    commands.entity(parent).add_child(child);
-}
-
-pub fn interact_start_button(
-    mut commands: Commands,
-    mut button_q: Query<(&Interaction, &mut UiImage, &ParentEntity), (With<StartButton>, Changed<Interaction>)>,
-    mut pause_event: EventWriter<PauseEvent>,
-    mut play_sound: EventWriter<PlaySoundEvent>,
-    asset_server: Res<AssetServer>,
-) {
-    if let Ok((interaction, mut image, parent_entity)) = button_q.get_single_mut() {
-        match *interaction {
-            Interaction::Pressed => {
-                commands.entity(parent_entity.entity).despawn_recursive();
-                play_sound.send(PlaySoundEvent::Selected);
-                pause_event.send(PauseEvent);
-            }
-            Interaction::Hovered => {
-                play_sound.send(PlaySoundEvent::Select);
-                *image = UiImage::from(asset_server.load("select_button.png"));
-            }
-            Interaction::None => {
-                *image = UiImage::from(asset_server.load("button.png"));
-            }
-        }
-    }
-}
\ No newline at end of file
@@ -3,20 +3,12 @@
 // Unused utilities and re-exports may trigger these lints undesirably.
 #![allow(dead_code, unused_imports)]
 
-pub mod interaction;
 pub mod palette;
 mod widgets;
 
 pub mod prelude {
     pub use super::{
-        interaction::{InteractionPalette, InteractionQuery},
         palette as ui_palette,
-        widgets::{Containers as _, Widgets as _},
+        widgets::{scroll, Containers as _, Widgets as _, UI_FONT},
     };
 }
-
-use bevy::prelude::*;
-
-pub(super) fn plugin(app: &mut App) {
-    app.add_plugins(interaction::plugin);
-}
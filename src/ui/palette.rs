@@ -1,10 +1,6 @@
 use bevy::prelude::*;
 
-pub const BUTTON_HOVERED_BACKGROUND: Color = Color::srgb(0.186, 0.328, 0.573);
-pub const BUTTON_PRESSED_BACKGROUND: Color = Color::srgb(0.286, 0.478, 0.773);
-
-pub const BUTTON_TEXT: Color = Color::srgb(0.925, 0.925, 0.925);
-pub const LABEL_TEXT: Color = Color::srgb(0.867, 0.827, 0.412);
-pub const HEADER_TEXT: Color = Color::srgb(0.867, 0.827, 0.412);
-
-pub const NODE_BACKGROUND: Color = Color::srgb(0.286, 0.478, 0.773);
+/// Ink on the scroll
+pub const BUTTON_TEXT: Color = Color::srgb(0.663, 0.376, 0.176);
+pub const LABEL_TEXT: Color = Color::srgb(0.663, 0.376, 0.176);
+pub const HEADER_TEXT: Color = Color::srgb(0.478, 0.129, 0.129);
@@ -2,40 +2,38 @@
 
 use bevy::{ecs::system::EntityCommands, prelude::*, ui::Val::*};
 
-use super::{interaction::InteractionPalette, palette::*};
+use super::palette::*;
+use crate::menu::Focusable;
+
+pub const UI_FONT: &str = "fonts/Monocraft.ttf";
 
 /// An extension trait for spawning UI widgets.
 pub trait Widgets {
-    /// Spawn a simple button with text.
-    fn button(&mut self, text: impl Into<String>) -> EntityCommands;
+    /// Spawn a button with text. Its image follows the menu focus, see [`Focusable`].
+    fn button(&mut self, text: impl Into<String>, font: &Handle<Font>) -> EntityCommands;
 
-    /// Spawn a simple header label. Bigger than [`Widgets::label`].
-    fn header(&mut self, text: impl Into<String>) -> EntityCommands;
+    /// Spawn a header label. Bigger than [`Widgets::label`].
+    fn header(&mut self, text: impl Into<String>, font: &Handle<Font>) -> EntityCommands;
 
-    /// Spawn a simple text label.
-    fn label(&mut self, text: impl Into<String>) -> EntityCommands;
+    /// Spawn a text label.
+    fn label(&mut self, text: impl Into<String>, font: &Handle<Font>) -> EntityCommands;
 }
 
 impl<T: Spawn> Widgets for T {
-    fn button(&mut self, text: impl Into<String>) -> EntityCommands {
+    fn button(&mut self, text: impl Into<String>, font: &Handle<Font>) -> EntityCommands {
         let mut entity = self.spawn((
             Name::new("Button"),
             ButtonBundle {
                 style: Style {
-                    width: Px(200.0),
-                    height: Px(65.0),
+                    width: Px(150.0),
+                    height: Px(30.0),
                     justify_content: JustifyContent::Center,
                     align_items: AlignItems::Center,
                     ..default()
                 },
-                background_color: BackgroundColor(NODE_BACKGROUND),
                 ..default()
             },
-            InteractionPalette {
-                none: NODE_BACKGROUND,
-                hovered: BUTTON_HOVERED_BACKGROUND,
-                pressed: BUTTON_PRESSED_BACKGROUND,
-            },
+            Focusable,
         ));
         entity.with_children(|children| {
             children.spawn((
@@ -43,9 +41,9 @@ impl<T: Spawn> Widgets for T {
                 TextBundle::from_section(
                     text,
                     TextStyle {
-                        font_size: 40.0,
+                        font: font.clone(),
+                        font_size: 16.0,
                         color: BUTTON_TEXT,
-                        ..default()
                     },
                 ),
             ));
@@ -53,18 +51,15 @@ impl<T: Spawn> Widgets for T {
         entity
     }
 
-    fn header(&mut self, text: impl Into<String>) -> EntityCommands {
+    fn header(&mut self, text: impl Into<String>, font: &Handle<Font>) -> EntityCommands {
         let mut entity = self.spawn((
             Name::new("Header"),
             NodeBundle {
                 style: Style {
-                    width: Px(500.0),
-                    height: Px(65.0),
                     justify_content: JustifyContent::Center,
                     align_items: AlignItems::Center,
                     ..default()
                 },
-                background_color: BackgroundColor(NODE_BACKGROUND),
                 ..default()
             },
         ));
@@ -74,9 +69,9 @@ impl<T: Spawn> Widgets for T {
                 TextBundle::from_section(
                     text,
                     TextStyle {
-                        font_size: 40.0,
+                        font: font.clone(),
+                        font_size: 24.0,
                         color: HEADER_TEXT,
-                        ..default()
                     },
                 ),
             ));
@@ -84,12 +79,12 @@ impl<T: Spawn> Widgets for T {
         entity
     }
 
-    fn label(&mut self, text: impl Into<String>) -> EntityCommands {
+    fn label(&mut self, text: impl Into<String>, font: &Handle<Font>) -> EntityCommands {
         let mut entity = self.spawn((
             Name::new("Label"),
             NodeBundle {
                 style: Style {
-                    width: Px(500.0),
+                    max_width: Percent(80.0),
                     justify_content: JustifyContent::Center,
                     align_items: AlignItems::Center,
                     ..default()
@@ -103,17 +98,38 @@ impl<T: Spawn> Widgets for T {
                 TextBundle::from_section(
                     text,
                     TextStyle {
-                        font_size: 24.0,
+                        font: font.clone(),
+                        font_size: 16.0,
                         color: LABEL_TEXT,
-                        ..default()
                     },
-                ),
+                )
+                .with_text_justify(JustifyText::Center),
             ));
         });
         entity
     }
 }
 
+/// The parchment every menu is written on, lays its children out in a centered column.
+pub fn scroll(asset_server: &AssetServer, width: f32, height: f32) -> impl Bundle {
+    (
+        Name::new("Scroll"),
+        ImageBundle {
+            style: Style {
+                width: Px(width),
+                height: Px(height),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Px(10.0),
+                ..default()
+            },
+            image: UiImage::from(asset_server.load("scroll.png")),
+            ..default()
+        },
+    )
+}
+
 /// An extension trait for spawning UI containers.
 pub trait Containers {
     /// Spawns a root node that covers the full screen
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use vampillage::{balance::GameBalance, npc::{ai::{civilian_brain, hunter_brain, Behavior, Blackboard, Brain, Node}, schedule::Activity}};
+
+#[test]
+fn archetypes_pick_their_behaviors() {
+    let balance = GameBalance::default();
+    let (civilian, hunter) = (civilian_brain(), hunter_brain());
+    let pick = |tree: &Node, bb: &Blackboard| tree.pick(bb, &balance);
+
+    let idle = Blackboard {active: true, ..default()};
+    assert_eq!(pick(&civilian, &idle), Some(Behavior::Wander));
+    assert_eq!(pick(&hunter, &idle), Some(Behavior::Wander));
+
+    let spotted = |dist: f32, is_night: bool| Blackboard {sees_player: true, player_dist: dist, is_night, last_known: Some(Vec2::ZERO), ..idle};
+    assert_eq!(pick(&civilian, &spotted(50., true)), Some(Behavior::Flee));
+    assert_eq!(pick(&civilian, &spotted(50., false)), Some(Behavior::ChaseTo));
+    assert_eq!(pick(&civilian, &spotted(10., false)), Some(Behavior::Melee));
+    assert_eq!(pick(&hunter, &spotted(50., true)), Some(Behavior::Flee));
+    assert_eq!(pick(&hunter, &spotted(150., true)), Some(Behavior::ThrowAt));
+    assert_eq!(pick(&hunter, &spotted(250., false)), Some(Behavior::ChaseTo));
+
+    // out of sight, hunters go looking while civilians only care at night
+    let heard = Blackboard {last_known: Some(Vec2::ZERO), ..idle};
+    assert_eq!(pick(&hunter, &heard), Some(Behavior::Investigate));
+    assert_eq!(pick(&civilian, &heard), Some(Behavior::Wander));
+    let lost = Blackboard {search_at: Some(Vec2::ZERO), ..idle};
+    assert_eq!(pick(&hunter, &lost), Some(Behavior::Search));
+    // a fresh lead beats the search
+    assert_eq!(pick(&hunter, &Blackboard {last_known: Some(Vec2::ZERO), ..lost}), Some(Behavior::Investigate));
+
+    // squads take their spots and fall back together
+    assert_eq!(pick(&hunter, &Blackboard {slot: Some(IVec2::ZERO), ..spotted(150., true)}), Some(Behavior::Surround));
+    assert_eq!(pick(&hunter, &Blackboard {slot: Some(IVec2::ZERO), ..spotted(50., true)}), Some(Behavior::Flee));
+    assert_eq!(pick(&hunter, &Blackboard {retreating: true, ..spotted(150., true)}), Some(Behavior::Retreat));
+
+    // witnesses tell someone before anything else, armed civilians stand their ground at night
+    assert_eq!(pick(&civilian, &Blackboard {witnessed: Some(Vec2::ZERO), ..spotted(50., true)}), Some(Behavior::Report));
+    assert_eq!(pick(&civilian, &Blackboard {armed: true, ..spotted(50., true)}), Some(Behavior::ChaseTo));
+
+    // civilians keep to their schedule, strolling once they are there
+    let far = Blackboard {activity: Activity::Work, destination: Some(IVec2::new(10, 0)), ..idle};
+    assert_eq!(pick(&civilian, &far), Some(Behavior::Commute));
+    assert_eq!(pick(&civilian, &Blackboard {destination: Some(IVec2::new(1, 0)), ..far}), Some(Behavior::Wander));
+    assert_eq!(pick(&civilian, &Blackboard {activity: Activity::Home, destination: Some(IVec2::new(1, 0)), ..idle}), Some(Behavior::Commute));
+    assert_eq!(pick(&civilian, &Blackboard {sees_player: true, player_dist: 50., ..far}), Some(Behavior::ChaseTo));
+
+    let dead = Blackboard {dead: true, ..spotted(10., false)};
+    assert_eq!(pick(&civilian, &dead), Some(Behavior::Die));
+    assert_eq!(pick(&hunter, &dead), Some(Behavior::Die));
+}
+
+#[test]
+fn npcs_of_an_archetype_share_their_tree() {
+    assert!(Arc::ptr_eq(&Brain::civilian().tree, &Brain::civilian().tree));
+    assert!(Arc::ptr_eq(&Brain::hunter().tree, &Brain::hunter().tree));
+    assert!(!Arc::ptr_eq(&Brain::civilian().tree, &Brain::hunter().tree));
+}
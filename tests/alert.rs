@@ -0,0 +1,36 @@
+use vampillage::{balance::AlertBalance, npc::alert::AlertLevel};
+
+#[test]
+fn reports_raise_the_alert_and_calm_days_lower_it() {
+    let balance = AlertBalance {per_report: 1., max: 2., decay: 0.5, ..Default::default()};
+    let mut alert = AlertLevel::default();
+    for _ in 0..3 {
+        alert.raise(&balance);
+    }
+    assert_eq!(alert.level, 2., "capped");
+
+    // the day of the reports doesn't count as calm
+    alert.dawn(&balance);
+    assert_eq!(alert.level, 2.);
+    alert.dawn(&balance);
+    assert_eq!(alert.level, 1.5);
+    for _ in 0..5 {
+        alert.dawn(&balance);
+    }
+    assert_eq!(alert.level, 0.);
+}
+
+#[test]
+fn alert_brings_hunters_arms_and_groups() {
+    let balance = AlertBalance {hunter_spawn: 0.5, armed_per_level: 0.25, group_level: 2., ..Default::default()};
+    let calm = AlertLevel::default();
+    assert_eq!(calm.hunter_spawn_chance(0.2, &balance), 0.2);
+    assert_eq!(calm.armed_chance(&balance), 0.);
+    assert!(!calm.grouped(&balance));
+
+    let alert = AlertLevel {level: 2., ..Default::default()};
+    assert!((alert.hunter_spawn_chance(0.2, &balance) - 0.4).abs() < 1e-9);
+    assert_eq!(alert.hunter_spawn_chance(0.8, &balance), 1.);
+    assert_eq!(alert.armed_chance(&balance), 0.5);
+    assert!(alert.grouped(&balance));
+}
@@ -0,0 +1,41 @@
+use std::{fs, path::Path};
+
+use vampillage::screen::loading::GAME_IMAGES;
+
+/// Shown before the loading screen, or not loaded through the asset server
+const NOT_PRELOADED: &[&str] = &["images/splash.png", "my_icon.png"];
+
+fn png_literals(dir: &Path, found: &mut Vec<String>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            png_literals(&path, found);
+            continue;
+        }
+        for line in fs::read_to_string(&path).unwrap().lines() {
+            for (end, _) in line.match_indices(".png\"") {
+                let Some(start) = line[..end].rfind('"') else {continue};
+                found.push(line[start + 1..end + 4].to_string());
+            }
+        }
+    }
+}
+
+#[test]
+fn every_preloaded_image_exists() {
+    let assets = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    for path in GAME_IMAGES {
+        assert!(assets.join(path).is_file(), "{path} is preloaded but missing");
+    }
+}
+
+#[test]
+fn every_image_the_game_loads_is_preloaded() {
+    let mut found = Vec::new();
+    png_literals(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut found);
+    assert!(!found.is_empty());
+    for path in found {
+        if NOT_PRELOADED.contains(&path.as_str()) {continue}
+        assert!(GAME_IMAGES.contains(&path.as_str()), "{path} is loaded in src but missing from GAME_IMAGES");
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use bevy::{asset::{AssetMetaCheck, LoadState}, prelude::*};
+use vampillage::balance::{BalanceHandle, BalancePlugin, GameBalance};
+
+#[test]
+fn validation_lists_every_bad_field() {
+    let mut balance = GameBalance::default();
+    assert!(balance.validate().is_ok());
+
+    balance.civilian.spawn_chance = 2.;
+    balance.daycycle.day_duration = 0.;
+    balance.search.look_time = f32::NAN;
+    balance.squad.thrower_cd = -0.5;
+    let errors = balance.validate().unwrap_err().0;
+    assert_eq!(errors.len(), 4, "{errors:?}");
+    for field in ["civilian.spawn_chance", "daycycle.day_duration", "search.look_time", "squad.thrower_cd"] {
+        assert!(errors.iter().any(|error| error.starts_with(field)), "{field} missing from {errors:?}");
+    }
+}
+
+#[test]
+fn a_balance_with_bad_values_keeps_the_previous_one() {
+    let dir = std::env::temp_dir().join(format!("vampillage-balance-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("game.balance.ron"), "(civilian: (spawn_chance: 2.0))").unwrap();
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            file_path: dir.to_string_lossy().into_owned(),
+            meta_check: AssetMetaCheck::Never,
+            ..default()
+        },
+        BalancePlugin,
+    ));
+    app.world_mut().resource_mut::<GameBalance>().civilian.spawn_chance = 0.3;
+
+    let mut error = None;
+    for _ in 0..1000 {
+        app.update();
+        let handle = app.world().resource::<BalanceHandle>().0.id();
+        if let Some(LoadState::Failed(e)) = app.world().resource::<AssetServer>().get_load_state(handle) {
+            error = Some(e.to_string());
+            break;
+        }
+        // assets load on other threads
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    let error = error.expect("the balance file never failed to load");
+    assert!(error.contains("civilian.spawn_chance"), "{error}");
+
+    app.update();
+    assert_eq!(app.world().resource::<GameBalance>().civilian.spawn_chance, 0.3);
+}
@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+use vampillage::controls::{Action, InputBindings};
+
+#[test]
+fn rebinding_replaces_the_first_key_only() {
+    let mut bindings = InputBindings::default();
+    bindings.rebind(Action::MoveUp, KeyCode::KeyZ).unwrap();
+    assert_eq!(bindings.keys(Action::MoveUp), &[KeyCode::KeyZ, KeyCode::ArrowUp]);
+}
+
+#[test]
+fn rebinding_takes_the_key_from_an_action_with_others() {
+    let mut bindings = InputBindings::default();
+    bindings.rebind(Action::Dash, KeyCode::KeyE).unwrap();
+    assert_eq!(bindings.keys(Action::Dash), &[KeyCode::KeyE]);
+    assert_eq!(bindings.keys(Action::Interact), &[KeyCode::Enter]);
+}
+
+#[test]
+fn an_action_keeps_its_last_key() {
+    let mut bindings = InputBindings::default();
+    assert_eq!(bindings.rebind(Action::Dash, KeyCode::F10), Err(Action::Settings));
+    assert_eq!(bindings.keys(Action::Dash), &[KeyCode::ShiftLeft]);
+    assert_eq!(bindings.keys(Action::Settings), &[KeyCode::F10]);
+}
@@ -0,0 +1,34 @@
+use bevy::{math::ivec2, prelude::*};
+use vampillage::{map::plugin::TrespassableCells, npc::{components::NpcState, flowfield::{FlowField, FlowFields}}};
+
+/// A 5x5 yard split by a wall at x = 2 with a gap at the top
+fn yard() -> TrespassableCells {
+    let mut cells = vec![vec![true; 5]; 5];
+    cells[2] = vec![false, false, false, false, true];
+    TrespassableCells {cells: cells.into(), ready: true, ..default()}
+}
+
+#[test]
+fn the_field_leads_through_the_gap_to_the_goal() {
+    let cells = yard();
+    let goal = ivec2(4, 0);
+    let toward = FlowField::toward(&cells, goal);
+    assert_eq!(toward.get(goal), Some(0));
+    assert_eq!(toward.get(ivec2(2, 0)), None, "walls are unreachable");
+
+    let path = toward.path(&cells, ivec2(0, 0), |p| p == goal);
+    assert_eq!(path.first(), Some(&ivec2(0, 0)));
+    assert_eq!(path.last(), Some(&goal));
+    assert!(path.contains(&ivec2(2, 4)));
+    for step in path.windows(2) {
+        let mov = step[1] - step[0];
+        assert!(mov.x.abs() <= 1 && mov.y.abs() <= 1);
+        // diagonal steps never cut the wall's corners
+        assert!(cells.is_trespassable(&ivec2(step[1].x, step[0].y)) && cells.is_trespassable(&ivec2(step[0].x, step[1].y)));
+    }
+
+    let away = FlowField::away(&toward, &cells);
+    let fields = FlowFields {goal: Some(goal), toward, away};
+    let escape = fields.path(NpcState::Escape, &cells, ivec2(4, 1), false).unwrap();
+    assert!(escape.last().unwrap().distance_squared(goal) > escape[0].distance_squared(goal));
+}
@@ -0,0 +1,488 @@
+use std::time::Duration;
+
+use bevy::{ecs::system::RunSystemOnce, input::{keyboard::{Key, KeyboardInput}, ButtonState}, input::gamepad::{GamepadAxisChangedEvent, GamepadConnection, GamepadConnectionEvent, GamepadEvent, GamepadInfo}, prelude::*};
+use bevy_ecs_ldtk::prelude::{LdtkProject, Respawn};
+use vampillage::{
+    balance::GameBalance,
+    controls::{Action, InputBindings, RebindAction, Rebinding},
+    core::functions::{ConfigDir, TextureAtlasLayoutHandles},
+    map::{campaign::Campaign, plugin::TrespassableCells, tilemap::{Structure, TransformToGrid}},
+    npc::{ai::Blackboard, components::{Civilian, Hunter, NpcId}, perception::Noise, systems::{spawn_civilian, spawn_hunter, Win}},
+    player::{components::Player, replay::{Replay, ReplayPlayer, ReplayRecorder}},
+    rng::GameRng,
+    screen::Screen,
+    systems::{DayCycle, GameState, PauseEvent, PauseReason},
+    HeadlessAppPlugin,
+};
+
+/// 60 seconds of simulated time
+const MAX_FRAMES: usize = 60 * 60;
+
+#[derive(Resource, Default)]
+struct Wins(u32);
+
+fn count_wins(mut wins: EventReader<Win>, mut count: ResMut<Wins>) {
+    count.0 += wins.read().count() as u32;
+}
+
+fn fixture_plugin() -> HeadlessAppPlugin {
+    HeadlessAppPlugin::default()
+        .with_asset_root(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets"))
+        .with_map("map/fixture.ldtk")
+}
+
+/// Builds the app on `tests/assets/map/fixture.ldtk` and steps it until the level is spawned
+/// and the pathfinding grid is built. Spawners are turned off so tests place every NPC themselves.
+fn fixture_app() -> App {
+    fixture_app_with(fixture_plugin())
+}
+
+fn fixture_app_with(plugin: HeadlessAppPlugin) -> App {
+    let mut app = App::new();
+    app.add_plugins(plugin);
+    app.init_resource::<Wins>();
+    app.add_systems(Update, count_wins);
+
+    let mut ready = false;
+    for _ in 0..1000 {
+        app.update();
+        let world = app.world();
+        if world.resource::<TrespassableCells>().ready && world.resource::<TransformToGrid>().ready {
+            ready = true;
+            break;
+        }
+        // assets load on other threads
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(ready, "fixture level never finished loading");
+
+    let mut balance = app.world_mut().resource_mut::<GameBalance>();
+    balance.civilian.spawn_chance = 0.;
+    balance.hunter.spawn_chance = 0.;
+    app
+}
+
+fn count<C: Component>(app: &mut App) -> usize {
+    let world = app.world_mut();
+    world.query_filtered::<(), With<C>>().iter(world).count()
+}
+
+fn player_pos(app: &mut App) -> Vec2 {
+    let world = app.world_mut();
+    world.query_filtered::<&Transform, With<Player>>().single(world).translation.xy()
+}
+
+fn move_player(app: &mut App, pos: Vec2) {
+    let world = app.world_mut();
+    let mut player = world.query_filtered::<&mut Transform, With<Player>>();
+    player.single_mut(world).translation = pos.extend(0.);
+}
+
+#[test]
+fn collecting_three_roses_fires_win() {
+    let mut app = fixture_app();
+    // roses lie in a row right of the player spawn
+    move_player(&mut app, Vec2::new(24., 8.));
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyD);
+
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        if app.world().resource::<Wins>().0 > 0 {
+            break;
+        }
+    }
+    assert_eq!(app.world().resource::<Wins>().0, 1);
+}
+
+/// Holds `key` until [`Win`] has fired `wins` times in total
+fn collect_roses(app: &mut App, key: KeyCode, wins: u32) {
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        if app.world().resource::<Wins>().0 >= wins {
+            break;
+        }
+    }
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(key);
+    assert_eq!(app.world().resource::<Wins>().0, wins);
+}
+
+fn player(app: &mut App) -> &Player {
+    let world = app.world_mut();
+    world.query::<&Player>().single(world)
+}
+
+#[test]
+fn collecting_the_roses_moves_the_campaign_to_the_next_level() {
+    let mut app = fixture_app();
+    let max_speed = {
+        let world = app.world_mut();
+        let mut player = world.query::<&mut Player>().single_mut(world);
+        player.max_speed *= 1.1;
+        player.max_speed
+    };
+    move_player(&mut app, Vec2::new(24., 8.));
+    collect_roses(&mut app, KeyCode::KeyD, 1);
+
+    // the second fixture level is narrower and sits east of the first one
+    let mut ready = false;
+    for _ in 0..1000 {
+        app.update();
+        let world = app.world();
+        if world.resource::<TrespassableCells>().ready && world.resource::<TransformToGrid>().current_grid().is_some_and(|grid| grid.grid_size == IVec2::new(24, 20)) {
+            ready = true;
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(ready, "second level never finished loading");
+    assert_eq!(app.world().resource::<Campaign>().level, 1);
+    assert_eq!(app.world().resource::<TrespassableCells>().cells.len(), 24);
+    // the player stands on the second level's PlayerStart
+    let start = Vec2::new(624., 16.);
+    assert_eq!(app.world().resource::<Campaign>().start, Some(start));
+    assert!(player_pos(&mut app).distance(start) < 1., "the player was left at {}", player_pos(&mut app));
+    let transformer = app.world().resource::<TransformToGrid>();
+    let (iid, grid) = transformer.level_at(start).expect("the start lies in no level");
+    assert_eq!(Some(iid), transformer.current.as_ref());
+    assert_eq!(grid.cell_size, Vec2::splat(16.));
+    assert!(transformer.level_at(Vec2::new(16., 16.)).is_none(), "the first level is still loaded");
+    assert!(!player(&mut app).is_dead);
+    assert_eq!(player(&mut app).max_speed, max_speed, "upgrades were lost");
+
+    // the roses of the last level win the run
+    move_player(&mut app, start + Vec2::new(8., -8.));
+    collect_roses(&mut app, KeyCode::KeyA, 2);
+    app.update();
+    assert!(player(&mut app).is_dead);
+}
+
+#[test]
+fn hunter_reaches_player_last_known_position() {
+    let mut app = fixture_app();
+    let hunter_pos = Vec2::new(328., -136.);
+    // far enough from the player spawn to stay out of sight the whole way
+    let target = IVec2::new(29, 2);
+
+    app.world_mut().run_system_once(move |
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
+        balance: Res<GameBalance>,
+    | {
+        spawn_hunter(&mut commands, &asset_server, hunter_pos, &mut layout_handles, &balance);
+    });
+    app.world_mut().run_system_once(move |mut hunter: Query<&mut Blackboard, With<Hunter>>, transformer: Res<TransformToGrid>| {
+        hunter.single_mut().last_known = Some(transformer.to_world(target));
+    });
+
+    let mut reached = false;
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        let world = app.world_mut();
+        let pos = world.query_filtered::<&Transform, With<Hunter>>().single(world).translation.xy();
+        if world.resource::<TransformToGrid>().from_world_i32(pos) == target {
+            reached = true;
+            break;
+        }
+    }
+    assert!(reached, "hunter never reached {target}");
+}
+
+#[test]
+fn civilians_go_home_at_night() {
+    let mut app = fixture_app();
+    assert!(app.world().resource::<DayCycle>().is_night);
+    // the fixture's home is a few cells right of here, away from the player
+    let civilian_pos = Vec2::new(232., -104.);
+    app.world_mut().run_system_once(move |
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
+        mut rng: ResMut<GameRng>,
+    | {
+        spawn_civilian(&mut commands, &asset_server, civilian_pos, &mut layout_handles, &mut rng.spawning);
+    });
+    app.update();
+    assert_eq!(count::<Civilian>(&mut app), 1);
+
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        if count::<Civilian>(&mut app) == 0 {
+            break;
+        }
+    }
+    assert_eq!(count::<Civilian>(&mut app), 0, "civilian never went inside");
+    assert!(!player(&mut app).is_dead);
+}
+
+fn add_hunter(app: &mut App, pos: Vec2) -> Entity {
+    app.world_mut().run_system_once(move |
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
+        balance: Res<GameBalance>,
+    | {
+        spawn_hunter(&mut commands, &asset_server, pos, &mut layout_handles, &balance);
+    });
+    let world = app.world_mut();
+    world.query_filtered::<(Entity, &Transform), With<Hunter>>().iter(world)
+        .find(|(_, transform)| transform.translation.xy() == pos)
+        .map(|(entity, _)| entity)
+        .unwrap()
+}
+
+#[test]
+fn npcs_are_numbered_in_spawn_order() {
+    let mut app = fixture_app();
+    let first = add_hunter(&mut app, Vec2::new(40., 8.));
+    let second = add_hunter(&mut app, Vec2::new(56., 8.));
+    let first = *app.world().get::<NpcId>(first).unwrap();
+    assert_eq!(*app.world().get::<NpcId>(second).unwrap(), NpcId(first.0 + 1));
+}
+
+fn last_known(app: &App, npc: Entity) -> Option<Vec2> {
+    app.world().get::<Blackboard>(npc).unwrap().last_known
+}
+
+#[test]
+fn hunters_hear_noises_within_their_hearing() {
+    let mut app = fixture_app();
+    let hunter = add_hunter(&mut app, Vec2::new(328., -136.));
+    app.update();
+    let hearing = app.world().resource::<GameBalance>().hunter.hearing;
+
+    let far = Vec2::new(328., -36.);
+    app.world_mut().send_event(Noise {pos: far, loudness: 50. / hearing});
+    app.update();
+    assert_eq!(last_known(&app, hunter), None, "too quiet to carry 100 px");
+
+    app.world_mut().send_event(Noise {pos: far, loudness: 150. / hearing});
+    app.update();
+    assert_eq!(last_known(&app, hunter), Some(far));
+}
+
+#[test]
+fn spotting_the_player_alerts_the_npcs_around() {
+    let mut app = fixture_app();
+    // only the hunter right next to the player sees it
+    app.world_mut().resource_mut::<GameBalance>().hunter.spot_dist = 0.;
+    let alert_radius = app.world().resource::<GameBalance>().perception.alert_radius;
+    let player = Vec2::new(-40., 0.);
+    move_player(&mut app, player);
+    let spotter = add_hunter(&mut app, player + Vec2::new(15., 0.));
+    let near = add_hunter(&mut app, player + Vec2::new(15., alert_radius - 20.));
+    let far = add_hunter(&mut app, player + Vec2::new(alert_radius + 35., 0.));
+    app.update();
+
+    let seen = player_pos(&mut app);
+    assert!(last_known(&app, spotter).is_some_and(|pos| pos.distance(seen) < 4.));
+    assert!(last_known(&app, near).is_some_and(|pos| pos.distance(seen) < 4.), "was not told");
+    assert_eq!(last_known(&app, far), None);
+}
+
+fn loaded_levels(app: &App) -> usize {
+    app.world().resource::<TransformToGrid>().levels.len()
+}
+
+#[test]
+fn walking_two_levels_east_despawns_the_npcs_left_behind() {
+    // three levels in a row, the player starts in the west one
+    let mut app = fixture_app_with(fixture_plugin().with_map("map/streaming.ldtk"));
+    for _ in 0..1000 {
+        if loaded_levels(&app) == 2 {break}
+        app.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(loaded_levels(&app), 2, "the middle level never loaded next to the west one");
+    let west = app.world().resource::<TransformToGrid>().current.clone().unwrap();
+
+    app.world_mut().run_system_once(|
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        mut layout_handles: ResMut<TextureAtlasLayoutHandles>,
+        mut rng: ResMut<GameRng>,
+    | {
+        spawn_civilian(&mut commands, &asset_server, Vec2::new(-130., 60.), &mut layout_handles, &mut rng.spawning);
+    });
+    app.update();
+    assert_eq!(count::<Civilian>(&mut app), 1);
+
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyD);
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        if !app.world().resource::<TransformToGrid>().levels.contains_key(&west) {break}
+        // assets load on other threads
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(KeyCode::KeyD);
+    app.update();
+    assert!(player_pos(&mut app).x > 352., "the player never reached the east level");
+    assert!(!app.world().resource::<TransformToGrid>().levels.contains_key(&west), "the west level was never unloaded");
+    assert_eq!(count::<Civilian>(&mut app), 0);
+}
+
+#[test]
+fn replay_reproduces_recorded_run() {
+    let mut app = fixture_app();
+    app.insert_resource(ReplayRecorder::new(None));
+    let keys = [(KeyCode::KeyD, 90), (KeyCode::KeyS, 40), (KeyCode::ShiftLeft, 1), (KeyCode::KeyA, 60)];
+    for (key, frames) in keys {
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+        for _ in 0..frames {
+            app.update();
+        }
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(key);
+    }
+    app.update();
+    let recorded_pos = player_pos(&mut app);
+    let replay = app.world().resource::<ReplayRecorder>().replay.clone();
+    assert!(!replay.frames.is_empty());
+
+    let mut bytes = vec![];
+    replay.write_to(&mut bytes).unwrap();
+    let replay = Replay::read_from(bytes.as_slice()).unwrap();
+
+    let mut playback = fixture_app_with(fixture_plugin().with_replay(replay));
+    let finished = |app: &App| app.world().resource::<ReplayPlayer>().is_finished();
+    for _ in 0..MAX_FRAMES {
+        if finished(&playback) {
+            break;
+        }
+        playback.update();
+    }
+    assert!(finished(&playback), "replay never finished");
+    let replayed_pos = player_pos(&mut playback);
+    assert!(recorded_pos.distance(replayed_pos) < 1e-3, "recorded {recorded_pos}, replayed {replayed_pos}");
+}
+
+/// How far the player walks in a second with the left stick held at `tilt`
+fn stick_distance(tilt: Vec2) -> f32 {
+    let mut app = fixture_app();
+    // open ground to the left, up to the level border
+    move_player(&mut app, Vec2::new(24., 8.));
+    let gamepad = Gamepad::new(0);
+    let info = GamepadInfo { name: "Test pad".to_string() };
+    app.world_mut().send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(gamepad, GamepadConnection::Connected(info))));
+    app.update();
+    app.world_mut().send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(gamepad, GamepadAxisType::LeftStickX, tilt.x)));
+    app.world_mut().send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(gamepad, GamepadAxisType::LeftStickY, tilt.y)));
+    app.update();
+    let start = player_pos(&mut app);
+    for _ in 0..60 {
+        app.update();
+    }
+    player_pos(&mut app).distance(start)
+}
+
+#[test]
+fn half_tilted_stick_walks_at_half_speed() {
+    let full = stick_distance(Vec2::new(-1., 0.));
+    let half = stick_distance(Vec2::new(-0.5, 0.));
+    assert!(full > 0., "the stick never moved the player");
+    let ratio = half / full;
+    assert!((0.4..0.6).contains(&ratio), "full tilt walked {full}, half tilt {half}");
+}
+
+#[test]
+fn leaving_a_run_despawns_it_and_the_next_run_starts_fresh() {
+    let mut app = fixture_app();
+    assert_eq!(count::<Player>(&mut app), 1);
+
+    app.world_mut().resource_mut::<NextState<Screen>>().set(Screen::Title);
+    app.update();
+    assert_eq!(count::<Player>(&mut app), 0);
+    assert!(app.world().get_resource::<State<GameState>>().is_none());
+
+    app.world_mut().resource_mut::<NextState<Screen>>().set(Screen::Playing);
+    app.update();
+    assert_eq!(count::<Player>(&mut app), 1);
+    assert_eq!(*app.world().resource::<State<GameState>>().get(), GameState::InGame);
+    let mut ready = false;
+    for _ in 0..1000 {
+        app.update();
+        if app.world().resource::<TrespassableCells>().ready {
+            ready = true;
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(ready, "second run never finished loading its level");
+}
+
+/// Presses and releases `key` over two frames
+fn tap_key(app: &mut App, key_code: KeyCode, logical_key: Key) {
+    for state in [ButtonState::Pressed, ButtonState::Released] {
+        app.world_mut().send_event(KeyboardInput { key_code, logical_key: logical_key.clone(), state, window: Entity::PLACEHOLDER });
+        app.update();
+    }
+}
+
+#[test]
+fn the_next_key_press_is_bound_and_saved() {
+    let mut app = fixture_app();
+    app.world_mut().send_event(RebindAction(Action::Dash));
+    app.update();
+    assert_eq!(app.world().resource::<Rebinding>().0, Some(Action::Dash));
+
+    tap_key(&mut app, KeyCode::Space, Key::Space);
+    assert_eq!(app.world().resource::<Rebinding>().0, None);
+    assert_eq!(app.world().resource::<InputBindings>().keys(Action::Dash), &[KeyCode::Space]);
+    assert_eq!(InputBindings::load(app.world().resource::<ConfigDir>()).keys(Action::Dash), &[KeyCode::Space]);
+}
+
+fn game_state(app: &App) -> GameState {
+    *app.world().resource::<State<GameState>>().get()
+}
+
+#[test]
+fn closing_the_pause_menu_keeps_a_level_up_paused() {
+    let mut app = fixture_app();
+    app.world_mut().send_event(PauseEvent::Pause(PauseReason::LevelUp));
+    app.update();
+    app.update();
+    assert_eq!(game_state(&app), GameState::Pause);
+
+    // open the menu over the level up, then close it
+    tap_key(&mut app, KeyCode::Escape, Key::Escape);
+    app.update();
+    assert_eq!(game_state(&app), GameState::Pause);
+    tap_key(&mut app, KeyCode::Escape, Key::Escape);
+    app.update();
+    assert_eq!(game_state(&app), GameState::Pause);
+    assert!(app.world().resource::<Time<Virtual>>().is_paused());
+
+    app.world_mut().send_event(PauseEvent::Resume(PauseReason::LevelUp));
+    app.update();
+    app.update();
+    assert_eq!(game_state(&app), GameState::InGame);
+    assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+}
+
+#[test]
+fn respawning_the_level_rebuilds_its_grids_and_colliders() {
+    let mut app = fixture_app();
+    let cells = app.world().resource::<TrespassableCells>().cells.clone();
+    let colliders = count::<Structure>(&mut app);
+    assert!(colliders > 0);
+
+    // what a hot reload of the .ldtk file does to the world
+    let world = app.world_mut();
+    let ldtk_world = world.query_filtered::<Entity, With<Handle<LdtkProject>>>().single(world);
+    world.entity_mut(ldtk_world).insert(Respawn);
+    let mut invalidated = false;
+    let mut rebuilt = false;
+    for _ in 0..100 {
+        app.update();
+        let ready = app.world().resource::<TrespassableCells>().ready;
+        invalidated |= !ready;
+        if invalidated && ready {
+            rebuilt = true;
+            break;
+        }
+    }
+    assert!(invalidated, "the grid was never invalidated");
+    assert!(rebuilt, "the grid was never rebuilt");
+    assert_eq!(app.world().resource::<TrespassableCells>().cells, cells);
+    assert_eq!(count::<Structure>(&mut app), colliders);
+}
@@ -0,0 +1,34 @@
+use bevy::{math::{ivec2, vec2}, prelude::*};
+use bevy_ecs_ldtk::prelude::LevelIid;
+use vampillage::map::{plugin::TrespassableCells, tilemap::{LevelGrid, TransformToGrid}};
+
+#[test]
+fn neighboring_levels_are_stitched_into_one_grid() {
+    let (left, right, small) = (LevelIid::new("left"), LevelIid::new("right"), LevelIid::new("small"));
+    let mut transformer = TransformToGrid::default();
+    let mut trespassable = TrespassableCells::default();
+    // two levels of 16px cells side by side, columns are listed left to right
+    transformer.levels.insert(left.clone(), LevelGrid::from_corner(Vec2::ZERO, Vec2::splat(16.), ivec2(2, 2)));
+    trespassable.levels.insert(left.clone(), vec![vec![false, true], vec![true, true]]);
+    transformer.levels.insert(right.clone(), LevelGrid::from_corner(vec2(32., 0.), Vec2::splat(16.), ivec2(3, 2)));
+    trespassable.levels.insert(right.clone(), vec![vec![true, true], vec![true, true], vec![false, false]]);
+    // another cell size can't be stitched
+    transformer.levels.insert(small.clone(), LevelGrid::from_corner(vec2(0., 32.), Vec2::splat(8.), ivec2(2, 2)));
+    trespassable.levels.insert(small, vec![vec![true, true], vec![true, true]]);
+
+    assert!(transformer.stitch(&trespassable).is_none(), "stitched without a current level");
+    transformer.current = Some(left);
+    let (grid, cells) = transformer.stitch(&trespassable).unwrap();
+    assert_eq!(grid.grid_size, ivec2(5, 2));
+    assert_eq!(cells, vec![
+        vec![false, true],
+        vec![true, true],
+        vec![true, true],
+        vec![true, true],
+        vec![false, false],
+    ]);
+    // across the border
+    assert_eq!(grid.from_world_i32(vec2(40., 8.)), ivec2(2, 1));
+    assert_eq!(grid.to_world(ivec2(2, 1)), vec2(40., 8.));
+    assert_eq!(transformer.level_at(vec2(40., 8.)).map(|(iid, _)| iid), Some(&right));
+}
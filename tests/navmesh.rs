@@ -0,0 +1,39 @@
+use bevy::{math::{ivec2, vec2}, prelude::*};
+use vampillage::{map::plugin::TrespassableCells, npc::navmesh::{funnel, NavMesh}};
+
+#[test]
+fn open_ground_is_one_region() {
+    let cells = TrespassableCells {cells: vec![vec![true; 64]; 64].into(), ready: true, ..default()};
+    let navmesh = NavMesh::new(&cells);
+    assert_eq!(navmesh.regions.len(), 1);
+    assert_eq!(navmesh.path(ivec2(0, 0), ivec2(63, 40)), Some(vec![ivec2(0, 0), ivec2(63, 40)]));
+}
+
+#[test]
+fn paths_bend_around_the_corners_of_walls() {
+    let cells = TrespassableCells::from_rows(&[
+        ".......",
+        "...#...",
+        "...#...",
+        ".......",
+    ]);
+    let navmesh = NavMesh::new(&cells);
+    assert_eq!(navmesh.region(ivec2(3, 1)), None);
+    let path = navmesh.path(ivec2(0, 1), ivec2(6, 1)).unwrap();
+    assert_eq!(path.first(), Some(&ivec2(0, 1)));
+    assert_eq!(path.last(), Some(&ivec2(6, 1)));
+    assert_eq!(path.len(), 3, "{path:?} does not turn at the wall");
+    for segment in path.windows(2) {
+        assert!(cells.line_of_sight(segment[0], segment[1]), "{segment:?} goes through the wall");
+    }
+}
+
+#[test]
+fn the_funnel_only_bends_where_it_has_to() {
+    // two wide portals in a row, the line from start to end passes through both
+    let portals = [(vec2(0., 4.), vec2(0., -4.)), (vec2(4., 4.), vec2(4., -4.))];
+    assert_eq!(funnel(vec2(-2., 0.), vec2(6., 1.), &portals), vec![vec2(-2., 0.), vec2(6., 1.)]);
+    // the second portal is above the line, the string bends at its lower end
+    let portals = [(vec2(0., 4.), vec2(0., -4.)), (vec2(4., 10.), vec2(4., 6.))];
+    assert_eq!(funnel(vec2(-2., 0.), vec2(6., 0.), &portals), vec![vec2(-2., 0.), vec2(4., 6.), vec2(6., 0.)]);
+}
@@ -0,0 +1,71 @@
+use bevy::{math::ivec2, prelude::*};
+use vampillage::{
+    map::{plugin::TrespassableCells, tilemap::TransformToGrid},
+    npc::{
+        components::{NpcId, NpcPath, NpcState, PathInFlight, PathRequest},
+        navmesh::LevelNavMesh,
+        pathfinder::{dispatch_path_requests, pathfinder, smooth_path, PathBudget, PathGrids},
+    },
+};
+
+#[test]
+fn line_of_sight_never_cuts_a_corner() {
+    let cells = TrespassableCells::from_rows(&[
+        ".....",
+        ".#...",
+        ".....",
+    ]);
+    assert!(cells.line_of_sight(ivec2(0, 0), ivec2(4, 0)));
+    assert!(cells.line_of_sight(ivec2(0, 2), ivec2(4, 1)), "passes beside the wall");
+    assert!(!cells.line_of_sight(ivec2(0, 1), ivec2(4, 1)));
+    assert!(!cells.line_of_sight(ivec2(0, 0), ivec2(2, 2)));
+    // exactly through the wall's corner
+    assert!(!cells.line_of_sight(ivec2(1, 2), ivec2(2, 1)));
+    assert!(!cells.line_of_sight(ivec2(2, 1), ivec2(1, 2)));
+    assert!(cells.line_of_sight(ivec2(2, 1), ivec2(3, 2)));
+}
+
+#[test]
+fn paths_walk_straight_lines_around_walls() {
+    let cells = TrespassableCells::from_rows(&[
+        ".......",
+        "...#...",
+        "...#...",
+        ".......",
+    ]);
+    let transformer = TransformToGrid {ready: true, ..default()};
+    let path = pathfinder(ivec2(0, 1), ivec2(6, 1), &cells, &transformer, NpcState::Chill, false).unwrap();
+    assert_eq!(path.first(), Some(&ivec2(0, 1)));
+    assert_eq!(path.last(), Some(&ivec2(6, 1)));
+    assert!(path.len() <= 4, "{path:?} was not smoothed");
+    for segment in path.windows(2) {
+        assert!(cells.line_of_sight(segment[0], segment[1]), "{segment:?} goes through the wall");
+    }
+
+    let straight = vec![ivec2(0, 0), ivec2(1, 0), ivec2(2, 0), ivec2(3, 0)];
+    assert_eq!(smooth_path(straight, &cells), vec![ivec2(0, 0), ivec2(3, 0)]);
+}
+
+#[test]
+fn urgent_paths_are_dispatched_first() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(TrespassableCells::from_rows(&["....."]))
+        .insert_resource(TransformToGrid {ready: true, ..default()})
+        .insert_resource(PathBudget {per_frame: 1})
+        .init_resource::<LevelNavMesh>()
+        .init_resource::<PathGrids>()
+        .add_systems(Update, dispatch_path_requests);
+    let request = |id, state| (NpcId(id), NpcPath::default(), PathRequest::new(ivec2(0, 0), ivec2(4, 0), state, false));
+    // the wanderer asked first
+    let wanderer = app.world_mut().spawn(request(0, NpcState::Chill)).id();
+    let hunter = app.world_mut().spawn(request(1, NpcState::Chase)).id();
+
+    app.update();
+    assert!(app.world().get::<PathInFlight>(hunter).is_some());
+    assert!(app.world().get::<PathRequest>(wanderer).is_some(), "went over the budget");
+
+    app.world_mut().entity_mut(hunter).remove::<PathInFlight>();
+    app.update();
+    assert!(app.world().get::<PathInFlight>(wanderer).is_some());
+}
@@ -0,0 +1,23 @@
+use bevy::{math::vec2, prelude::*};
+use vampillage::npc::perception::{in_view, lamp_light};
+
+#[test]
+fn npcs_see_ahead_and_feel_behind() {
+    let facing = Vec2::X;
+    // 90 degree cone, 100 range, felt closer than 20
+    assert!(in_view(Vec2::ZERO, facing, vec2(50., 10.), 100., 90., 20.));
+    assert!(!in_view(Vec2::ZERO, facing, vec2(150., 0.), 100., 90., 20.), "out of range");
+    assert!(!in_view(Vec2::ZERO, facing, vec2(30., 40.), 100., 90., 20.), "out of the cone");
+    assert!(!in_view(Vec2::ZERO, facing, vec2(-50., 0.), 100., 90., 20.), "behind the back");
+    assert!(in_view(Vec2::ZERO, facing, vec2(-10., 0.), 100., 90., 20.), "too close not to notice");
+}
+
+#[test]
+fn the_closest_lamp_lights_up_to_its_radius() {
+    let lights = [(Vec2::ZERO, 100., 0.8), (vec2(300., 0.), 100., 0.4)];
+    assert_eq!(lamp_light(Vec2::ZERO, &lights), 0.8);
+    assert_eq!(lamp_light(vec2(50., 0.), &lights), 0.4);
+    assert_eq!(lamp_light(vec2(260., 0.), &lights), 0.4 * 0.6, "the far lamp is closer");
+    assert_eq!(lamp_light(vec2(150., 0.), &lights), 0., "out of reach of both");
+    assert_eq!(lamp_light(Vec2::ZERO, &[]), 0.);
+}
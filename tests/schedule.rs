@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use vampillage::{balance::ScheduleBalance, npc::schedule::{activity_at, pick_spot, Activity}, rng::RngStream};
+
+#[test]
+fn the_latest_started_activity_runs() {
+    let activities = ScheduleBalance::default().activities;
+    assert_eq!(activity_at(0.1, &activities), Activity::Home);
+    assert_eq!(activity_at(0.5, &activities), Activity::Work);
+    assert_eq!(activity_at(0.8, &activities), Activity::Wander);
+    assert_eq!(activity_at(0.95, &activities), Activity::Gather);
+
+    // the evening goes on past midnight until the first entry
+    let late = [(0.2, Activity::Work), (0.9, Activity::Home)];
+    assert_eq!(activity_at(0.1, &late), Activity::Home);
+    assert_eq!(activity_at(0.5, &late), Activity::Work);
+    assert_eq!(activity_at(0.5, &[]), Activity::Wander);
+}
+
+#[test]
+fn unreachable_spots_are_never_picked_again() {
+    let mut rng = RngStream::seed_from_u64(0);
+    let spots = [Vec2::new(0., 0.), Vec2::new(64., 0.), Vec2::new(128., 0.)];
+    for _ in 0..50 {
+        assert_eq!(pick_spot(&spots, &spots[..2], &mut rng), Some(spots[2]));
+    }
+    assert_eq!(pick_spot(&spots, &spots, &mut rng), None);
+    assert_eq!(pick_spot(&[], &[], &mut rng), None);
+}
@@ -0,0 +1,40 @@
+use bevy::math::ivec2;
+use vampillage::{map::plugin::TrespassableCells, npc::search::{search_points, split_points}};
+
+#[test]
+fn searches_ahead_of_the_player_then_hiding_spots() {
+    let cells = TrespassableCells::from_rows(&[
+        "#########",
+        "#.......#",
+        "#.......#",
+        "#...#####",
+        "#.......#",
+        "#########",
+    ]);
+    let points = search_points(ivec2(2, 2), ivec2(12, 2), &cells, 4, 4);
+    // the player ran right, followed up to the wall
+    assert_eq!(points[0], ivec2(7, 2));
+    assert!(points.len() <= 4);
+    for point in &points {
+        assert!(cells.is_trespassable(point));
+        assert_ne!(*point, ivec2(2, 2));
+    }
+    // the corridor behind the wall is hidden, so it is checked first
+    assert!(!cells.line_of_sight(ivec2(2, 2), points[1]), "{points:?}");
+    for (i, a) in points.iter().enumerate() {
+        for b in &points[i + 1..] {
+            assert!((*a - *b).abs().max_element() >= 2, "{a} and {b} are too close");
+        }
+    }
+}
+
+#[test]
+fn searchers_split_the_points() {
+    let points = [ivec2(0, 0), ivec2(1, 0), ivec2(10, 0), ivec2(11, 0)];
+    let shares = split_points(&points, &[ivec2(0, 1), ivec2(10, 1)]);
+    assert_eq!(shares, vec![vec![ivec2(0, 0), ivec2(1, 0)], vec![ivec2(10, 0), ivec2(11, 0)]]);
+
+    let alone = split_points(&points, &[ivec2(0, 0)]);
+    assert_eq!(alone, vec![points.to_vec()]);
+    assert!(split_points(&points, &[]).is_empty());
+}
@@ -0,0 +1,76 @@
+use bevy::{core_pipeline::bloom::BloomSettings, prelude::*, window::PresentMode};
+use bevy_kira_audio::AudioChannel;
+use vampillage::{
+    controls::ActionState,
+    core::{camera::plugin::MainCamera, functions::ConfigDir, post_processing::PostProcessUniform},
+    settings::{Settings, SettingsPlugin},
+    sounds::{components::{ChannelVolumes, DayChannel, NightChannel, SfxChannel, DAY_VOLUME, NIGHT_VOLUME}, systems::apply_volume},
+};
+
+/// Config files of `test` go to a temporary directory of their own
+fn temp_config_dir(test: &str) -> ConfigDir {
+    ConfigDir(std::env::temp_dir().join(format!("vampillage-{test}-{}", std::process::id())))
+}
+
+#[test]
+fn settings_round_trip_through_the_config_file() {
+    let config_dir = temp_config_dir("round-trip");
+    let settings = Settings {
+        day_volume: 0.3,
+        night_volume: 0.6,
+        sfx_volume: 0.,
+        vignette_strength: 0.1,
+        wave_strength: 4.,
+        bloom: false,
+        tree_sway: false,
+        present_mode: PresentMode::Fifo,
+    };
+    settings.save(&config_dir);
+    assert_eq!(Settings::load(&config_dir), settings);
+}
+
+#[test]
+fn changing_a_volume_sets_only_that_channel() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .insert_resource(AudioChannel::<DayChannel>::default())
+        .insert_resource(AudioChannel::<NightChannel>::default())
+        .insert_resource(AudioChannel::<SfxChannel>::default())
+        .insert_resource(Settings::default())
+        .init_resource::<ChannelVolumes>()
+        .add_systems(Update, apply_volume.run_if(resource_changed::<Settings>));
+    app.update();
+    assert_eq!(*app.world().resource::<ChannelVolumes>(), ChannelVolumes {
+        day: Some(DAY_VOLUME),
+        night: Some(NIGHT_VOLUME),
+        sfx: Some(1.),
+    });
+
+    app.world_mut().resource_mut::<Settings>().day_volume = 0.5;
+    app.update();
+    let volumes = app.world().resource::<ChannelVolumes>();
+    assert_eq!(volumes.day, Some(DAY_VOLUME * 0.5));
+    assert_eq!(volumes.night, Some(NIGHT_VOLUME));
+}
+
+#[test]
+fn post_process_and_bloom_follow_the_settings() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .init_resource::<ActionState>()
+        .insert_resource(temp_config_dir("post-process"))
+        .insert_resource(Settings::default())
+        .add_plugins(SettingsPlugin);
+    let camera = app.world_mut().spawn((MainCamera, PostProcessUniform::default())).id();
+    app.update();
+    assert!(app.world().get::<BloomSettings>(camera).is_some());
+
+    let mut settings = app.world_mut().resource_mut::<Settings>();
+    settings.vignette_strength = 0.8;
+    settings.wave_strength = 2.;
+    settings.bloom = false;
+    app.update();
+    let uniform = app.world().get::<PostProcessUniform>(camera).unwrap();
+    assert_eq!((uniform.vignette_strength, uniform.wave_strength), (0.8, 2.));
+    assert!(app.world().get::<BloomSettings>(camera).is_none());
+}
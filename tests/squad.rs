@@ -0,0 +1,60 @@
+use bevy::{math::ivec2, prelude::*};
+use vampillage::{
+    balance::{DayCycleBalance, GameBalance}, map::plugin::TrespassableCells,
+    npc::squad::{assign_roles, group_squads, slot_cell, Role}, systems::until_dawn,
+};
+
+#[test]
+fn hunters_in_reach_of_each_other_group_up() {
+    let hunters = [Vec2::new(0., 0.), Vec2::new(500., 0.), Vec2::new(90., 0.), Vec2::new(180., 0.)];
+    // chained through the one in the middle
+    assert_eq!(group_squads(&hunters, 100.), vec![vec![0, 2, 3], vec![1]]);
+    assert_eq!(group_squads(&[], 100.), Vec::<Vec<usize>>::new());
+}
+
+#[test]
+fn squads_block_flank_and_throw() {
+    let player = Vec2::ZERO;
+    let members = [Vec2::new(-100., 0.), Vec2::new(0., 100.), Vec2::new(0., -100.), Vec2::new(100., 10.)];
+    let roles = assign_roles(&members, player, Vec2::X, 50.);
+    assert_eq!(roles[3], (Role::Blocker, Some(Vec2::new(50., 0.))));
+    assert_eq!(roles[1], (Role::Flanker, Some(Vec2::new(0., 50.))));
+    assert_eq!(roles[2], (Role::Flanker, Some(Vec2::new(0., -50.))));
+    assert_eq!(roles[0], (Role::Thrower, None));
+
+    let pair = assign_roles(&members[..2], player, Vec2::X, 50.);
+    assert_eq!(pair, vec![(Role::Thrower, None), (Role::Flanker, Some(Vec2::new(0., 50.)))]);
+    assert_eq!(assign_roles(&members[..1], player, Vec2::X, 50.), vec![(Role::Thrower, None)]);
+}
+
+#[test]
+fn slots_see_the_player() {
+    let cells = TrespassableCells::from_rows(&[
+        ".......",
+        "..###..",
+        ".......",
+    ]);
+    // behind the wall, moved to where the player is in sight
+    let slot = slot_cell(ivec2(3, 2), ivec2(3, 0), &cells, 2).unwrap();
+    assert!(cells.line_of_sight(slot, ivec2(3, 0)));
+    assert!((slot - ivec2(3, 2)).abs().max_element() <= 2);
+    assert_eq!(slot_cell(ivec2(3, 2), ivec2(3, 0), &cells, 0), None);
+}
+
+#[test]
+fn dawn_is_counted_down_from_any_time() {
+    let day = DayCycleBalance {day_duration: 10., translation_duration: 2.};
+    assert_eq!(until_dawn(0., &day), 11.);
+    assert_eq!(until_dawn(10., &day), 1.);
+    // the night after the day
+    assert_eq!(until_dawn(12., &day), 23.);
+}
+
+#[test]
+fn throwers_throw_faster_and_blockers_hold() {
+    let balance = GameBalance::default();
+    assert_eq!(Role::Blocker.throw_cd(&balance), None);
+    assert_eq!(Role::Flanker.throw_cd(&balance), Some(balance.hunter.throw_cd));
+    assert!(Role::Thrower.throw_cd(&balance).unwrap() < balance.hunter.throw_cd);
+    assert!(Role::Blocker.reach(&balance.squad) < Role::Flanker.reach(&balance.squad));
+}
@@ -0,0 +1,40 @@
+use bevy::{math::vec2, prelude::*};
+use vampillage::{balance::SteeringBalance, npc::steering::{steer, Agent, SpatialHash}};
+
+fn agent(index: u32, pos: Vec2, vel: Vec2) -> Agent {
+    Agent {entity: Entity::from_raw(index), pos, vel}
+}
+
+#[test]
+fn neighbors_are_found_across_cells() {
+    let mut hash = SpatialHash::new(16.);
+    hash.insert(agent(0, vec2(15., 0.), Vec2::ZERO));
+    hash.insert(agent(1, vec2(17., 0.), Vec2::ZERO));
+    hash.insert(agent(2, vec2(-30., 0.), Vec2::ZERO));
+    let mut found: Vec<u32> = hash.neighbors(vec2(10., 0.), 10.).map(|a| a.entity.index()).collect();
+    found.sort();
+    assert_eq!(found, vec![0, 1]);
+    hash.clear();
+    assert_eq!(hash.neighbors(vec2(10., 0.), 10.).count(), 0);
+}
+
+#[test]
+fn crowds_spread_and_sidestep() {
+    let balance = SteeringBalance::default();
+    // two NPCs standing on each other make room in opposite directions
+    let (a, b) = (agent(0, Vec2::ZERO, Vec2::ZERO), agent(1, Vec2::ZERO, Vec2::ZERO));
+    let va = steer(&a, [a, b].iter(), &balance);
+    let vb = steer(&b, [a, b].iter(), &balance);
+    assert!(va.length() > 0. && va.dot(vb) < 0.);
+    assert!(va.length() <= balance.idle_speed + 0.001);
+
+    // walking head-on into someone slightly off the line, the walker turns aside without speeding up
+    let walker = agent(0, Vec2::ZERO, vec2(40., 0.));
+    let other = agent(1, vec2(19., 2.), Vec2::ZERO);
+    let steered = steer(&walker, [other].iter(), &balance);
+    assert!(steered.y < 0., "{steered} does not sidestep");
+    assert!(steered.length() <= 40.001);
+
+    // nobody around, nothing changes
+    assert_eq!(steer(&walker, [].iter(), &balance), walker.vel);
+}
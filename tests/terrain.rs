@@ -0,0 +1,82 @@
+use bevy::{asset::ron, math::ivec2, prelude::*, utils::HashSet};
+use vampillage::{balance::GameBalance, map::terrain::{merge_cells, CellRect, Terrain, TerrainTable}};
+
+fn cells(rows: &[&str]) -> HashSet<IVec2> {
+    // the first row is the top one, like in the editor
+    let height = rows.len() as i32;
+    rows.iter().enumerate().flat_map(|(y, row)| {
+        row.chars().enumerate().filter(|(_, c)| *c == '#').map(move |(x, _)| ivec2(x as i32, height - 1 - y as i32))
+    }).collect()
+}
+
+fn merged(rows: &[&str]) -> Vec<CellRect> {
+    let mut rects = merge_cells(&cells(rows));
+    rects.sort_by_key(|r| (r.bottom, r.left));
+    rects
+}
+
+fn rect(left: i32, right: i32, bottom: i32, top: i32) -> CellRect {
+    CellRect {left, right, bottom, top}
+}
+
+#[test]
+fn a_filled_block_merges_into_one_rect() {
+    assert_eq!(merged(&["###", "###"]), vec![rect(0, 2, 0, 1)]);
+    assert!(merge_cells(&HashSet::new()).is_empty());
+}
+
+#[test]
+fn plates_only_merge_when_they_line_up() {
+    assert_eq!(
+        merged(&[
+            "##.",
+            "###",
+            "###",
+        ]),
+        vec![rect(0, 2, 0, 1), rect(0, 1, 2, 2)],
+    );
+    assert_eq!(merged(&["#.#", "#.#"]), vec![rect(0, 0, 0, 1), rect(2, 2, 0, 1)]);
+}
+
+#[test]
+fn merged_rects_cover_every_cell_exactly_once() {
+    let cells = cells(&[
+        "#..##.#",
+        "##.####",
+        ".#..#..",
+        "####..#",
+    ]);
+    let mut covered = HashSet::new();
+    for r in merge_cells(&cells) {
+        for x in r.left..=r.right {
+            for y in r.bottom..=r.top {
+                assert!(covered.insert(ivec2(x, y)), "{:?} is covered twice", (x, y));
+            }
+        }
+    }
+    assert_eq!(covered, cells);
+}
+
+#[test]
+fn terrain_is_looked_up_by_layer_and_value() {
+    let swamp = Terrain {groups: 1, sensor: true, friction: 0.2, blocks_path: false};
+    let table = TerrainTable::default().with("Water", 2, swamp);
+    assert_eq!(table.get("Water", 2), Some(&swamp));
+    assert_eq!(table.get("Ground", 4), Some(&Terrain::WALL));
+    assert_eq!(table.get("Ground", 2), None);
+}
+
+#[test]
+fn the_balance_file_lists_the_default_terrain() {
+    let text = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/game.balance.ron")).unwrap();
+    let balance: GameBalance = ron::from_str(&text).unwrap();
+    let mut rows: Vec<_> = balance.terrain.0.into_iter().collect();
+    let mut defaults: Vec<_> = TerrainTable::default().0.into_iter().collect();
+    rows.sort_by(|a, b| a.0.cmp(&b.0));
+    defaults.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(rows, defaults);
+
+    let swamp: TerrainTable = ron::from_str(r#"[(layer: "Water", value: 2, collides: Wall, sensor: true, blocks_path: false)]"#).unwrap();
+    assert_eq!(swamp.get("Water", 2), Some(&Terrain {sensor: true, blocks_path: false, ..Terrain::WALL}));
+    assert_eq!(swamp.get("Ground", 4), None);
+}